@@ -110,7 +110,7 @@ impl Value {
         self.join_impl(other, ()).unwrap()
     }
     fn join_impl<C: FillContext>(self, other: Self, ctx: C) -> Result<Self, C::Error> {
-        Ok(match (self, other) {
+        Ok(match Value::unify_ints(self, other) {
             (Value::Num(a), Value::Num(b)) => a.join_impl(b, ctx)?.into(),
             (Value::Byte(a), Value::Byte(b)) => op2_bytes_retry_fill::<_, C>(
                 a,
@@ -119,6 +119,7 @@ impl Value {
                 |a, b| Ok(a.join_impl(b, ctx)?.into()),
             )?,
             (Value::Char(a), Value::Char(b)) => a.join_impl(b, ctx)?.into(),
+            (Value::Int(a), Value::Int(b)) => a.join_impl(b, ctx)?.into(),
             (Value::Byte(a), Value::Num(b)) => a.convert().join_impl(b, ctx)?.into(),
             (Value::Num(a), Value::Byte(b)) => a.join_impl(b.convert(), ctx)?.into(),
             (a, b) => a.coerce_to_functions(
@@ -130,7 +131,7 @@ impl Value {
         })
     }
     fn append<C: FillContext>(self, other: Self, ctx: C, action: &str) -> Result<Self, C::Error> {
-        Ok(match (self, other) {
+        Ok(match Value::unify_ints(self, other) {
             (Value::Num(a), Value::Num(b)) => a.append(b, ctx, action)?.into(),
             (Value::Byte(a), Value::Byte(b)) => op2_bytes_retry_fill::<_, C>(
                 a,
@@ -139,6 +140,7 @@ impl Value {
                 |a, b| Ok(a.append(b, ctx, action)?.into()),
             )?,
             (Value::Char(a), Value::Char(b)) => a.append(b, ctx, action)?.into(),
+            (Value::Int(a), Value::Int(b)) => a.append(b, ctx, action)?.into(),
             (Value::Byte(a), Value::Num(b)) => a.convert().append(b, ctx, action)?.into(),
             (Value::Num(a), Value::Byte(b)) => a.append(b.convert(), ctx, action)?.into(),
             (a, b) => a.coerce_to_functions(
@@ -270,7 +272,7 @@ impl Value {
         self.couple_impl(other, ()).unwrap()
     }
     fn couple_impl<C: FillContext>(self, other: Self, ctx: C) -> Result<Self, C::Error> {
-        Ok(match (self, other) {
+        Ok(match Value::unify_ints(self, other) {
             (Value::Num(a), Value::Num(b)) => a.couple_impl(b, ctx)?.into(),
             (Value::Byte(a), Value::Byte(b)) => op2_bytes_retry_fill::<_, C>(
                 a,
@@ -279,6 +281,7 @@ impl Value {
                 |a, b| Ok(a.couple_impl(b, ctx)?.into()),
             )?,
             (Value::Char(a), Value::Char(b)) => a.couple_impl(b, ctx)?.into(),
+            (Value::Int(a), Value::Int(b)) => a.couple_impl(b, ctx)?.into(),
             (Value::Func(a), Value::Func(b)) => a.couple_impl(b, ctx)?.into(),
             (Value::Num(a), Value::Byte(b)) => a.couple_impl(b.convert(), ctx)?.into(),
             (Value::Byte(a), Value::Num(b)) => a.convert().couple_impl(b, ctx)?.into(),
@@ -294,8 +297,10 @@ impl Value {
         match self {
             Value::Num(a) => a.uncouple(env).map(|(a, b)| (a.into(), b.into())),
             Value::Byte(a) => a.uncouple(env).map(|(a, b)| (a.into(), b.into())),
+            Value::Int(a) => a.uncouple(env).map(|(a, b)| (a.into(), b.into())),
             Value::Char(a) => a.uncouple(env).map(|(a, b)| (a.into(), b.into())),
             Value::Func(a) => a.uncouple(env).map(|(a, b)| (a.into(), b.into())),
+            Value::Mask(a) => Value::from(a.into_unpacked()).uncouple(env),
         }
     }
 }
@@ -418,12 +423,16 @@ impl<T: ArrayValue> Array<T> {
 
 impl Value {
     pub fn reshape(&mut self, shape: &Self, env: &Uiua) -> UiuaResult {
+        self.unpack();
         if let Ok(n) = shape.as_nat(env, "") {
+            env.check_array_limit(n.saturating_mul(self.flat_len()))?;
             match self {
                 Value::Num(a) => a.reshape_scalar(n),
                 Value::Byte(a) => a.reshape_scalar(n),
+                Value::Int(a) => a.reshape_scalar(n),
                 Value::Char(a) => a.reshape_scalar(n),
                 Value::Func(a) => a.reshape_scalar(n),
+                Value::Mask(_) => unreachable!("masks are unpacked"),
             }
         } else {
             let target_shape = shape.as_naturals(
@@ -432,11 +441,17 @@ impl Value {
                 or a list of natural numbers",
             )?;
             let target_shape = Shape::from(&*target_shape);
+            if self.flat_len() > 0 {
+                let target_len = (target_shape.iter()).fold(1usize, |a, &b| a.saturating_mul(b));
+                env.check_array_limit(target_len)?;
+            }
             match self {
                 Value::Num(a) => a.reshape(target_shape),
                 Value::Byte(a) => a.reshape(target_shape),
+                Value::Int(a) => a.reshape(target_shape),
                 Value::Char(a) => a.reshape(target_shape),
                 Value::Func(a) => a.reshape(target_shape),
+                Value::Mask(_) => unreachable!("masks are unpacked"),
             }
         }
         Ok(())
@@ -489,15 +504,19 @@ impl Value {
             match kept {
                 Value::Num(a) => a.scalar_keep(amount[0]).into(),
                 Value::Byte(a) => a.scalar_keep(amount[0]).into(),
+                Value::Int(a) => a.scalar_keep(amount[0]).into(),
                 Value::Char(a) => a.scalar_keep(amount[0]).into(),
                 Value::Func(a) => a.scalar_keep(amount[0]).into(),
+                Value::Mask(a) => a.into_unpacked().scalar_keep(amount[0]).into(),
             }
         } else {
             match kept {
                 Value::Num(a) => a.list_keep(&amount, env)?.into(),
                 Value::Byte(a) => a.list_keep(&amount, env)?.into(),
+                Value::Int(a) => a.list_keep(&amount, env)?.into(),
                 Value::Char(a) => a.list_keep(&amount, env)?.into(),
                 Value::Func(a) => a.list_keep(&amount, env)?.into(),
+                Value::Mask(a) => a.into_unpacked().list_keep(&amount, env)?.into(),
             }
         })
     }
@@ -644,6 +663,13 @@ impl Value {
                 }
                 (arr.shape, index_data)
             }
+            Value::Int(arr) => {
+                let mut index_data = Vec::with_capacity(arr.flat_len());
+                for n in arr.data {
+                    index_data.push(n as isize);
+                }
+                (arr.shape, index_data)
+            }
             value => {
                 return Err(env.error(format!(
                     "Index must be an array of integers, not {}s",
@@ -654,23 +680,26 @@ impl Value {
     }
     pub fn pick(self, from: Self, env: &Uiua) -> UiuaResult<Self> {
         let (index_shape, index_data) = self.into_shaped_indices(env)?;
-        Ok(match from {
+        Ok(match from.unpacked() {
             Value::Num(a) => Value::Num(a.pick_shaped(&index_shape, &index_data, env)?),
             Value::Byte(a) => op_bytes_retry_fill(
                 a,
                 |a| Ok(a.pick_shaped(&index_shape, &index_data, env)?.into()),
                 |a| Ok(a.pick_shaped(&index_shape, &index_data, env)?.into()),
             )?,
+            Value::Int(a) => Value::Int(a.pick_shaped(&index_shape, &index_data, env)?),
             Value::Char(a) => Value::Char(a.pick_shaped(&index_shape, &index_data, env)?),
             Value::Func(a) => Value::Func(a.pick_shaped(&index_shape, &index_data, env)?),
+            Value::Mask(_) => unreachable!("masks are unpacked"),
         })
     }
     pub fn unpick(self, index: Self, into: Self, env: &Uiua) -> UiuaResult<Self> {
         let index = index.as_indices(env, "Index must be an array of integers")?;
-        Ok(match (self, into) {
+        Ok(match Value::unify_ints(self, into) {
             (Value::Num(a), Value::Num(b)) => a.unpick_impl(&index, b, env)?.into(),
             (Value::Byte(a), Value::Byte(b)) => a.unpick_impl(&index, b, env)?.into(),
             (Value::Char(a), Value::Char(b)) => a.unpick_impl(&index, b, env)?.into(),
+            (Value::Int(a), Value::Int(b)) => a.unpick_impl(&index, b, env)?.into(),
             (Value::Func(a), Value::Func(b)) => a.unpick_impl(&index, b, env)?.into(),
             (Value::Num(a), Value::Byte(b)) => a.unpick_impl(&index, b.convert(), env)?.into(),
             (Value::Byte(a), Value::Num(b)) => a.convert().unpick_impl(&index, b, env)?.into(),
@@ -784,8 +813,10 @@ impl Value {
                 |a| Ok(a.take(&index, env)?.into()),
                 |a| Ok(a.take(&index, env)?.into()),
             )?,
+            Value::Int(a) => Value::Int(a.take(&index, env)?),
             Value::Char(a) => Value::Char(a.take(&index, env)?),
             Value::Func(a) => Value::Func(a.take(&index, env)?),
+            Value::Mask(a) => self.take(a.into_unpacked().into(), env)?,
         })
     }
     pub fn drop(self, from: Self, env: &Uiua) -> UiuaResult<Self> {
@@ -793,16 +824,19 @@ impl Value {
         Ok(match from {
             Value::Num(a) => Value::Num(a.drop(&index, env)?),
             Value::Byte(a) => Value::Byte(a.drop(&index, env)?),
+            Value::Int(a) => Value::Int(a.drop(&index, env)?),
             Value::Char(a) => Value::Char(a.drop(&index, env)?),
             Value::Func(a) => Value::Func(a.drop(&index, env)?),
+            Value::Mask(a) => Value::Byte(a.into_unpacked().drop(&index, env)?),
         })
     }
     pub(crate) fn untake(self, index: Self, into: Self, env: &Uiua) -> UiuaResult<Self> {
         let index = index.as_indices(env, "Index must be a list of integers")?;
-        Ok(match (self, into) {
+        Ok(match Value::unify_ints(self, into) {
             (Value::Num(a), Value::Num(b)) => Value::Num(a.untake(&index, b, env)?),
             (Value::Byte(a), Value::Byte(b)) => Value::Byte(a.untake(&index, b, env)?),
             (Value::Char(a), Value::Char(b)) => Value::Char(a.untake(&index, b, env)?),
+            (Value::Int(a), Value::Int(b)) => Value::Int(a.untake(&index, b, env)?),
             (Value::Func(a), Value::Func(b)) => Value::Func(a.untake(&index, b, env)?),
             (Value::Num(a), Value::Byte(b)) => Value::Num(a.untake(&index, b.convert(), env)?),
             (Value::Byte(a), Value::Num(b)) => Value::Num(a.convert().untake(&index, b, env)?),
@@ -817,10 +851,11 @@ impl Value {
     }
     pub(crate) fn undrop(self, index: Self, into: Self, env: &Uiua) -> UiuaResult<Self> {
         let index = index.as_indices(env, "Index must be a list of integers")?;
-        Ok(match (self, into) {
+        Ok(match Value::unify_ints(self, into) {
             (Value::Num(a), Value::Num(b)) => Value::Num(a.undrop(&index, b, env)?),
             (Value::Byte(a), Value::Byte(b)) => Value::Byte(a.undrop(&index, b, env)?),
             (Value::Char(a), Value::Char(b)) => Value::Char(a.undrop(&index, b, env)?),
+            (Value::Int(a), Value::Int(b)) => Value::Int(a.undrop(&index, b, env)?),
             (Value::Func(a), Value::Func(b)) => Value::Func(a.undrop(&index, b, env)?),
             (Value::Num(a), Value::Byte(b)) => Value::Num(a.undrop(&index, b.convert(), env)?),
             (Value::Byte(a), Value::Num(b)) => Value::Num(a.convert().undrop(&index, b, env)?),
@@ -1151,11 +1186,14 @@ impl<T: ArrayValue> Array<T> {
 impl Value {
     pub fn rotate(&self, mut rotated: Self, env: &Uiua) -> UiuaResult<Self> {
         let by = self.as_indices(env, "Rotation amount must be a list of integers")?;
+        rotated.unpack();
         match &mut rotated {
             Value::Num(a) => a.rotate(&by, env)?,
             Value::Byte(a) => a.rotate(&by, env)?,
+            Value::Int(a) => a.rotate(&by, env)?,
             Value::Char(a) => a.rotate(&by, env)?,
             Value::Func(a) => a.rotate(&by, env)?,
+            Value::Mask(_) => unreachable!("masks are unpacked"),
         }
         Ok(rotated)
     }
@@ -1216,6 +1254,11 @@ impl Value {
                     indices.push(i as isize);
                 }
             }
+            Value::Int(arr) => {
+                for &i in arr.data.iter() {
+                    indices.push(i as isize);
+                }
+            }
             v => {
                 return Err(env.error(format!(
                     "Indices must be an array of integers, but it is {}s",
@@ -1234,8 +1277,10 @@ impl Value {
                 |a| Ok(a.select_impl(indices_shape, &indices, env)?.into()),
                 |a| Ok(a.select_impl(indices_shape, &indices, env)?.into()),
             )?,
+            Value::Int(a) => a.select_impl(indices_shape, &indices, env)?.into(),
             Value::Char(a) => a.select_impl(indices_shape, &indices, env)?.into(),
             Value::Func(a) => a.select_impl(indices_shape, &indices, env)?.into(),
+            Value::Mask(a) => self.select(&a.unpacked().clone().into(), env)?,
         })
     }
     pub fn unselect(self, index: Self, into: Self, env: &Uiua) -> UiuaResult<Self> {
@@ -1245,10 +1290,11 @@ impl Value {
         if sorted_indices.windows(2).any(|win| win[0] == win[1]) {
             return Err(env.error("Cannot undo selection with duplicate indices"));
         }
-        Ok(match (self, into) {
+        Ok(match Value::unify_ints(self, into) {
             (Value::Num(a), Value::Num(b)) => a.unselect_impl(ind_shape, &ind, b, env)?.into(),
             (Value::Byte(a), Value::Byte(b)) => a.unselect_impl(ind_shape, &ind, b, env)?.into(),
             (Value::Char(a), Value::Char(b)) => a.unselect_impl(ind_shape, &ind, b, env)?.into(),
+            (Value::Int(a), Value::Int(b)) => a.unselect_impl(ind_shape, &ind, b, env)?.into(),
             (Value::Func(a), Value::Func(b)) => a.unselect_impl(ind_shape, &ind, b, env)?.into(),
             (Value::Num(a), Value::Byte(b)) => {
                 a.unselect_impl(ind_shape, &ind, b.convert(), env)?.into()
@@ -1402,8 +1448,10 @@ impl Value {
         Ok(match from {
             Value::Num(a) => a.windows(&size_spec, env)?.into(),
             Value::Byte(a) => a.windows(&size_spec, env)?.into(),
+            Value::Int(a) => a.windows(&size_spec, env)?.into(),
             Value::Char(a) => a.windows(&size_spec, env)?.into(),
             Value::Func(a) => a.windows(&size_spec, env)?.into(),
+            Value::Mask(a) => a.unpacked().windows(&size_spec, env)?.into(),
         })
     }
 }
@@ -1481,10 +1529,12 @@ impl<T: ArrayValue> Array<T> {
 
 impl Value {
     pub fn find(&self, searched: &Self, env: &Uiua) -> UiuaResult<Self> {
-        Ok(match (self, searched) {
+        let (a, b) = Value::unify_ints(self.clone(), searched.clone());
+        Ok(match (&a, &b) {
             (Value::Num(a), Value::Num(b)) => a.find(b, env)?.into(),
             (Value::Byte(a), Value::Byte(b)) => a.find(b, env)?.into(),
             (Value::Char(a), Value::Char(b)) => a.find(b, env)?.into(),
+            (Value::Int(a), Value::Int(b)) => a.find(b, env)?.into(),
             (Value::Func(a), Value::Func(b)) => a.find(b, env)?.into(),
             (Value::Num(a), Value::Byte(b)) => a.find(&b.clone().convert(), env)?.into(),
             (Value::Byte(a), Value::Num(b)) => a.clone().convert().find(b, env)?.into(),
@@ -1588,10 +1638,12 @@ impl<T: ArrayValue> Array<T> {
 
 impl Value {
     pub fn member(&self, of: &Self, env: &Uiua) -> UiuaResult<Self> {
-        Ok(match (self, of) {
+        let (a, b) = Value::unify_ints(self.clone(), of.clone());
+        Ok(match (&a, &b) {
             (Value::Num(a), Value::Num(b)) => a.member(b, env)?.into(),
             (Value::Byte(a), Value::Byte(b)) => a.member(b, env)?.into(),
             (Value::Char(a), Value::Char(b)) => a.member(b, env)?.into(),
+            (Value::Int(a), Value::Int(b)) => a.member(b, env)?.into(),
             (Value::Func(a), Value::Func(b)) => a.member(b, env)?.into(),
             (Value::Num(a), Value::Byte(b)) => a.member(b, env)?.into(),
             (Value::Byte(a), Value::Num(b)) => a.member(b, env)?.into(),
@@ -1665,10 +1717,12 @@ impl<T: ArrayValue> Array<T> {
 
 impl Value {
     pub fn index_of(&self, searched_in: &Value, env: &Uiua) -> UiuaResult<Value> {
-        Ok(match (self, searched_in) {
+        let (a, b) = Value::unify_ints(self.clone(), searched_in.clone());
+        Ok(match (&a, &b) {
             (Value::Num(a), Value::Num(b)) => a.index_of(b, env)?.into(),
             (Value::Byte(a), Value::Byte(b)) => a.index_of(b, env)?.into(),
             (Value::Char(a), Value::Char(b)) => a.index_of(b, env)?.into(),
+            (Value::Int(a), Value::Int(b)) => a.index_of(b, env)?.into(),
             (Value::Func(a), Value::Func(b)) => a.index_of(b, env)?.into(),
             (Value::Num(a), Value::Byte(b)) => a.index_of(&b.clone().convert(), env)?.into(),
             (Value::Byte(a), Value::Num(b)) => a.clone().convert().index_of(b, env)?.into(),
@@ -5,6 +5,7 @@ use std::{borrow::Cow, cell::RefCell, collections::HashMap, fmt};
 use crate::{
     check::instrs_signature,
     function::{Function, FunctionKind, Instr},
+    optimize,
     primitive::Primitive,
     value::Value,
 };
@@ -38,6 +39,9 @@ pub(crate) fn invert_instrs(instrs: &[Instr]) -> Option<Vec<Instr>> {
     if instrs.is_empty() {
         return Some(Vec::new());
     }
+    if let Some(instrs) = optimize::unfuse(instrs) {
+        return invert_instrs(&instrs);
+    }
 
     thread_local! {
         static INVERT_CACHE: RefCell<HashMap<Vec<Instr>, Option<Vec<Instr>>>> = RefCell::new(HashMap::new());
@@ -140,6 +144,9 @@ fn under_instrs(instrs: &[Instr]) -> Option<Under> {
     if instrs.is_empty() {
         return Some((Vec::new(), Vec::new()));
     }
+    if let Some(instrs) = optimize::unfuse(instrs) {
+        return under_instrs(&instrs);
+    }
 
     thread_local! {
         static UNDER_CACHE: RefCell<HashMap<Vec<Instr>, Option<Under>>> = RefCell::new(HashMap::new());
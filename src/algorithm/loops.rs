@@ -8,7 +8,7 @@ use std::{
 use tinyvec::tiny_vec;
 
 use crate::{
-    algorithm::pervade::bin_pervade_generic,
+    algorithm::{parallel, pervade::bin_pervade_generic},
     array::{Array, ArrayValue, FormatShape, Shape},
     cowslice::cowslice,
     primitive::Primitive,
@@ -24,41 +24,87 @@ fn flip<A, B, C>(f: impl Fn(A, B) -> C) -> impl Fn(B, A) -> C {
 pub fn reduce(env: &mut Uiua) -> UiuaResult {
     crate::profile_function!();
     let f = env.pop(FunctionArg(1))?;
-    let xs = env.pop(ArrayArg(1))?;
+    let xs = env.pop_packed(ArrayArg(1))?;
+    // Summing a list mask counts its set bits
+    if let (Some((Primitive::Add, _)), Value::Mask(mask)) = (f.as_flipped_primitive(), &xs) {
+        if mask.shape().len() == 1 {
+            env.push(mask.count() as f64);
+            return Ok(());
+        }
+    }
+    reduce_impl(f, xs.unpacked(), None, env)
+}
 
+/// Reduce the windows of `size` rows of an array, as if by `/f◫`, without making the array of windows
+///
+/// The windows must fit in the array.
+pub(crate) fn reduce_windows(f: Value, size: usize, xs: Value, env: &mut Uiua) -> UiuaResult {
+    crate::profile_function!();
+    reduce_impl(f, xs, Some(size), env)
+}
+
+fn reduce_impl(f: Value, xs: Value, window: Option<usize>, env: &mut Uiua) -> UiuaResult {
+    let threads = env.thread_count();
     match (f.as_flipped_primitive(), xs) {
         (Some((prim, flipped)), Value::Num(nums)) => env.push(match prim {
-            Primitive::Add => fast_reduce(nums, 0.0, Add::add),
-            Primitive::Sub if flipped => fast_reduce(nums, 0.0, Sub::sub),
-            Primitive::Sub => fast_reduce(nums, 0.0, flip(Sub::sub)),
-            Primitive::Mul => fast_reduce(nums, 1.0, Mul::mul),
-            Primitive::Div if flipped => fast_reduce(nums, 1.0, Div::div),
-            Primitive::Div => fast_reduce(nums, 1.0, flip(Div::div)),
-            Primitive::Max => fast_reduce(nums, f64::NEG_INFINITY, f64::max),
-            Primitive::Min => fast_reduce(nums, f64::INFINITY, f64::min),
-            _ => return generic_fold(f, Value::Num(nums), None, env),
+            Primitive::Add => fast_reduce(nums, window, threads, 0.0, Add::add),
+            Primitive::Sub if flipped => fast_reduce(nums, window, threads, 0.0, Sub::sub),
+            Primitive::Sub => fast_reduce(nums, window, threads, 0.0, flip(Sub::sub)),
+            Primitive::Mul => fast_reduce(nums, window, threads, 1.0, Mul::mul),
+            Primitive::Div if flipped => fast_reduce(nums, window, threads, 1.0, Div::div),
+            Primitive::Div => fast_reduce(nums, window, threads, 1.0, flip(Div::div)),
+            Primitive::Max => fast_reduce(nums, window, threads, f64::NEG_INFINITY, f64::max),
+            Primitive::Min => fast_reduce(nums, window, threads, f64::INFINITY, f64::min),
+            _ => return generic_fold(f, reduced_rows(Value::Num(nums), window), None, env),
         }),
         (Some((prim, flipped)), Value::Byte(bytes)) => env.push(match prim {
-            Primitive::Add => fast_reduce(bytes, 0.0, |a, b| a + f64::from(b)),
-            Primitive::Sub if flipped => fast_reduce(bytes, 0.0, |a, b| a - f64::from(b)),
-            Primitive::Sub => fast_reduce(bytes, 0.0, |a, b| f64::from(b) - a),
-            Primitive::Mul => fast_reduce(bytes, 1.0, |a, b| a * f64::from(b)),
-            Primitive::Div if flipped => fast_reduce(bytes, 1.0, |a, b| a / f64::from(b)),
-            Primitive::Div => fast_reduce(bytes, 1.0, |a, b| f64::from(b) / a),
-            Primitive::Max => fast_reduce(bytes, f64::NEG_INFINITY, |a, b| a.max(f64::from(b))),
-            Primitive::Min => fast_reduce(bytes, f64::INFINITY, |a, b| a.min(f64::from(b))),
-            _ => return generic_fold(f, Value::Byte(bytes), None, env),
+            Primitive::Add => fast_reduce(bytes, window, threads, 0.0, |a, b| a + f64::from(b)),
+            Primitive::Sub if flipped => {
+                fast_reduce(bytes, window, threads, 0.0, |a, b| a - f64::from(b))
+            }
+            Primitive::Sub => fast_reduce(bytes, window, threads, 0.0, |a, b| f64::from(b) - a),
+            Primitive::Mul => fast_reduce(bytes, window, threads, 1.0, |a, b| a * f64::from(b)),
+            Primitive::Div if flipped => {
+                fast_reduce(bytes, window, threads, 1.0, |a, b| a / f64::from(b))
+            }
+            Primitive::Div => fast_reduce(bytes, window, threads, 1.0, |a, b| f64::from(b) / a),
+            Primitive::Max => fast_reduce(bytes, window, threads, f64::NEG_INFINITY, |a, b| {
+                a.max(f64::from(b))
+            }),
+            Primitive::Min => fast_reduce(bytes, window, threads, f64::INFINITY, |a, b| {
+                a.min(f64::from(b))
+            }),
+            _ => return generic_fold(f, reduced_rows(Value::Byte(bytes), window), None, env),
         }),
-        (_, xs) => generic_fold(f, xs, None, env)?,
+        (_, xs) => generic_fold(f, reduced_rows(xs, window), None, env)?,
     }
     Ok(())
 }
 
+/// The rows that are reduced, which are either the rows of an array or its windows
+fn reduced_rows(xs: Value, window: Option<usize>) -> Box<dyn Iterator<Item = Value>> {
+    match window {
+        Some(size) => xs.into_row_windows(size),
+        None => xs.into_rows(),
+    }
+}
+
 pub fn fast_reduce<T: ArrayValue + Into<R>, R: ArrayValue>(
     mut arr: Array<T>,
+    window: Option<usize>,
+    threads: usize,
     identity: R,
-    f: impl Fn(R, T) -> R,
+    f: impl Fn(R, T) -> R + Sync,
 ) -> Array<R> {
+    if let Some(size) = window {
+        // Each window starts one row after the previous one
+        let row_len = arr.row_len();
+        let window_len = size * row_len;
+        let count = arr.row_count() - size + 1;
+        let new_data = fold_columns(&arr.data, window_len, row_len, count, threads, f);
+        arr.shape[0] = size;
+        return Array::new(arr.shape, new_data);
+    }
     match arr.shape.len() {
         0 => Array::new(
             tiny_vec![],
@@ -83,27 +129,48 @@ pub fn fast_reduce<T: ArrayValue + Into<R>, R: ArrayValue>(
                 let data = cowslice![identity; row_len];
                 return Array::new(arr.shape, data);
             }
-            let mut new_data: Vec<R> = arr.data[..row_len]
-                .iter()
-                .cloned()
-                .map(Into::into)
-                .collect();
-            for i in 1..row_count {
-                let start = i * row_len;
-                for j in 0..row_len {
-                    new_data[j] = f(new_data[j].clone(), arr.data[start + j].clone());
-                }
-            }
+            let new_data = fold_columns(&arr.data, row_len, row_len, row_count, threads, f);
             arr.shape.remove(0);
             Array::new(arr.shape, new_data)
         }
     }
 }
 
-fn generic_fold(f: Value, xs: Value, init: Option<Value>, env: &mut Uiua) -> UiuaResult {
+/// Fold `count` slices of `len` elements, each starting `stride` after the last, together
+/// element-wise
+///
+/// The columns are split across threads, and each is folded in order, so the result does not
+/// depend on the number of threads.
+fn fold_columns<T: ArrayValue + Into<R>, R: ArrayValue>(
+    data: &[T],
+    len: usize,
+    stride: usize,
+    count: usize,
+    threads: usize,
+    f: impl Fn(R, T) -> R + Sync,
+) -> Vec<R> {
+    let mut new_data: Vec<R> = data[..len].iter().cloned().map(Into::into).collect();
+    let threads = parallel::threads(threads, len * count);
+    parallel::for_each_part(&mut new_data, 1, threads, |start, part| {
+        for i in 1..count {
+            let row = &data[i * stride + start..][..part.len()];
+            for (acc, x) in part.iter_mut().zip(row) {
+                *acc = f(acc.clone(), x.clone());
+            }
+        }
+    });
+    new_data
+}
+
+fn generic_fold(
+    f: Value,
+    rows: Box<dyn Iterator<Item = Value>>,
+    init: Option<Value>,
+    env: &mut Uiua,
+) -> UiuaResult {
     match f.signature().args {
         0 | 1 => {
-            let mut rows = init.into_iter().chain(xs.into_rows());
+            let mut rows = init.into_iter().chain(rows);
             while let Some(row) = rows.next() {
                 env.push(row);
                 if env.call_catch_break(f.clone())? {
@@ -119,7 +186,7 @@ fn generic_fold(f: Value, xs: Value, init: Option<Value>, env: &mut Uiua) -> Uiu
             }
         }
         2 => {
-            let mut rows = xs.into_rows();
+            let mut rows = rows;
             let mut acc = init
                 .or_else(|| rows.next())
                 .ok_or_else(|| env.error("Cannot reduce empty array"))?;
@@ -149,7 +216,7 @@ pub fn fold(env: &mut Uiua) -> UiuaResult {
     let f = env.pop(FunctionArg(1))?;
     let acc = env.pop(ArrayArg(1))?;
     let xs = env.pop(ArrayArg(2))?;
-    generic_fold(f, xs, Some(acc), env)
+    generic_fold(f, xs.into_rows(), Some(acc), env)
 }
 
 pub fn scan(env: &mut Uiua) -> UiuaResult {
@@ -301,20 +368,34 @@ pub fn each(env: &mut Uiua) -> UiuaResult {
 }
 
 fn each1_1(f: Value, xs: Value, env: &mut Uiua) -> UiuaResult {
-    let mut new_values = Vec::with_capacity(xs.flat_len());
+    let threads = call_threads(&f, &[&xs], env);
     let mut new_shape = Shape::from(xs.shape());
-    let mut old_values = xs.into_flat_values();
-    for val in old_values.by_ref() {
-        env.push(val);
-        let broke = env.call_catch_break(f.clone())?;
-        new_values.push(env.pop("each's function result")?);
-        if broke {
-            for row in old_values {
-                new_values.push(row);
+    let new_values = if threads > 1 {
+        let values = xs.into_flat_values().collect();
+        call_parallel(
+            &f,
+            values,
+            threads,
+            Uiua::push,
+            "each's function result",
+            env,
+        )?
+    } else {
+        let mut new_values = Vec::with_capacity(xs.flat_len());
+        let mut old_values = xs.into_flat_values();
+        for val in old_values.by_ref() {
+            env.push(val);
+            let broke = env.call_catch_break(f.clone())?;
+            new_values.push(env.pop("each's function result")?);
+            if broke {
+                for row in old_values {
+                    new_values.push(row);
+                }
+                break;
             }
-            break;
         }
-    }
+        new_values
+    };
     let mut eached = Value::from_row_values(new_values, env)?;
     new_shape.extend_from_slice(&eached.shape()[1..]);
     *eached.shape_mut() = new_shape;
@@ -446,9 +527,10 @@ pub fn rows(env: &mut Uiua) -> UiuaResult {
         1 => {
             let xs = env.pop(ArrayArg(1))?;
             if output {
-                rows1_1(f, xs, env)
+                let threads = call_threads(&f, &[&xs], env);
+                rows1_1(f, xs.into_rows(), threads, env)
             } else {
-                rows1_0(f, xs, env)
+                rows1_0(f, xs.into_rows(), env)
             }
         }
         2 => {
@@ -474,9 +556,33 @@ pub fn rows(env: &mut Uiua) -> UiuaResult {
     }
 }
 
-fn rows1_1(f: Value, xs: Value, env: &mut Uiua) -> UiuaResult {
-    let mut new_rows = Vec::with_capacity(xs.row_count());
-    let mut old_rows = xs.into_rows();
+/// Call a function on the windows of `size` rows of an array, as if by `≡f◫`, without making the array of windows
+///
+/// The windows must fit in the array, and the function must take 1 argument.
+pub(crate) fn rows_windows(f: Value, size: usize, xs: Value, env: &mut Uiua) -> UiuaResult {
+    crate::profile_function!();
+    if f.signature().outputs == 0 {
+        rows1_0(f, xs.into_row_windows(size), env)
+    } else {
+        let threads = call_threads(&f, &[&xs], env);
+        rows1_1(f, xs.into_row_windows(size), threads, env)
+    }
+}
+
+fn rows1_1(
+    f: Value,
+    mut old_rows: Box<dyn Iterator<Item = Value>>,
+    threads: usize,
+    env: &mut Uiua,
+) -> UiuaResult {
+    if threads > 1 {
+        let rows = old_rows.collect();
+        let new_rows = call_parallel(&f, rows, threads, Uiua::push, "rows' function result", env)?;
+        let res = Value::from_row_values(new_rows, env)?;
+        env.push(res);
+        return Ok(());
+    }
+    let mut new_rows = Vec::with_capacity(old_rows.size_hint().0);
     for row in old_rows.by_ref() {
         env.push(row);
         let broke = env.call_catch_break(f.clone())?;
@@ -493,8 +599,58 @@ fn rows1_1(f: Value, xs: Value, env: &mut Uiua) -> UiuaResult {
     Ok(())
 }
 
-fn rows1_0(f: Value, xs: Value, env: &mut Uiua) -> UiuaResult {
-    for row in xs.into_rows() {
+/// The number of threads to split calls to a function on some arguments across
+///
+/// Calls are only split if the function is pure and none of the arguments are functions,
+/// which could be impure when called.
+fn call_threads(f: &Value, args: &[&Value], env: &Uiua) -> usize {
+    let work = args.iter().map(|arg| arg.flat_len()).sum();
+    let threads = parallel::threads(env.thread_count(), work);
+    if threads == 1 || args.iter().any(|arg| matches!(arg, Value::Func(_))) {
+        return 1;
+    }
+    match f {
+        Value::Func(fs) if !fs.data.iter().all(|f| f.is_pure()) => 1,
+        _ => threads,
+    }
+}
+
+/// Call a pure function on each of some groups of arguments, splitting the calls across threads
+///
+/// The results are in order, and an error is that of the first call that fails, just as if
+/// the calls were made one at a time.
+fn call_parallel<A: Send>(
+    f: &Value,
+    mut args: Vec<A>,
+    threads: usize,
+    push: impl Fn(&mut Uiua, A) + Sync,
+    result_name: &'static str,
+    env: &Uiua,
+) -> UiuaResult<Vec<Value>> {
+    let len = args.len();
+    let mut parts = Vec::new();
+    for range in parallel::ranges(len, 1, threads).into_iter().rev() {
+        parts.push((env.fork(), args.split_off(range.start)));
+    }
+    parts.reverse();
+    let results = parallel::map(parts, |(mut env, args)| -> UiuaResult<_> {
+        let mut values = Vec::with_capacity(args.len());
+        for arg in args {
+            push(&mut env, arg);
+            env.call(f.clone())?;
+            values.push(env.pop(result_name)?);
+        }
+        Ok(values)
+    });
+    let mut values = Vec::with_capacity(len);
+    for res in results {
+        values.extend(res?);
+    }
+    Ok(values)
+}
+
+fn rows1_0(f: Value, rows: Box<dyn Iterator<Item = Value>>, env: &mut Uiua) -> UiuaResult {
+    for row in rows {
         env.push(row);
         let broke = env.call_catch_break(f.clone())?;
         if broke {
@@ -512,14 +668,24 @@ fn rows2_1(f: Value, xs: Value, ys: Value, env: &mut Uiua) -> UiuaResult {
             ys.row_count()
         )));
     }
+    let threads = call_threads(&f, &[&xs, &ys], env);
     let mut new_rows = Vec::with_capacity(xs.row_count());
     let x_rows = xs.into_rows();
     let y_rows = ys.into_rows();
-    for (x, y) in x_rows.into_iter().zip(y_rows) {
-        env.push(y);
-        env.push(x);
-        env.call_error_on_break(f.clone(), "break is not allowed in multi-argument rows")?;
-        new_rows.push(env.pop("rows's function result")?);
+    if threads > 1 {
+        let rows = x_rows.zip(y_rows).collect();
+        let push = |env: &mut Uiua, (x, y)| {
+            env.push(y);
+            env.push(x);
+        };
+        new_rows = call_parallel(&f, rows, threads, push, "rows's function result", env)?;
+    } else {
+        for (x, y) in x_rows.into_iter().zip(y_rows) {
+            env.push(y);
+            env.push(x);
+            env.call_error_on_break(f.clone(), "break is not allowed in multi-argument rows")?;
+            new_rows.push(env.pop("rows's function result")?);
+        }
     }
     env.push(Value::from_row_values(new_rows, env)?);
     Ok(())
@@ -596,6 +762,8 @@ pub fn table(env: &mut Uiua) -> UiuaResult {
     let f = env.pop(FunctionArg(1))?;
     let xs = env.pop(ArrayArg(1))?;
     let ys = env.pop(ArrayArg(2))?;
+    env.check_array_limit(xs.flat_len().saturating_mul(ys.flat_len()))?;
+    let threads = env.thread_count();
     match (f.as_flipped_primitive(), xs, ys) {
         (Some((prim, flipped)), Value::Num(xs), Value::Num(ys)) => {
             if let Err((xs, ys)) = table_nums(prim, flipped, xs, ys, env) {
@@ -615,28 +783,44 @@ pub fn table(env: &mut Uiua) -> UiuaResult {
             }
         }
         (Some((prim, flipped)), Value::Byte(xs), Value::Byte(ys)) => match prim {
-            Primitive::Eq => env.push(fast_table(xs, ys, bin_bool(|x, y| x == y))),
-            Primitive::Ne => env.push(fast_table(xs, ys, bin_bool(|x, y| x != y))),
-            Primitive::Lt if flipped => env.push(fast_table(xs, ys, bin_bool(|x, y| x < y))),
-            Primitive::Lt => env.push(fast_table(xs, ys, bin_bool(|x, y| y < x))),
-            Primitive::Gt if flipped => env.push(fast_table(xs, ys, bin_bool(|x, y| x > y))),
-            Primitive::Gt => env.push(fast_table(xs, ys, bin_bool(|x, y| y > x))),
-            Primitive::Le if flipped => env.push(fast_table(xs, ys, bin_bool(|x, y| x <= y))),
-            Primitive::Le => env.push(fast_table(xs, ys, bin_bool(|x, y| y <= x))),
-            Primitive::Ge if flipped => env.push(fast_table(xs, ys, bin_bool(|x, y| x >= y))),
-            Primitive::Ge => env.push(fast_table(xs, ys, bin_bool(|x, y| y >= x))),
-            Primitive::Add => env.push(fast_table(xs, ys, |a, b| f64::from(a) + f64::from(b))),
-            Primitive::Sub if flipped => {
-                env.push(fast_table(xs, ys, |a, b| f64::from(a) - f64::from(b)))
+            Primitive::Eq => env.push(fast_table(xs, ys, threads, bin_bool(|x, y| x == y))),
+            Primitive::Ne => env.push(fast_table(xs, ys, threads, bin_bool(|x, y| x != y))),
+            Primitive::Lt if flipped => {
+                env.push(fast_table(xs, ys, threads, bin_bool(|x, y| x < y)))
             }
-            Primitive::Sub => env.push(fast_table(xs, ys, |a, b| f64::from(b) - f64::from(a))),
-            Primitive::Mul => env.push(fast_table(xs, ys, |a, b| f64::from(a) * f64::from(b))),
-            Primitive::Div if flipped => {
-                env.push(fast_table(xs, ys, |a, b| f64::from(a) / f64::from(b)))
+            Primitive::Lt => env.push(fast_table(xs, ys, threads, bin_bool(|x, y| y < x))),
+            Primitive::Gt if flipped => {
+                env.push(fast_table(xs, ys, threads, bin_bool(|x, y| x > y)))
+            }
+            Primitive::Gt => env.push(fast_table(xs, ys, threads, bin_bool(|x, y| y > x))),
+            Primitive::Le if flipped => {
+                env.push(fast_table(xs, ys, threads, bin_bool(|x, y| x <= y)))
             }
-            Primitive::Div => env.push(fast_table(xs, ys, |a, b| f64::from(b) / f64::from(a))),
-            Primitive::Min => env.push(fast_table(xs, ys, u8::min)),
-            Primitive::Max => env.push(fast_table(xs, ys, u8::max)),
+            Primitive::Le => env.push(fast_table(xs, ys, threads, bin_bool(|x, y| y <= x))),
+            Primitive::Ge if flipped => {
+                env.push(fast_table(xs, ys, threads, bin_bool(|x, y| x >= y)))
+            }
+            Primitive::Ge => env.push(fast_table(xs, ys, threads, bin_bool(|x, y| y >= x))),
+            Primitive::Add => env.push(fast_table(xs, ys, threads, |a, b| {
+                f64::from(a) + f64::from(b)
+            })),
+            Primitive::Sub if flipped => env.push(fast_table(xs, ys, threads, |a, b| {
+                f64::from(a) - f64::from(b)
+            })),
+            Primitive::Sub => env.push(fast_table(xs, ys, threads, |a, b| {
+                f64::from(b) - f64::from(a)
+            })),
+            Primitive::Mul => env.push(fast_table(xs, ys, threads, |a, b| {
+                f64::from(a) * f64::from(b)
+            })),
+            Primitive::Div if flipped => env.push(fast_table(xs, ys, threads, |a, b| {
+                f64::from(a) / f64::from(b)
+            })),
+            Primitive::Div => env.push(fast_table(xs, ys, threads, |a, b| {
+                f64::from(b) / f64::from(a)
+            })),
+            Primitive::Min => env.push(fast_table(xs, ys, threads, u8::min)),
+            Primitive::Max => env.push(fast_table(xs, ys, threads, u8::max)),
             Primitive::Join | Primitive::Couple => env.push(fast_table_join_or_couple(xs, ys)),
             _ => generic_table(f, Value::Byte(xs), Value::Byte(ys), env)?,
         },
@@ -653,41 +837,51 @@ fn table_nums(
     ys: Array<f64>,
     env: &mut Uiua,
 ) -> Result<(), (Array<f64>, Array<f64>)> {
+    let threads = env.thread_count();
     match prim {
-        Primitive::Eq => env.push(fast_table(xs, ys, bin_bool(|x, y| x == y))),
-        Primitive::Ne => env.push(fast_table(xs, ys, bin_bool(|x, y| x != y))),
-        Primitive::Lt if flipped => env.push(fast_table(xs, ys, bin_bool(|x, y| x < y))),
-        Primitive::Lt => env.push(fast_table(xs, ys, bin_bool(|x, y| y < x))),
-        Primitive::Gt if flipped => env.push(fast_table(xs, ys, bin_bool(|x, y| x > y))),
-        Primitive::Gt => env.push(fast_table(xs, ys, bin_bool(|x, y| y > x))),
-        Primitive::Le if flipped => env.push(fast_table(xs, ys, bin_bool(|x, y| x <= y))),
-        Primitive::Le => env.push(fast_table(xs, ys, bin_bool(|x, y| y <= x))),
-        Primitive::Ge if flipped => env.push(fast_table(xs, ys, bin_bool(|x, y| x >= y))),
-        Primitive::Ge => env.push(fast_table(xs, ys, bin_bool(|x, y| y >= x))),
-        Primitive::Add => env.push(fast_table(xs, ys, Add::add)),
-        Primitive::Sub if flipped => env.push(fast_table(xs, ys, Sub::sub)),
-        Primitive::Sub => env.push(fast_table(xs, ys, flip(Sub::sub))),
-        Primitive::Mul => env.push(fast_table(xs, ys, Mul::mul)),
-        Primitive::Div if flipped => env.push(fast_table(xs, ys, Div::div)),
-        Primitive::Div => env.push(fast_table(xs, ys, flip(Div::div))),
-        Primitive::Min => env.push(fast_table(xs, ys, f64::min)),
-        Primitive::Max => env.push(fast_table(xs, ys, f64::max)),
+        Primitive::Eq => env.push(fast_table(xs, ys, threads, bin_bool(|x, y| x == y))),
+        Primitive::Ne => env.push(fast_table(xs, ys, threads, bin_bool(|x, y| x != y))),
+        Primitive::Lt if flipped => env.push(fast_table(xs, ys, threads, bin_bool(|x, y| x < y))),
+        Primitive::Lt => env.push(fast_table(xs, ys, threads, bin_bool(|x, y| y < x))),
+        Primitive::Gt if flipped => env.push(fast_table(xs, ys, threads, bin_bool(|x, y| x > y))),
+        Primitive::Gt => env.push(fast_table(xs, ys, threads, bin_bool(|x, y| y > x))),
+        Primitive::Le if flipped => env.push(fast_table(xs, ys, threads, bin_bool(|x, y| x <= y))),
+        Primitive::Le => env.push(fast_table(xs, ys, threads, bin_bool(|x, y| y <= x))),
+        Primitive::Ge if flipped => env.push(fast_table(xs, ys, threads, bin_bool(|x, y| x >= y))),
+        Primitive::Ge => env.push(fast_table(xs, ys, threads, bin_bool(|x, y| y >= x))),
+        Primitive::Add => env.push(fast_table(xs, ys, threads, Add::add)),
+        Primitive::Sub if flipped => env.push(fast_table(xs, ys, threads, Sub::sub)),
+        Primitive::Sub => env.push(fast_table(xs, ys, threads, flip(Sub::sub))),
+        Primitive::Mul => env.push(fast_table(xs, ys, threads, Mul::mul)),
+        Primitive::Div if flipped => env.push(fast_table(xs, ys, threads, Div::div)),
+        Primitive::Div => env.push(fast_table(xs, ys, threads, flip(Div::div))),
+        Primitive::Min => env.push(fast_table(xs, ys, threads, f64::min)),
+        Primitive::Max => env.push(fast_table(xs, ys, threads, f64::max)),
         Primitive::Join | Primitive::Couple => env.push(fast_table_join_or_couple(xs, ys)),
         _ => return Err((xs, ys)),
     }
     Ok(())
 }
 
-fn fast_table<A: ArrayValue, B: ArrayValue, C: ArrayValue>(
+/// Table two arrays with a function, splitting the rows of the result across threads
+fn fast_table<A: ArrayValue, B: ArrayValue, C: ArrayValue + Default>(
     a: Array<A>,
     b: Array<B>,
-    f: impl Fn(A, B) -> C,
+    threads: usize,
+    f: impl Fn(A, B) -> C + Sync,
 ) -> Array<C> {
-    let mut new_data = Vec::with_capacity(a.data.len() * b.data.len());
-    for x in a.data {
-        for y in b.data.iter().cloned() {
-            new_data.push(f(x.clone(), y));
-        }
+    let mut new_data = vec![C::default(); a.data.len() * b.data.len()];
+    if !new_data.is_empty() {
+        let row_len = b.data.len();
+        let threads = parallel::threads(threads, new_data.len());
+        parallel::for_each_part(&mut new_data, row_len, threads, |start, part| {
+            let xs = &a.data[start / row_len..];
+            for (row, x) in part.chunks_mut(row_len).zip(xs) {
+                for (z, y) in row.iter_mut().zip(b.data.iter()) {
+                    *z = f(x.clone(), y.clone());
+                }
+            }
+        });
     }
     let mut new_shape = a.shape;
     new_shape.extend_from_slice(&b.shape);
@@ -812,7 +1006,10 @@ pub fn level(env: &mut Uiua) -> UiuaResult {
             }
             let rank = match n {
                 Some(0) => return each1_1(f, xs, env),
-                Some(-1) => return rows1_1(f, xs, env),
+                Some(-1) => {
+                    let threads = call_threads(&f, &[&xs], env);
+                    return rows1_1(f, xs.into_rows(), threads, env);
+                }
                 None => {
                     env.push(xs);
                     return env.call(f);
@@ -1051,6 +1248,10 @@ impl Value {
                 .partition_groups(markers, env)?
                 .map(Into::into)
                 .collect(),
+            Value::Int(arr) => arr
+                .partition_groups(markers, env)?
+                .map(Into::into)
+                .collect(),
             Value::Char(arr) => arr
                 .partition_groups(markers, env)?
                 .map(Into::into)
@@ -1059,6 +1260,10 @@ impl Value {
                 .partition_groups(markers, env)?
                 .map(Into::into)
                 .collect(),
+            Value::Mask(mask) => (mask.unpacked())
+                .partition_groups(markers, env)?
+                .map(Into::into)
+                .collect(),
         })
     }
 }
@@ -1109,8 +1314,12 @@ impl Value {
         Ok(match self {
             Value::Num(arr) => arr.group_groups(indices, env)?.map(Into::into).collect(),
             Value::Byte(arr) => arr.group_groups(indices, env)?.map(Into::into).collect(),
+            Value::Int(arr) => arr.group_groups(indices, env)?.map(Into::into).collect(),
             Value::Char(arr) => arr.group_groups(indices, env)?.map(Into::into).collect(),
             Value::Func(arr) => arr.group_groups(indices, env)?.map(Into::into).collect(),
+            Value::Mask(mask) => (mask.unpacked().group_groups(indices, env)?)
+                .map(Into::into)
+                .collect(),
         })
     }
 }
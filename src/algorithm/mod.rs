@@ -12,7 +12,9 @@ pub mod fork;
 pub(crate) mod invert;
 pub mod loops;
 mod monadic;
+pub mod parallel;
 pub mod pervade;
+pub mod simd;
 
 fn max_shape(a: &[usize], b: &[usize]) -> Shape {
     let shape_len = a.len().max(b.len());
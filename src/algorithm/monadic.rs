@@ -9,7 +9,7 @@ use std::{
 
 use tinyvec::tiny_vec;
 
-use crate::{array::*, value::Value, Uiua, UiuaResult};
+use crate::{algorithm::parallel, array::*, value::Value, Uiua, UiuaResult};
 
 impl Value {
     pub fn deshape(&mut self) {
@@ -18,6 +18,7 @@ impl Value {
             Array::deshape,
             Array::deshape,
             Array::deshape,
+            Array::deshape,
         )
     }
     pub fn parse_num(&self, env: &Uiua) -> UiuaResult<Self> {
@@ -72,6 +73,7 @@ fn range(shape: &[usize], env: &Uiua) -> UiuaResult<Vec<f64>> {
         }
         len = new;
     }
+    env.check_array_limit(len)?;
     let mut data: Vec<f64> = Vec::with_capacity(len);
     let mut curr = vec![0; shape.len()];
     loop {
@@ -101,6 +103,7 @@ impl Value {
             |a| a.first(env).map(Into::into),
             |a| a.first(env).map(Into::into),
             |a| a.first(env).map(Into::into),
+            |a| a.first(env).map(Into::into),
         )
     }
     pub fn last(self, env: &Uiua) -> UiuaResult<Self> {
@@ -109,6 +112,7 @@ impl Value {
             |a| a.last(env).map(Into::into),
             |a| a.last(env).map(Into::into),
             |a| a.last(env).map(Into::into),
+            |a| a.last(env).map(Into::into),
         )
     }
 }
@@ -146,6 +150,7 @@ impl Value {
             Array::reverse,
             Array::reverse,
             Array::reverse,
+            Array::reverse,
         )
     }
 }
@@ -176,6 +181,7 @@ impl Value {
             Array::transpose,
             Array::transpose,
             Array::transpose,
+            Array::transpose,
         )
     }
     pub fn inv_transpose(&mut self) {
@@ -184,6 +190,7 @@ impl Value {
             Array::inv_transpose,
             Array::inv_transpose,
             Array::inv_transpose,
+            Array::inv_transpose,
         )
     }
 }
@@ -233,8 +240,15 @@ impl<T: ArrayValue> Array<T> {
 
 impl Value {
     pub fn grade(&self, env: &Uiua) -> UiuaResult<Self> {
-        self.generic_ref_env(Array::grade, Array::grade, Array::grade, Array::grade, env)
-            .map(Self::from_iter)
+        self.generic_ref_env(
+            Array::grade,
+            Array::grade,
+            Array::grade,
+            Array::grade,
+            Array::grade,
+            env,
+        )
+        .map(Self::from_iter)
     }
     pub fn classify(&self, env: &Uiua) -> UiuaResult<Self> {
         self.generic_ref_env(
@@ -242,6 +256,7 @@ impl Value {
             Array::classify,
             Array::classify,
             Array::classify,
+            Array::classify,
             env,
         )
         .map(Self::from_iter)
@@ -252,6 +267,7 @@ impl Value {
             Array::deduplicate,
             Array::deduplicate,
             Array::deduplicate,
+            Array::deduplicate,
         )
     }
 }
@@ -265,7 +281,8 @@ impl<T: ArrayValue> Array<T> {
             return Ok(Vec::new());
         }
         let mut indices = (0..self.row_count()).collect::<Vec<_>>();
-        indices.sort_by(|&a, &b| {
+        let threads = parallel::threads(env.thread_count(), self.flat_len());
+        parallel::sort_by(&mut indices, threads, |&a, &b| {
             self.row_slice(a)
                 .iter()
                 .zip(self.row_slice(b))
@@ -347,15 +364,17 @@ impl Value {
 impl Value {
     pub fn bits(&self, env: &Uiua) -> UiuaResult<Array<u8>> {
         match self {
-            Value::Byte(n) => n.convert_ref().bits(env),
+            Value::Byte(n) => n.convert_ref::<i64>().bits(env),
             Value::Num(n) => n.bits(env),
+            Value::Int(n) => n.bits(env),
             _ => Err(env.error("Argument to bits must be an array of natural numbers")),
         }
     }
-    pub fn inverse_bits(&self, env: &Uiua) -> UiuaResult<Array<f64>> {
+    pub fn inverse_bits(&self, env: &Uiua) -> UiuaResult<Value> {
         match self {
             Value::Byte(n) => n.inverse_bits(env),
             Value::Num(n) => n.convert_ref_with(|n| n as u8).inverse_bits(env),
+            Value::Int(n) => n.convert_ref_with(|n| n as u8).inverse_bits(env),
             _ => Err(env.error("Argument to inverse_bits must be an array of naturals")),
         }
     }
@@ -370,35 +389,49 @@ impl Array<f64> {
             }
             nats.push(n as u128);
         }
-        let mut max = if let Some(max) = nats.iter().max() {
-            *max
-        } else {
-            let mut shape = self.shape.clone();
-            shape.push(0);
-            return Ok(Array::new(shape, Vec::new()));
-        };
-        let mut max_bits = 0;
-        while max != 0 {
-            max_bits += 1;
-            max >>= 1;
-        }
-        let mut new_data = Vec::with_capacity(self.data.len() * max_bits);
-        // Big endian
-        for n in nats {
-            for i in 0..max_bits {
-                new_data.push(u8::from(n & (1 << i) != 0));
+        Ok(nats_bits(self.shape.clone(), nats))
+    }
+}
+
+impl Array<i64> {
+    pub fn bits(&self, env: &Uiua) -> UiuaResult<Array<u8>> {
+        let mut nats = Vec::new();
+        for &n in &self.data {
+            if n < 0 {
+                return Err(env.error("Array must be a list of naturals"));
             }
+            nats.push(n as u128);
         }
-        let mut shape = self.shape.clone();
-        shape.push(max_bits);
-        let arr = Array::new(shape, new_data);
-        arr.validate_shape();
-        Ok(arr)
+        Ok(nats_bits(self.shape.clone(), nats))
     }
 }
 
+fn nats_bits(mut shape: Shape, nats: Vec<u128>) -> Array<u8> {
+    let Some(mut max) = nats.iter().max().copied() else {
+        shape.push(0);
+        return Array::new(shape, Vec::new());
+    };
+    let mut max_bits = 0;
+    while max != 0 {
+        max_bits += 1;
+        max >>= 1;
+    }
+    let mut new_data = Vec::with_capacity(nats.len() * max_bits);
+    // Big endian
+    for n in nats {
+        for i in 0..max_bits {
+            new_data.push(u8::from(n & (1 << i) != 0));
+        }
+    }
+    shape.push(max_bits);
+    let arr = Array::new(shape, new_data);
+    arr.validate_shape();
+    arr
+}
+
 impl Array<u8> {
-    pub fn inverse_bits(&self, env: &Uiua) -> UiuaResult<Array<f64>> {
+    /// Decode bits into numbers, or into integers if any would lose precision as a number
+    pub fn inverse_bits(&self, env: &Uiua) -> UiuaResult<Value> {
         let mut bools = Vec::with_capacity(self.data.len());
         for &b in &self.data {
             if b > 1 {
@@ -407,23 +440,41 @@ impl Array<u8> {
             bools.push(b != 0);
         }
         if self.rank() == 0 {
-            return Ok(Array::from(bools[0] as u8 as f64));
+            return Ok(Array::from(bools[0] as u8 as f64).into());
         }
         let mut shape = self.shape.clone();
         let bit_string_len = shape.pop().unwrap();
-        let mut new_data = Vec::with_capacity(self.data.len() / bit_string_len);
-        // Big endian
-        for bits in bools.chunks_exact(bit_string_len) {
-            let mut n = 0;
-            for (i, b) in bits.iter().enumerate() {
-                if *b {
-                    n |= 1 << i;
-                }
-            }
-            new_data.push(n as f64);
+        let new_data: Vec<u128> = if bit_string_len == 0 {
+            vec![0; shape.iter().product()]
+        } else {
+            // Big endian
+            (bools.chunks_exact(bit_string_len))
+                .map(|bits| {
+                    let mut n = 0;
+                    for (i, b) in bits.iter().enumerate() {
+                        if *b {
+                            n |= 1 << i;
+                        }
+                    }
+                    n
+                })
+                .collect()
+        };
+        const EXACT: u128 = 1 << f64::MANTISSA_DIGITS;
+        if new_data.iter().all(|&n| n <= EXACT) || new_data.iter().any(|&n| n > i64::MAX as u128) {
+            let arr = Array::new(
+                shape,
+                new_data.into_iter().map(|n| n as f64).collect::<Vec<_>>(),
+            );
+            arr.validate_shape();
+            Ok(arr.into())
+        } else {
+            let arr = Array::new(
+                shape,
+                new_data.into_iter().map(|n| n as i64).collect::<Vec<_>>(),
+            );
+            arr.validate_shape();
+            Ok(arr.into())
         }
-        let arr = Array::new(shape, new_data);
-        arr.validate_shape();
-        Ok(arr)
     }
 }
@@ -0,0 +1,161 @@
+//! Splitting large array operations across threads
+//!
+//! Work is only split when it is big enough to pay for starting threads. Each thread
+//! computes a contiguous part of the result exactly as a single thread would, so results
+//! do not depend on the number of threads.
+
+use std::{cmp::Ordering, ops::Range, thread};
+
+/// The amount of work, in elements, below which an operation runs on one thread
+pub const THRESHOLD: usize = 1 << 16;
+
+/// The number of threads to use for an operation on `work` elements, given the most allowed
+pub fn threads(max: usize, work: usize) -> usize {
+    if work < THRESHOLD {
+        1
+    } else {
+        max.max(1)
+    }
+}
+
+/// Split `0..len` into at most `parts` contiguous ranges whose lengths are multiples of
+/// `align`, except maybe the last
+pub fn ranges(len: usize, align: usize, parts: usize) -> Vec<Range<usize>> {
+    let align = align.max(1);
+    let units = len.div_ceil(align);
+    let parts = parts.clamp(1, units.max(1));
+    let part_len = units.div_ceil(parts).max(1) * align;
+    (0..len.max(1))
+        .step_by(part_len)
+        .map(|start| start..(start + part_len).min(len))
+        .collect()
+}
+
+/// Call a function on each input on its own thread, and collect the results in order
+///
+/// The first input is handled on the current thread.
+pub fn map<I: Send, R: Send>(inputs: Vec<I>, f: impl Fn(I) -> R + Sync) -> Vec<R> {
+    let mut inputs = inputs.into_iter();
+    let Some(first) = inputs.next() else {
+        return Vec::new();
+    };
+    if inputs.len() == 0 {
+        return vec![f(first)];
+    }
+    let f = &f;
+    thread::scope(|s| {
+        let handles: Vec<_> = inputs.map(|input| s.spawn(move || f(input))).collect();
+        let mut results = Vec::with_capacity(handles.len() + 1);
+        results.push(f(first));
+        for handle in handles {
+            match handle.join() {
+                Ok(res) => results.push(res),
+                Err(e) => std::panic::resume_unwind(e),
+            }
+        }
+        results
+    })
+}
+
+/// Call a function on contiguous parts of a slice, each on its own thread
+///
+/// Each part's length is a multiple of `align`, except maybe the last. The function is
+/// passed the index of the part's first element.
+pub fn for_each_part<T: Send>(
+    items: &mut [T],
+    align: usize,
+    threads: usize,
+    f: impl Fn(usize, &mut [T]) + Sync,
+) {
+    if threads <= 1 || items.is_empty() {
+        f(0, items);
+        return;
+    }
+    let ranges = ranges(items.len(), align, threads);
+    let mut parts = Vec::with_capacity(ranges.len());
+    let mut rest = items;
+    for range in ranges {
+        let (part, tail) = rest.split_at_mut(range.len());
+        parts.push((range.start, part));
+        rest = tail;
+    }
+    map(parts, |(start, part)| f(start, part));
+}
+
+/// Sort a slice stably, sorting parts of it on separate threads and then merging them
+///
+/// The result is the same as that of [`slice::sort_by`].
+pub fn sort_by<T: Send + Copy>(
+    items: &mut [T],
+    threads: usize,
+    cmp: impl Fn(&T, &T) -> Ordering + Sync,
+) {
+    if threads <= 1 {
+        items.sort_by(cmp);
+        return;
+    }
+    let runs = ranges(items.len(), 1, threads);
+    for_each_part(items, 1, threads, |_, part| part.sort_by(&cmp));
+    // Merge adjacent runs until there is only one. Ties are taken from the left run,
+    // which keeps the sort stable.
+    let mut runs: Vec<usize> = runs.iter().map(|r| r.end).collect();
+    let mut buf = Vec::with_capacity(items.len());
+    while runs.len() > 1 {
+        let mut start = 0;
+        let mut merged = Vec::with_capacity(runs.len().div_ceil(2));
+        for pair in runs.chunks(2) {
+            let end = *pair.last().unwrap();
+            if let &[mid, _] = pair {
+                buf.clear();
+                let (left, right) = items[start..end].split_at(mid - start);
+                let (mut i, mut j) = (0, 0);
+                while i < left.len() && j < right.len() {
+                    if cmp(&right[j], &left[i]) == Ordering::Less {
+                        buf.push(right[j]);
+                        j += 1;
+                    } else {
+                        buf.push(left[i]);
+                        i += 1;
+                    }
+                }
+                buf.extend_from_slice(&left[i..]);
+                buf.extend_from_slice(&right[j..]);
+                items[start..end].copy_from_slice(&buf);
+            }
+            merged.push(end);
+            start = end;
+        }
+        runs = merged;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parts_match_one_thread() {
+        assert_eq!(ranges(10, 3, 2), [0..6, 6..10]);
+        assert_eq!(ranges(0, 3, 4).len(), 1);
+        assert_eq!(ranges(5, 1, 8).len(), 5);
+
+        let mut items = vec![0usize; 1000];
+        for_each_part(&mut items, 7, 6, |start, part| {
+            assert_eq!(start % 7, 0);
+            for (i, x) in part.iter_mut().enumerate() {
+                *x = start + i;
+            }
+        });
+        assert!(items.iter().enumerate().all(|(i, &x)| i == x));
+
+        // Many equal keys, so that a sort that is not stable would be caught
+        let keys: Vec<(u32, usize)> = (0..5000).map(|i| ((i * 7919 % 13) as u32, i)).collect();
+        let mut sorted = keys.clone();
+        sorted.sort_by_key(|&(k, _)| k);
+        for threads in 1..6 {
+            let mut par = keys.clone();
+            sort_by(&mut par, threads, |a, b| a.0.cmp(&b.0));
+            assert_eq!(par, sorted);
+        }
+    }
+}
@@ -6,11 +6,12 @@ use std::{
     fmt::Display,
     marker::PhantomData,
     slice::{self, Chunks},
+    sync::atomic::{self, AtomicBool},
 };
 
 use crate::{array::*, Uiua, UiuaError, UiuaResult};
 
-use super::max_shape;
+use super::{max_shape, simd};
 
 #[allow(clippy::len_without_is_empty)]
 pub trait Arrayish {
@@ -214,6 +215,30 @@ where
     Ok(Array::new(shape, data))
 }
 
+/// Pervade a checked integer function
+///
+/// Returns `None` if the function fails for any pair of elements, in which case the
+/// caller computes the result with numbers instead.
+pub fn int_bin(
+    a: &Array<i64>,
+    b: &Array<i64>,
+    env: &Uiua,
+    f: fn(i64, i64) -> Option<i64>,
+) -> UiuaResult<Option<Array<i64>>> {
+    let overflow = AtomicBool::new(false);
+    let checked = |a, b| {
+        f(a, b).unwrap_or_else(|| {
+            overflow.store(true, atomic::Ordering::Relaxed);
+            0
+        })
+    };
+    let arr = match simd::bin(a, b, env.thread_count(), checked) {
+        Some(arr) => arr,
+        None => bin_pervade(a, b, env, InfalliblePervasiveFn::new(checked))?,
+    };
+    Ok((!overflow.into_inner()).then_some(arr))
+}
+
 fn bin_pervade_recursive<A, B, C, F>(
     a: &A,
     b: &B,
@@ -261,6 +286,9 @@ pub mod not {
     pub fn byte(a: u8) -> f64 {
         num(a.into())
     }
+    pub fn int(a: i64) -> Option<i64> {
+        1i64.checked_sub(a)
+    }
     pub fn error<T: Display>(a: T, env: &Uiua) -> UiuaError {
         env.error(format!("Cannot negate {a}"))
     }
@@ -274,6 +302,9 @@ pub mod neg {
     pub fn byte(a: u8) -> f64 {
         -f64::from(a)
     }
+    pub fn int(a: i64) -> Option<i64> {
+        a.checked_neg()
+    }
     pub fn error<T: Display>(a: T, env: &Uiua) -> UiuaError {
         env.error(format!("Cannot negate {a}"))
     }
@@ -286,6 +317,9 @@ pub mod abs {
     pub fn byte(a: u8) -> u8 {
         a
     }
+    pub fn int(a: i64) -> Option<i64> {
+        a.checked_abs()
+    }
     pub fn error<T: Display>(a: T, env: &Uiua) -> UiuaError {
         env.error(format!("Cannot take the absolute value of {a}"))
     }
@@ -304,6 +338,9 @@ pub mod sign {
     pub fn byte(a: u8) -> u8 {
         (a > 0) as u8
     }
+    pub fn int(a: i64) -> Option<i64> {
+        Some(a.signum())
+    }
     pub fn error<T: Display>(a: T, env: &Uiua) -> UiuaError {
         env.error(format!("Cannot get the sign of {a}"))
     }
@@ -388,6 +425,9 @@ pub mod floor {
     pub fn byte(a: u8) -> u8 {
         a
     }
+    pub fn int(a: i64) -> Option<i64> {
+        Some(a)
+    }
     pub fn error<T: Display>(a: T, env: &Uiua) -> UiuaError {
         env.error(format!("Cannot get the floor of {a}"))
     }
@@ -400,6 +440,9 @@ pub mod ceil {
     pub fn byte(a: u8) -> u8 {
         a
     }
+    pub fn int(a: i64) -> Option<i64> {
+        Some(a)
+    }
     pub fn error<T: Display>(a: T, env: &Uiua) -> UiuaError {
         env.error(format!("Cannot get the ceiling of {a}"))
     }
@@ -412,6 +455,9 @@ pub mod round {
     pub fn byte(a: u8) -> u8 {
         a
     }
+    pub fn int(a: i64) -> Option<i64> {
+        Some(a)
+    }
     pub fn error<T: Display>(a: T, env: &Uiua) -> UiuaError {
         env.error(format!("Cannot get the rounded value of {a}"))
     }
@@ -436,6 +482,12 @@ macro_rules! cmp_impl {
             pub fn num_byte(a: f64, b: u8) -> u8 {
                 (f64::from(b).array_cmp(&a) $eq $ordering) as u8
             }
+            pub fn int_num(a: i64, b: f64) -> u8 {
+                (b.array_cmp(&a) $eq $ordering) as u8
+            }
+            pub fn num_int(a: f64, b: i64) -> u8 {
+                (b.array_cmp(&a) $eq $ordering) as u8
+            }
             pub fn generic<T: Ord>(a: T, b: T) -> u8 {
                 (b.cmp(&a) $eq $ordering).into()
             }
@@ -480,6 +532,9 @@ pub mod add {
     pub fn char_byte(a: char, b: u8) -> char {
         char::from_u32((b as i64 + a as i64) as u32).unwrap_or('\0')
     }
+    pub fn int_int(a: i64, b: i64) -> Option<i64> {
+        b.checked_add(a)
+    }
     pub fn error<T: Display>(a: T, b: T, env: &Uiua) -> UiuaError {
         env.error(format!("Cannot add {a} and {b}"))
     }
@@ -508,6 +563,9 @@ pub mod sub {
     pub fn byte_char(a: u8, b: char) -> char {
         char::from_u32(((b as i64) - (a as i64)) as u32).unwrap_or('\0')
     }
+    pub fn int_int(a: i64, b: i64) -> Option<i64> {
+        b.checked_sub(a)
+    }
     pub fn error<T: Display>(a: T, b: T, env: &Uiua) -> UiuaError {
         env.error(format!("Cannot subtract {a} from {b}"))
     }
@@ -527,6 +585,9 @@ pub mod mul {
     pub fn num_byte(a: f64, b: u8) -> f64 {
         f64::from(b) * a
     }
+    pub fn int_int(a: i64, b: i64) -> Option<i64> {
+        b.checked_mul(a)
+    }
     pub fn error<T: Display>(a: T, b: T, env: &Uiua) -> UiuaError {
         env.error(format!("Cannot multiply {a} and {b}"))
     }
@@ -566,6 +627,14 @@ pub mod modulus {
     pub fn num_byte(a: f64, b: u8) -> f64 {
         (f64::from(b) % a + a) % a
     }
+    pub fn int_int(a: i64, b: i64) -> Option<i64> {
+        let r = b.checked_rem(a)?;
+        Some(if r != 0 && (r < 0) != (a < 0) {
+            r + a
+        } else {
+            r
+        })
+    }
     pub fn error<T: Display>(a: T, b: T, env: &Uiua) -> UiuaError {
         env.error(format!("Cannot take the modulus of {a} by {b}"))
     }
@@ -595,6 +664,9 @@ pub mod pow {
     pub fn num_byte(a: f64, b: u8) -> f64 {
         f64::from(b).powf(a)
     }
+    pub fn int_int(a: i64, b: i64) -> Option<i64> {
+        b.checked_pow(u32::try_from(a).ok()?)
+    }
     pub fn error<T: Display>(a: T, b: T, env: &Uiua) -> UiuaError {
         env.error(format!("Cannot get the power of {a} to {b}"))
     }
@@ -640,6 +712,9 @@ pub mod max {
     pub fn byte_num(a: u8, b: f64) -> f64 {
         num_num(a.into(), b)
     }
+    pub fn int_int(a: i64, b: i64) -> Option<i64> {
+        Some(a.max(b))
+    }
     pub fn error<T: Display>(a: T, b: T, env: &Uiua) -> UiuaError {
         env.error(format!("Cannot get the max of {a} and {b}"))
     }
@@ -666,6 +741,9 @@ pub mod min {
     pub fn byte_num(a: u8, b: f64) -> f64 {
         num_num(a.into(), b)
     }
+    pub fn int_int(a: i64, b: i64) -> Option<i64> {
+        Some(a.min(b))
+    }
     pub fn error<T: Display>(a: T, b: T, env: &Uiua) -> UiuaError {
         env.error(format!("Cannot get the min of {a} and {b}"))
     }
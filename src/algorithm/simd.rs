@@ -0,0 +1,173 @@
+//! SIMD kernels for pervasive dyadic functions on numbers and bytes
+//!
+//! The kernels are flat loops that the compiler vectorizes. On x86_64, each kernel
+//! is also compiled with AVX2 enabled, and that version is used if the CPU supports it.
+//!
+//! Large arrays are split into parts that are computed on separate threads.
+
+use ecow::EcoVec;
+
+use crate::{
+    algorithm::parallel,
+    array::{Array, ArrayValue},
+};
+
+/// Apply a function to the elements of two arrays with the same shape, or of a scalar
+/// and an array, using up to `threads` threads
+///
+/// Returns `None` for other shapes, which need the general pervasion algorithm.
+pub fn bin<A, B, C>(
+    a: &Array<A>,
+    b: &Array<B>,
+    threads: usize,
+    f: impl Fn(A, B) -> C + Copy + Sync,
+) -> Option<Array<C>>
+where
+    A: Copy + Send + Sync,
+    B: Copy + Send + Sync,
+    C: ArrayValue + Copy + Default,
+{
+    let (shape, len) = if a.shape == b.shape || b.shape.is_empty() {
+        (a.shape.clone(), a.data.len())
+    } else if a.shape.is_empty() {
+        (b.shape.clone(), b.data.len())
+    } else {
+        return None;
+    };
+    let mut data = EcoVec::from_elem(C::default(), len);
+    let threads = parallel::threads(threads, len);
+    parallel::for_each_part(data.make_mut(), 1, threads, |start, out| {
+        let end = start + out.len();
+        if a.shape == b.shape {
+            zip(&a.data[start..end], &b.data[start..end], out, f)
+        } else if a.shape.is_empty() {
+            let a = a.data[0];
+            map(&b.data[start..end], out, move |b| f(a, b))
+        } else {
+            let b = b.data[0];
+            map(&a.data[start..end], out, move |a| f(a, b))
+        }
+    });
+    Some(Array::new(shape, data))
+}
+
+fn zip<A, B, C>(a: &[A], b: &[B], out: &mut [C], f: impl Fn(A, B) -> C + Copy)
+where
+    A: Copy,
+    B: Copy,
+{
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("avx2") {
+        // Safety: the CPU supports AVX2
+        return unsafe { zip_avx2(a, b, out, f) };
+    }
+    zip_impl(a, b, out, f)
+}
+
+fn map<A, C>(a: &[A], out: &mut [C], f: impl Fn(A) -> C + Copy)
+where
+    A: Copy,
+{
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("avx2") {
+        // Safety: the CPU supports AVX2
+        return unsafe { map_avx2(a, out, f) };
+    }
+    map_impl(a, out, f)
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn zip_avx2<A, B, C>(a: &[A], b: &[B], out: &mut [C], f: impl Fn(A, B) -> C + Copy)
+where
+    A: Copy,
+    B: Copy,
+{
+    zip_impl(a, b, out, f)
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn map_avx2<A, C>(a: &[A], out: &mut [C], f: impl Fn(A) -> C + Copy)
+where
+    A: Copy,
+{
+    map_impl(a, out, f)
+}
+
+#[inline(always)]
+fn zip_impl<A, B, C>(a: &[A], b: &[B], out: &mut [C], f: impl Fn(A, B) -> C)
+where
+    A: Copy,
+    B: Copy,
+{
+    for ((c, &a), &b) in out.iter_mut().zip(a).zip(b) {
+        *c = f(a, b);
+    }
+}
+
+#[inline(always)]
+fn map_impl<A, C>(a: &[A], out: &mut [C], f: impl Fn(A) -> C)
+where
+    A: Copy,
+{
+    for (c, &a) in out.iter_mut().zip(a) {
+        *c = f(a);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{algorithm::pervade::*, array::Shape};
+
+    #[test]
+    fn simd_matches_scalar() {
+        let nums: Vec<f64> = (0..100).map(|i| (i as f64 - 50.0) / 7.0).collect();
+        let mut others = nums.clone();
+        others.reverse();
+        others[3] = f64::NAN;
+        others[4] = f64::INFINITY;
+        let (a, b) = (Array::from(nums.clone()), Array::from(others.clone()));
+        let scalar = Array::unit(2.5);
+
+        let sum = bin(&a, &b, 1, add::num_num).unwrap();
+        let lt = bin(&a, &b, 1, is_lt::num_num).unwrap();
+        let max = bin(&a, &b, 1, max::num_num).unwrap();
+        for i in 0..100 {
+            assert_eq!(
+                sum.data[i].to_bits(),
+                add::num_num(nums[i], others[i]).to_bits()
+            );
+            assert_eq!(lt.data[i], is_lt::num_num(nums[i], others[i]));
+            assert_eq!(
+                max.data[i].to_bits(),
+                max::num_num(nums[i], others[i]).to_bits()
+            );
+        }
+
+        let left = bin(&scalar, &b, 1, mul::num_num).unwrap();
+        let right = bin(&a, &scalar, 1, sub::num_num).unwrap();
+        assert_eq!(left.shape, b.shape);
+        for i in 0..100 {
+            assert_eq!(left.data[i].to_bits(), (others[i] * 2.5).to_bits());
+            assert_eq!(right.data[i], 2.5 - nums[i]);
+        }
+
+        let bytes: Array<u8> = Array::from((0..=255).collect::<Vec<u8>>());
+        let rev: Array<u8> = Array::from((0..=255).rev().collect::<Vec<u8>>());
+        let eq = bin(&bytes, &rev, 1, is_eq::generic).unwrap();
+        let min = bin(&bytes, &rev, 1, min::byte_byte).unwrap();
+        let sum = bin(&bytes, &rev, 1, add::byte_byte).unwrap();
+        for i in 0..256 {
+            let (x, y) = (bytes.data[i], rev.data[i]);
+            assert_eq!(eq.data[i], is_eq::generic(x, y));
+            assert_eq!(min.data[i], x.min(y));
+            assert_eq!(sum.data[i], 255.0);
+        }
+
+        // Shapes that need the general algorithm
+        let table = Array::new(Shape::from(&[2, 50][..]), nums);
+        assert!(bin(&table, &a, 1, add::num_num).is_none());
+    }
+}
@@ -213,6 +213,18 @@ impl<T: ArrayValue> Array<T> {
             )
         })
     }
+    /// Iterate over the windows of `size` consecutive rows, making each one only when it is needed
+    ///
+    /// The array must have at least `size` rows.
+    pub fn into_row_windows(self, size: usize) -> impl Iterator<Item = Self> {
+        let row_len = self.row_len();
+        let mut window_shape = self.shape.clone();
+        window_shape[0] = size;
+        (0..=self.row_count() - size).map(move |i| {
+            let data: CowSlice<T> = self.data[i * row_len..(i + size) * row_len].to_vec().into();
+            Array::new(window_shape.clone(), data)
+        })
+    }
     pub fn into_rows_rev(self) -> impl Iterator<Item = Self> {
         let row_len = self.row_len();
         let mut row_shape = self.shape.clone();
@@ -333,7 +345,8 @@ impl<T: ArrayValue> FromIterator<T> for Array<T> {
 
 impl From<String> for Array<char> {
     fn from(s: String) -> Self {
-        Self::new(tiny_vec![s.len()], s.chars().collect::<CowSlice<_>>())
+        let data = s.chars().collect::<CowSlice<_>>();
+        Self::new(tiny_vec![data.len()], data)
     }
 }
 
@@ -365,7 +378,7 @@ impl FromIterator<String> for Array<Arc<Function>> {
 }
 
 #[allow(unused_variables)]
-pub trait ArrayValue: Clone + Debug + Display + GridFmt + ArrayCmp {
+pub trait ArrayValue: Clone + Send + Sync + Debug + Display + GridFmt + ArrayCmp {
     const NAME: &'static str;
     fn get_fill(env: &Uiua) -> Option<Self>;
     fn array_hash<H: Hasher>(&self, hasher: &mut H);
@@ -407,6 +420,16 @@ impl ArrayValue for u8 {
     }
 }
 
+impl ArrayValue for i64 {
+    const NAME: &'static str = "integer";
+    fn get_fill(env: &Uiua) -> Option<Self> {
+        env.int_fill()
+    }
+    fn array_hash<H: Hasher>(&self, hasher: &mut H) {
+        self.hash(hasher)
+    }
+}
+
 impl ArrayValue for char {
     const NAME: &'static str = "character";
     fn get_fill(env: &Uiua) -> Option<Self> {
@@ -471,6 +494,12 @@ impl ArrayCmp for u8 {
     }
 }
 
+impl ArrayCmp for i64 {
+    fn array_cmp(&self, other: &Self) -> Ordering {
+        self.cmp(other)
+    }
+}
+
 impl ArrayCmp for char {
     fn array_cmp(&self, other: &Self) -> Ordering {
         self.cmp(other)
@@ -495,6 +524,49 @@ impl ArrayCmp<u8> for f64 {
     }
 }
 
+impl ArrayCmp<f64> for i64 {
+    fn array_cmp(&self, other: &f64) -> Ordering {
+        cmp_int_num(*self, *other)
+    }
+}
+
+impl ArrayCmp<i64> for f64 {
+    fn array_cmp(&self, other: &i64) -> Ordering {
+        cmp_int_num(*other, *self).reverse()
+    }
+}
+
+impl ArrayCmp<u8> for i64 {
+    fn array_cmp(&self, other: &u8) -> Ordering {
+        self.cmp(&(*other as i64))
+    }
+}
+
+impl ArrayCmp<i64> for u8 {
+    fn array_cmp(&self, other: &i64) -> Ordering {
+        (*self as i64).cmp(other)
+    }
+}
+
+/// Compare an integer and a number exactly, without rounding the integer to a number
+///
+/// `NaN` is greater than every integer, as it is greater than every number.
+pub(crate) fn cmp_int_num(i: i64, n: f64) -> Ordering {
+    const LIMIT: f64 = 9_223_372_036_854_775_808.0; // 2^63
+    if n.is_nan() || n >= LIMIT {
+        Ordering::Less
+    } else if n < -LIMIT {
+        Ordering::Greater
+    } else {
+        let floor = n.floor();
+        i.cmp(&(floor as i64)).then(if n > floor {
+            Ordering::Less
+        } else {
+            Ordering::Equal
+        })
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub struct FormatShape<'a>(pub &'a [usize]);
 
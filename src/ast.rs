@@ -12,6 +12,7 @@ pub enum Item {
     Scoped { items: Vec<Item>, test: bool },
     Words(Vec<Sp<Word>>),
     Binding(Binding),
+    Import(Import),
     ExtraNewlines(CodeSpan),
 }
 
@@ -20,6 +21,15 @@ pub struct Binding {
     pub name: Sp<Ident>,
     pub signature: Option<Sp<Signature>>,
     pub words: Vec<Sp<Word>>,
+    /// Whether the binding is hidden from files that import this one
+    pub private: bool,
+}
+
+/// An import of a file as a module, like `Name ? "path"`
+#[derive(Debug, Clone)]
+pub struct Import {
+    pub name: Sp<Ident>,
+    pub path: Sp<String>,
 }
 
 #[derive(Clone)]
@@ -0,0 +1,605 @@
+//! Serialization of compiled code
+//!
+//! A compiled file is the list of items that were run when the file was loaded,
+//! with each item's instructions, the values they push, and the spans they refer to.
+//! Primitives are referred to by name, so files stay valid when primitives are added.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{
+    array::{Array, Shape},
+    function::*,
+    lex::{CodeSpan, Loc, Span},
+    optimize,
+    primitive::Primitive,
+    value::Value,
+    Ident,
+};
+
+/// The start of every compiled file. The number changes with the format.
+const MAGIC: &[u8] = b"UIUAC\x02";
+
+/// A compiled item of a file, in the order it was run
+#[derive(Clone)]
+pub(crate) enum CompiledItem {
+    /// Instructions run at the top level
+    Run(Vec<Instr>),
+    /// A binding, with the instructions that produce its value
+    Bind {
+        name: Ident,
+        private: bool,
+        instrs: Vec<Instr>,
+        signature: Signature,
+        /// Whether the instructions are run to get the value, rather than bound as a function
+        run: bool,
+    },
+    /// The items of a scope
+    Scoped(Vec<CompiledItem>),
+    /// A module import
+    Import { name: Ident, path: PathBuf },
+}
+
+/// A compiled file
+pub(crate) struct CompiledFile {
+    /// Imported files and the hashes of their sources
+    pub deps: Vec<(PathBuf, u64)>,
+    pub items: Vec<CompiledItem>,
+}
+
+/// Encode a compiled file
+///
+/// `spans` is the table that span indices in instructions refer to.
+/// Fails if the file refers to a native function.
+pub(crate) fn encode(file: &CompiledFile, spans: &[Span]) -> Result<Vec<u8>, String> {
+    let mut enc = Encoder {
+        out: Vec::new(),
+        all_spans: spans,
+        span_indices: HashMap::new(),
+        spans: Vec::new(),
+        source_indices: HashMap::new(),
+        sources: Vec::new(),
+        prim_indices: HashMap::new(),
+        prims: Vec::new(),
+    };
+    enc.items(&file.items)?;
+    let items = std::mem::take(&mut enc.out);
+    // The span table is encoded after the items that refer to it, and may refer to more sources
+    let span_indices = std::mem::take(&mut enc.spans);
+    enc.usize(span_indices.len());
+    for index in span_indices {
+        match &spans[index] {
+            Span::Builtin => enc.u8(0),
+            Span::Code(span) => {
+                enc.u8(1);
+                enc.code_span(span);
+            }
+        }
+    }
+    // So is the primitive table
+    let prims = std::mem::take(&mut enc.prims);
+    enc.usize(prims.len());
+    for prim in prims {
+        enc.str(&format!("{prim:?}"));
+    }
+    let span_table = std::mem::take(&mut enc.out);
+
+    enc.out.extend_from_slice(MAGIC);
+    enc.str(env!("CARGO_PKG_VERSION"));
+    enc.usize(file.deps.len());
+    for (path, hash) in &file.deps {
+        enc.str(&path.to_string_lossy());
+        enc.out.extend_from_slice(&hash.to_le_bytes());
+    }
+    let sources = std::mem::take(&mut enc.sources);
+    enc.usize(sources.len());
+    for (path, input) in sources {
+        enc.opt_str(path.as_ref().map(|path| path.to_string_lossy()).as_deref());
+        enc.str(&input);
+    }
+    enc.out.extend(span_table);
+    enc.out.extend(items);
+    Ok(enc.out)
+}
+
+/// Decode a compiled file
+///
+/// The spans it refers to are added to `spans`.
+pub(crate) fn decode(bytes: &[u8], spans: &mut Vec<Span>) -> Result<CompiledFile, String> {
+    let mut dec = Decoder {
+        bytes,
+        pos: 0,
+        sources: Vec::new(),
+        span_indices: Vec::new(),
+        prims: Vec::new(),
+    };
+    if !bytes.starts_with(MAGIC) {
+        return Err("Not a compiled Uiua file".into());
+    }
+    dec.pos = MAGIC.len();
+    let version = dec.str()?;
+    if version != env!("CARGO_PKG_VERSION") {
+        return Err(format!(
+            "File was compiled by Uiua {version}, but this is Uiua {}",
+            env!("CARGO_PKG_VERSION")
+        ));
+    }
+    let deps = dec.list(|dec| {
+        let path = PathBuf::from(dec.str()?);
+        let hash = u64::from_le_bytes(dec.take(8)?.try_into().unwrap());
+        Ok((path, hash))
+    })?;
+    dec.sources = dec.list(|dec| {
+        let path = dec.opt_str()?.map(|path| Arc::from(Path::new(&path)));
+        let input: Arc<str> = dec.str()?.into();
+        Ok((path, input))
+    })?;
+    let table = dec.list(|dec| match dec.u8()? {
+        0 => Ok(Span::Builtin),
+        1 => Ok(Span::Code(dec.code_span()?)),
+        tag => Err(format!("Invalid span tag {tag}")),
+    })?;
+    dec.span_indices = (spans.len()..).take(table.len()).collect();
+    spans.extend(table);
+    let names: HashMap<String, Primitive> = (Primitive::all())
+        .map(|prim| (format!("{prim:?}"), prim))
+        .collect();
+    dec.prims = dec.list(|dec| {
+        let name = dec.str()?;
+        (names.get(&name).copied()).ok_or_else(|| format!("Unknown primitive `{name}`"))
+    })?;
+    let items = dec.items()?;
+    Ok(CompiledFile { deps, items })
+}
+
+type Source = (Option<Arc<Path>>, Arc<str>);
+
+struct Encoder<'a> {
+    out: Vec<u8>,
+    all_spans: &'a [Span],
+    /// Map global span indices to indices in the encoded span table
+    span_indices: HashMap<usize, usize>,
+    spans: Vec<usize>,
+    /// Map source pointers to indices in the encoded source table
+    source_indices: HashMap<(usize, Option<usize>), usize>,
+    sources: Vec<Source>,
+    /// Map primitives to indices in the encoded primitive table
+    prim_indices: HashMap<Primitive, usize>,
+    prims: Vec<Primitive>,
+}
+
+impl<'a> Encoder<'a> {
+    fn u8(&mut self, n: u8) {
+        self.out.push(n);
+    }
+    /// Write an unsigned LEB128 number
+    fn usize(&mut self, mut n: usize) {
+        loop {
+            let byte = (n & 0x7f) as u8;
+            n >>= 7;
+            if n == 0 {
+                self.out.push(byte);
+                break;
+            }
+            self.out.push(byte | 0x80);
+        }
+    }
+    fn str(&mut self, s: &str) {
+        self.usize(s.len());
+        self.out.extend_from_slice(s.as_bytes());
+    }
+    fn opt_str(&mut self, s: Option<&str>) {
+        match s {
+            Some(s) => {
+                self.u8(1);
+                self.str(s);
+            }
+            None => self.u8(0),
+        }
+    }
+    fn span(&mut self, index: usize) -> Result<(), String> {
+        if index >= self.all_spans.len() {
+            return Err(format!("Invalid span index {index}"));
+        }
+        let local = *self.span_indices.entry(index).or_insert_with(|| {
+            self.spans.push(index);
+            self.spans.len() - 1
+        });
+        self.usize(local);
+        Ok(())
+    }
+    fn code_span(&mut self, span: &CodeSpan) {
+        let key = (
+            Arc::as_ptr(&span.input) as *const u8 as usize,
+            (span.path.as_ref()).map(|path| Arc::as_ptr(path) as *const u8 as usize),
+        );
+        let source = *self.source_indices.entry(key).or_insert_with(|| {
+            self.sources.push((span.path.clone(), span.input.clone()));
+            self.sources.len() - 1
+        });
+        self.usize(source);
+        for loc in [span.start, span.end] {
+            self.usize(loc.char_pos);
+            self.usize(loc.byte_pos);
+            self.usize(loc.line);
+            self.usize(loc.col);
+        }
+    }
+    fn items(&mut self, items: &[CompiledItem]) -> Result<(), String> {
+        self.usize(items.len());
+        for item in items {
+            match item {
+                CompiledItem::Run(instrs) => {
+                    self.u8(0);
+                    self.instrs(instrs)?;
+                }
+                CompiledItem::Bind {
+                    name,
+                    private,
+                    instrs,
+                    signature,
+                    run,
+                } => {
+                    self.u8(1);
+                    self.str(name);
+                    self.u8(*private as u8);
+                    self.instrs(instrs)?;
+                    self.signature(*signature);
+                    self.u8(*run as u8);
+                }
+                CompiledItem::Scoped(items) => {
+                    self.u8(2);
+                    self.items(items)?;
+                }
+                CompiledItem::Import { name, path } => {
+                    self.u8(3);
+                    self.str(name);
+                    self.str(&path.to_string_lossy());
+                }
+            }
+        }
+        Ok(())
+    }
+    fn signature(&mut self, sig: Signature) {
+        self.usize(sig.args);
+        self.usize(sig.outputs);
+    }
+    fn instrs(&mut self, instrs: &[Instr]) -> Result<(), String> {
+        self.usize(instrs.len());
+        for instr in instrs {
+            match instr {
+                Instr::Push(val) => {
+                    self.u8(0);
+                    self.value(val)?;
+                }
+                Instr::BeginArray => self.u8(1),
+                Instr::EndArray { constant, span } => {
+                    self.u8(2);
+                    self.u8(*constant as u8);
+                    self.span(*span)?;
+                }
+                Instr::Prim(prim, span) => {
+                    self.u8(3);
+                    self.prim(*prim);
+                    self.span(*span)?;
+                }
+                Instr::Call(span) => {
+                    self.u8(4);
+                    self.span(*span)?;
+                }
+                Instr::Fused(fused) => {
+                    self.u8(5);
+                    self.instrs(&fused.instrs)?;
+                }
+            }
+        }
+        Ok(())
+    }
+    fn prim(&mut self, prim: Primitive) {
+        let index = *self.prim_indices.entry(prim).or_insert_with(|| {
+            self.prims.push(prim);
+            self.prims.len() - 1
+        });
+        self.usize(index);
+    }
+    fn shape(&mut self, shape: &[usize]) {
+        self.usize(shape.len());
+        for &dim in shape {
+            self.usize(dim);
+        }
+    }
+    fn value(&mut self, val: &Value) -> Result<(), String> {
+        match val {
+            Value::Num(arr) => {
+                self.u8(0);
+                self.shape(arr.shape());
+                for n in arr.data.iter() {
+                    self.out.extend_from_slice(&n.to_le_bytes());
+                }
+            }
+            Value::Byte(arr) => {
+                self.u8(1);
+                self.shape(arr.shape());
+                self.out.extend_from_slice(&arr.data);
+            }
+            Value::Mask(mask) => {
+                self.u8(1);
+                self.shape(mask.shape());
+                self.out.extend_from_slice(&mask.unpacked().data);
+            }
+            Value::Char(arr) => {
+                self.u8(2);
+                self.shape(arr.shape());
+                for &c in arr.data.iter() {
+                    self.usize(c as usize);
+                }
+            }
+            Value::Func(arr) => {
+                self.u8(3);
+                self.shape(arr.shape());
+                for f in arr.data.iter() {
+                    self.function(f)?;
+                }
+            }
+            Value::Int(arr) => {
+                self.u8(4);
+                self.shape(arr.shape());
+                for i in arr.data.iter() {
+                    self.out.extend_from_slice(&i.to_le_bytes());
+                }
+            }
+        }
+        Ok(())
+    }
+    fn function(&mut self, f: &Function) -> Result<(), String> {
+        self.function_id(&f.id);
+        match &f.kind {
+            FunctionKind::Normal => self.u8(0),
+            FunctionKind::Format(frags) => {
+                self.u8(1);
+                self.usize(frags.len());
+                for frag in frags.iter() {
+                    self.str(frag);
+                }
+            }
+            FunctionKind::Dynamic(_) => {
+                return Err(format!("Native function {} cannot be compiled", f.id))
+            }
+        }
+        self.instrs(&f.instrs)?;
+        self.signature(f.signature());
+        Ok(())
+    }
+    fn function_id(&mut self, id: &FunctionId) {
+        match id {
+            FunctionId::Named(name) => {
+                self.u8(0);
+                self.str(name);
+            }
+            FunctionId::Anonymous(span) => {
+                self.u8(1);
+                self.code_span(span);
+            }
+            FunctionId::Primitive(prim) => {
+                self.u8(2);
+                self.prim(*prim);
+            }
+            FunctionId::Constant => self.u8(3),
+            FunctionId::Main => self.u8(4),
+            FunctionId::Composed(ids) => {
+                self.u8(5);
+                self.usize(ids.len());
+                for id in ids {
+                    self.function_id(id);
+                }
+            }
+        }
+    }
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    sources: Vec<Source>,
+    /// Map indices in the encoded span table to global span indices
+    span_indices: Vec<usize>,
+    /// The encoded primitive table
+    prims: Vec<Primitive>,
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        let bytes = (self.pos.checked_add(n))
+            .and_then(|end| self.bytes.get(self.pos..end))
+            .ok_or("Compiled file ended unexpectedly")?;
+        self.pos += n;
+        Ok(bytes)
+    }
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+    fn bool(&mut self) -> Result<bool, String> {
+        Ok(self.u8()? != 0)
+    }
+    fn usize(&mut self) -> Result<usize, String> {
+        let mut n = 0usize;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            if shift >= usize::BITS {
+                return Err("Invalid number in compiled file".into());
+            }
+            n |= ((byte & 0x7f) as usize) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(n);
+            }
+            shift += 7;
+        }
+    }
+    fn str(&mut self) -> Result<String, String> {
+        let len = self.usize()?;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|e| e.to_string())
+    }
+    fn opt_str(&mut self) -> Result<Option<String>, String> {
+        Ok(if self.bool()? {
+            Some(self.str()?)
+        } else {
+            None
+        })
+    }
+    fn list<T>(
+        &mut self,
+        mut f: impl FnMut(&mut Self) -> Result<T, String>,
+    ) -> Result<Vec<T>, String> {
+        let len = self.usize()?;
+        // Don't trust the length for allocation
+        let mut items = Vec::with_capacity(len.min(1024));
+        for _ in 0..len {
+            items.push(f(self)?);
+        }
+        Ok(items)
+    }
+    fn span(&mut self) -> Result<usize, String> {
+        let index = self.usize()?;
+        (self.span_indices.get(index).copied()).ok_or_else(|| format!("Invalid span index {index}"))
+    }
+    fn code_span(&mut self) -> Result<CodeSpan, String> {
+        let source = self.usize()?;
+        let (path, input) = (self.sources.get(source).cloned())
+            .ok_or_else(|| format!("Invalid source index {source}"))?;
+        let mut loc = || -> Result<Loc, String> {
+            Ok(Loc {
+                char_pos: self.usize()?,
+                byte_pos: self.usize()?,
+                line: self.usize()?,
+                col: self.usize()?,
+            })
+        };
+        let start = loc()?;
+        let end = loc()?;
+        Ok(CodeSpan {
+            start,
+            end,
+            path,
+            input,
+        })
+    }
+    fn items(&mut self) -> Result<Vec<CompiledItem>, String> {
+        self.list(|dec| {
+            Ok(match dec.u8()? {
+                0 => CompiledItem::Run(dec.instrs()?),
+                1 => CompiledItem::Bind {
+                    name: dec.str()?.into(),
+                    private: dec.bool()?,
+                    instrs: dec.instrs()?,
+                    signature: dec.signature()?,
+                    run: dec.bool()?,
+                },
+                2 => CompiledItem::Scoped(dec.items()?),
+                3 => CompiledItem::Import {
+                    name: dec.str()?.into(),
+                    path: dec.str()?.into(),
+                },
+                tag => return Err(format!("Invalid item tag {tag}")),
+            })
+        })
+    }
+    fn signature(&mut self) -> Result<Signature, String> {
+        Ok(Signature::new(self.usize()?, self.usize()?))
+    }
+    fn instrs(&mut self) -> Result<Vec<Instr>, String> {
+        self.list(|dec| {
+            Ok(match dec.u8()? {
+                0 => Instr::push(dec.value()?),
+                1 => Instr::BeginArray,
+                2 => Instr::EndArray {
+                    constant: dec.bool()?,
+                    span: dec.span()?,
+                },
+                3 => Instr::Prim(dec.prim()?, dec.span()?),
+                4 => Instr::Call(dec.span()?),
+                5 => match optimize::fuse(dec.instrs()?).as_slice() {
+                    [fused @ Instr::Fused(_)] => fused.clone(),
+                    _ => return Err("Invalid fused instructions".into()),
+                },
+                tag => return Err(format!("Invalid instruction tag {tag}")),
+            })
+        })
+    }
+    fn prim(&mut self) -> Result<Primitive, String> {
+        let index = self.usize()?;
+        (self.prims.get(index).copied()).ok_or_else(|| format!("Invalid primitive index {index}"))
+    }
+    /// Read a shape and the number of elements it has
+    fn shape(&mut self) -> Result<(Shape, usize), String> {
+        let shape: Shape = self.list(Self::usize)?.into_iter().collect();
+        let len = (shape.iter())
+            .try_fold(1usize, |acc, &dim| acc.checked_mul(dim))
+            .filter(|&len| len <= self.bytes.len() - self.pos)
+            .ok_or("Invalid array shape in compiled file")?;
+        Ok((shape, len))
+    }
+    fn value(&mut self) -> Result<Value, String> {
+        let tag = self.u8()?;
+        let (shape, len) = self.shape()?;
+        Ok(match tag {
+            0 => {
+                let data = (0..len)
+                    .map(|_| Ok(f64::from_le_bytes(self.take(8)?.try_into().unwrap())))
+                    .collect::<Result<Vec<_>, String>>()?;
+                Array::new(shape, data).into()
+            }
+            1 => Array::new(shape, self.take(len)?.to_vec()).into(),
+            2 => {
+                let data = (0..len)
+                    .map(|_| {
+                        let c = self.usize()?;
+                        (u32::try_from(c).ok())
+                            .and_then(char::from_u32)
+                            .ok_or_else(|| format!("Invalid character {c}"))
+                    })
+                    .collect::<Result<Vec<_>, String>>()?;
+                Array::new(shape, data).into()
+            }
+            3 => {
+                let data = (0..len)
+                    .map(|_| self.function().map(Arc::new))
+                    .collect::<Result<Vec<_>, String>>()?;
+                Array::new(shape, data).into()
+            }
+            4 => {
+                let data = (0..len)
+                    .map(|_| Ok(i64::from_le_bytes(self.take(8)?.try_into().unwrap())))
+                    .collect::<Result<Vec<_>, String>>()?;
+                Array::new(shape, data).into()
+            }
+            tag => return Err(format!("Invalid value tag {tag}")),
+        })
+    }
+    fn function(&mut self) -> Result<Function, String> {
+        let id = self.function_id()?;
+        let kind = match self.u8()? {
+            0 => FunctionKind::Normal,
+            1 => FunctionKind::Format(self.list(Self::str)?.into()),
+            tag => return Err(format!("Invalid function kind {tag}")),
+        };
+        let instrs = self.instrs()?;
+        let signature = self.signature()?;
+        Ok(Function::new(id, instrs, kind, signature))
+    }
+    fn function_id(&mut self) -> Result<FunctionId, String> {
+        Ok(match self.u8()? {
+            0 => FunctionId::Named(self.str()?.into()),
+            1 => FunctionId::Anonymous(self.code_span()?),
+            2 => FunctionId::Primitive(self.prim()?),
+            3 => FunctionId::Constant,
+            4 => FunctionId::Main,
+            5 => FunctionId::Composed(self.list(Self::function_id)?),
+            tag => return Err(format!("Invalid function id tag {tag}")),
+        })
+    }
+}
@@ -74,6 +74,10 @@ impl<'a> BasicValue<'a> {
             BasicValue::Arr(match value {
                 Value::Num(n) => n.data.iter().map(|n| BasicValue::Num(*n)).collect(),
                 Value::Byte(b) => b.data.iter().map(|b| BasicValue::Num(*b as f64)).collect(),
+                Value::Mask(m) => (m.unpacked().data.iter())
+                    .map(|b| BasicValue::Num(*b as f64))
+                    .collect(),
+                Value::Int(i) => i.data.iter().map(|i| BasicValue::Num(*i as f64)).collect(),
                 Value::Char(c) => c.data.iter().map(|_| BasicValue::Other).collect(),
                 Value::Func(f) => f.data.iter().map(|f| BasicValue::Func(f)).collect(),
             })
@@ -105,6 +109,11 @@ impl<'a> VirtualEnv<'a> {
                 self.stack.push(BasicValue::Arr(items));
             }
             Instr::Call(_) => self.handle_call(false)?,
+            Instr::Fused(fused) => {
+                for instr in &fused.instrs {
+                    self.instr(instr)?;
+                }
+            }
             Instr::Prim(prim, _) => match prim {
                 Reduce | Scan => self.handle_mod(prim, Some(2), Some(1), 1, None)?,
                 Fold => self.handle_mod(prim, Some(2), Some(1), 2, None)?,
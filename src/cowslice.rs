@@ -32,6 +32,10 @@ impl<T> CowSlice<T> {
     pub fn truncate(&mut self, len: usize) {
         self.end = (self.start + len as u32).min(self.end);
     }
+    /// Whether the data can be modified without being copied
+    pub fn is_unique(&mut self) -> bool {
+        self.data.is_unique()
+    }
 }
 
 impl<T: Clone> CowSlice<T> {
@@ -119,7 +123,7 @@ impl<T: Clone> DerefMut for CowSlice<T> {
         if !self.data.is_unique() {
             *self = self.to_vec().into();
         }
-        self.data.make_mut()
+        &mut self.data.make_mut()[self.start as usize..self.end as usize]
     }
 }
 
@@ -133,6 +137,11 @@ fn cow_slice_deref_mut() {
     sub[1] = 5;
     assert_eq!(slice, [1, 7, 3, 4]);
     assert_eq!(sub, [7, 5]);
+
+    let mut sub = slice.slice(1..=2);
+    drop(slice);
+    sub[0] = 6;
+    assert_eq!(sub, [6, 3]);
 }
 
 impl<T: Clone> From<Vec<T>> for CowSlice<T> {
@@ -0,0 +1,609 @@
+//! A server for the Debug Adapter Protocol
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    io::{self, BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Arc,
+    },
+    thread,
+};
+
+use parking_lot::Mutex;
+use serde_json::{json, Value as Json};
+
+use crate::{
+    function::FunctionId,
+    lex::{CodeSpan, Span},
+    DebugHook, DebugPause, DebugStep, PauseReason, Uiua,
+};
+
+/// The only thread a program runs on, as far as the client is concerned
+const THREAD_ID: u64 = 1;
+/// The variables reference of the stack scope
+const STACK_REF: u64 = 1;
+
+/// Run the debug adapter on stdin and stdout
+///
+/// On unix, anything the program writes to stdout is sent to the client as output events.
+pub fn run_server() {
+    let (send, recv) = channel();
+    #[cfg(unix)]
+    let output: Box<dyn Write> = match redirect_stdout(send.clone()) {
+        Ok(file) => Box::new(file),
+        Err(e) => {
+            eprintln!("Failed to redirect stdout: {e}");
+            return;
+        }
+    };
+    #[cfg(not(unix))]
+    let output: Box<dyn Write> = Box::new(io::stdout());
+    if let Err(e) = Session::new(output, send).run(io::stdin(), recv) {
+        eprintln!("Debug adapter failed: {e}");
+    }
+}
+
+/// Run the debug adapter on the given input and output
+///
+/// Program output is not captured.
+pub fn serve(input: impl Read + Send + 'static, output: impl Write) -> io::Result<()> {
+    let (send, recv) = channel();
+    Session::new(output, send).run(input, recv)
+}
+
+/// Move stdout to a pipe that is forwarded as output events, and return the original stdout
+#[cfg(unix)]
+fn redirect_stdout(events: Sender<Input>) -> io::Result<fs::File> {
+    use std::os::fd::FromRawFd;
+    // Safety: the file descriptors are created here and each is owned by exactly one `File`
+    let (protocol, reader) = unsafe {
+        let protocol = libc::dup(1);
+        if protocol < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut fds = [0; 2];
+        if libc::pipe(fds.as_mut_ptr()) < 0 || libc::dup2(fds[1], 1) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        libc::close(fds[1]);
+        (
+            fs::File::from_raw_fd(protocol),
+            fs::File::from_raw_fd(fds[0]),
+        )
+    };
+    thread::spawn(move || {
+        let mut reader = reader;
+        let mut buffer = [0; 1024];
+        while let Ok(n @ 1..) = reader.read(&mut buffer) {
+            let text = String::from_utf8_lossy(&buffer[..n]).into_owned();
+            if events.send(Input::Output(text)).is_err() {
+                break;
+            }
+        }
+    });
+    Ok(protocol)
+}
+
+/// Something the session must respond to
+enum Input {
+    Message(Json),
+    Output(String),
+    Paused(Paused),
+    Finished(Result<Vec<String>, String>),
+    Eof,
+}
+
+/// A snapshot of a paused program
+struct Paused {
+    reason: PauseReason,
+    /// Stack frames, innermost first
+    frames: Vec<Json>,
+    /// Variables for stack values, top first
+    stack: Vec<Json>,
+}
+
+type Breakpoints = Arc<Mutex<HashMap<PathBuf, BTreeSet<usize>>>>;
+
+struct Session<W> {
+    output: W,
+    seq: u64,
+    events: Sender<Input>,
+    breakpoints: Breakpoints,
+    launch: Option<(PathBuf, bool)>,
+    configured: bool,
+    steps: Option<Sender<DebugStep>>,
+    paused: Option<Paused>,
+}
+
+impl<W: Write> Session<W> {
+    fn new(output: W, events: Sender<Input>) -> Self {
+        Session {
+            output,
+            seq: 0,
+            events,
+            breakpoints: Breakpoints::default(),
+            launch: None,
+            configured: false,
+            steps: None,
+            paused: None,
+        }
+    }
+    fn run(mut self, input: impl Read + Send + 'static, inputs: Receiver<Input>) -> io::Result<()> {
+        let events = self.events.clone();
+        thread::spawn(move || {
+            let mut input = BufReader::new(input);
+            while let Ok(Some(message)) = read_message(&mut input) {
+                if events.send(Input::Message(message)).is_err() {
+                    return;
+                }
+            }
+            _ = events.send(Input::Eof);
+        });
+        while let Ok(input) = inputs.recv() {
+            match input {
+                Input::Message(message) => {
+                    if !self.request(message)? {
+                        break;
+                    }
+                }
+                Input::Output(text) => self.output_event("stdout", &text)?,
+                Input::Paused(paused) => {
+                    let reason = match paused.reason {
+                        PauseReason::Entry => "entry",
+                        PauseReason::Breakpoint => "breakpoint",
+                        PauseReason::Step => "step",
+                    };
+                    self.paused = Some(paused);
+                    self.event(
+                        "stopped",
+                        json!({
+                            "reason": reason,
+                            "threadId": THREAD_ID,
+                            "allThreadsStopped": true,
+                        }),
+                    )?;
+                }
+                Input::Finished(res) => {
+                    self.steps = None;
+                    self.paused = None;
+                    let exit_code = match res {
+                        Ok(stack) => {
+                            for value in stack {
+                                self.output_event("stdout", &format!("{value}\n"))?;
+                            }
+                            0
+                        }
+                        Err(e) => {
+                            self.output_event("stderr", &format!("{e}\n"))?;
+                            1
+                        }
+                    };
+                    self.event("exited", json!({ "exitCode": exit_code }))?;
+                    self.event("terminated", json!({}))?;
+                }
+                Input::Eof => break,
+            }
+        }
+        if let Some(steps) = self.steps.take() {
+            _ = steps.send(DebugStep::Stop);
+        }
+        Ok(())
+    }
+    /// Handle a request, returning whether the session should continue
+    fn request(&mut self, message: Json) -> io::Result<bool> {
+        let command = message["command"].as_str().unwrap_or_default().to_string();
+        let args = &message["arguments"];
+        let seq = message["seq"].as_u64().unwrap_or(0);
+        let res = match command.as_str() {
+            "initialize" => {
+                self.respond(
+                    seq,
+                    &command,
+                    Ok(json!({ "supportsConfigurationDoneRequest": true })),
+                )?;
+                return self.event("initialized", json!({})).map(|_| true);
+            }
+            "launch" => match args["program"].as_str() {
+                Some(program) => {
+                    let program = fs::canonicalize(program).unwrap_or_else(|_| program.into());
+                    let stop_on_entry = args["stopOnEntry"].as_bool().unwrap_or(false);
+                    self.launch = Some((program, stop_on_entry));
+                    self.start();
+                    Ok(Json::Null)
+                }
+                None => Err("Launch requires a `program` path".to_string()),
+            },
+            "configurationDone" => {
+                self.configured = true;
+                self.start();
+                Ok(Json::Null)
+            }
+            "setBreakpoints" => match args["source"]["path"].as_str() {
+                Some(path) => {
+                    let lines: BTreeSet<usize> = (args["breakpoints"].as_array().into_iter())
+                        .flatten()
+                        .filter_map(|bp| bp["line"].as_u64())
+                        .map(|line| line as usize)
+                        .collect();
+                    let breakpoints: Vec<Json> = (lines.iter())
+                        .map(|line| json!({ "verified": true, "line": line }))
+                        .collect();
+                    let path = fs::canonicalize(path).unwrap_or_else(|_| path.into());
+                    self.breakpoints.lock().insert(path, lines);
+                    Ok(json!({ "breakpoints": breakpoints }))
+                }
+                None => Err("Breakpoints require a source path".into()),
+            },
+            "threads" => Ok(json!({ "threads": [{ "id": THREAD_ID, "name": "main" }] })),
+            "stackTrace" => match &self.paused {
+                Some(paused) => Ok(json!({
+                    "stackFrames": paused.frames,
+                    "totalFrames": paused.frames.len(),
+                })),
+                None => Err("The program is not paused".into()),
+            },
+            "scopes" => Ok(json!({
+                "scopes": [{
+                    "name": "Stack",
+                    "variablesReference": STACK_REF,
+                    "expensive": false,
+                }]
+            })),
+            "variables" => match &self.paused {
+                Some(paused) if args["variablesReference"].as_u64() == Some(STACK_REF) => {
+                    Ok(json!({ "variables": paused.stack }))
+                }
+                Some(_) => Ok(json!({ "variables": [] })),
+                None => Err("The program is not paused".into()),
+            },
+            "continue" | "next" | "stepIn" | "stepOut" => {
+                let step = match command.as_str() {
+                    "continue" => DebugStep::Continue,
+                    "next" => DebugStep::Over,
+                    "stepIn" => DebugStep::Into,
+                    _ => DebugStep::Out,
+                };
+                match (&self.steps, self.paused.take()) {
+                    (Some(steps), Some(_)) => {
+                        _ = steps.send(step);
+                        Ok(json!({ "allThreadsContinued": true }))
+                    }
+                    _ => Err("The program is not paused".into()),
+                }
+            }
+            "terminate" | "disconnect" => {
+                if let Some(steps) = &self.steps {
+                    _ = steps.send(DebugStep::Stop);
+                }
+                self.paused = None;
+                self.respond(seq, &command, Ok(Json::Null))?;
+                return Ok(command == "terminate");
+            }
+            _ => Err(format!("Unsupported request `{command}`")),
+        };
+        self.respond(seq, &command, res)?;
+        Ok(true)
+    }
+    /// Start the program once it has been launched and configured
+    fn start(&mut self) {
+        if !self.configured || self.steps.is_some() {
+            return;
+        }
+        let Some((program, stop_on_entry)) = self.launch.clone() else {
+            return;
+        };
+        let (send, recv) = channel();
+        self.steps = Some(send);
+        let hook = DapHook {
+            breakpoints: self.breakpoints.clone(),
+            canonical: HashMap::new(),
+            events: self.events.clone(),
+            steps: recv,
+            stop_on_entry,
+        };
+        let events = self.events.clone();
+        thread::spawn(move || {
+            let mut rt = Uiua::with_native_sys().with_debug_hook(hook);
+            let res = match rt.load_file(&program) {
+                Ok(rt) => Ok(rt.take_stack().iter().map(|v| v.show()).collect()),
+                Err(e) => Err(e.show(false)),
+            };
+            _ = events.send(Input::Finished(res));
+        });
+    }
+    fn send(&mut self, mut message: Json) -> io::Result<()> {
+        self.seq += 1;
+        message["seq"] = self.seq.into();
+        let body = message.to_string();
+        write!(self.output, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+        self.output.flush()
+    }
+    fn respond(&mut self, seq: u64, command: &str, res: Result<Json, String>) -> io::Result<()> {
+        let message = match res {
+            Ok(Json::Null) => json!({
+                "type": "response",
+                "request_seq": seq,
+                "success": true,
+                "command": command,
+            }),
+            Ok(body) => json!({
+                "type": "response",
+                "request_seq": seq,
+                "success": true,
+                "command": command,
+                "body": body,
+            }),
+            Err(message) => json!({
+                "type": "response",
+                "request_seq": seq,
+                "success": false,
+                "command": command,
+                "message": message,
+            }),
+        };
+        self.send(message)
+    }
+    fn event(&mut self, event: &str, body: Json) -> io::Result<()> {
+        self.send(json!({ "type": "event", "event": event, "body": body }))
+    }
+    fn output_event(&mut self, category: &str, output: &str) -> io::Result<()> {
+        self.event("output", json!({ "category": category, "output": output }))
+    }
+}
+
+/// Read a message with a `Content-Length` header
+///
+/// Returns `None` at the end of the input.
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<Json>> {
+    let mut len = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            if len.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some(n) = line.strip_prefix("Content-Length:") {
+            len = n.trim().parse::<usize>().ok();
+        }
+    }
+    let mut body = vec![0; len.unwrap()];
+    input.read_exact(&mut body)?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+struct DapHook {
+    breakpoints: Breakpoints,
+    /// Canonical forms of span paths
+    canonical: HashMap<PathBuf, PathBuf>,
+    events: Sender<Input>,
+    steps: Receiver<DebugStep>,
+    stop_on_entry: bool,
+}
+
+impl DebugHook for DapHook {
+    fn entry(&mut self) -> DebugStep {
+        if self.stop_on_entry {
+            DebugStep::Into
+        } else {
+            DebugStep::Continue
+        }
+    }
+    fn is_breakpoint(&mut self, path: Option<&Path>, line: usize) -> bool {
+        let Some(path) = path else {
+            return false;
+        };
+        let path = (self.canonical.entry(path.into()))
+            .or_insert_with(|| fs::canonicalize(path).unwrap_or_else(|_| path.into()));
+        (self.breakpoints.lock())
+            .get(path)
+            .is_some_and(|lines| lines.contains(&line))
+    }
+    fn pause(&mut self, pause: &DebugPause) -> DebugStep {
+        // The innermost frame is at the paused instruction,
+        // and each outer frame is where the next inner one was called
+        let mut locations = vec![Some(pause.span)];
+        locations.extend(
+            pause
+                .frames
+                .iter()
+                .skip(1)
+                .rev()
+                .map(|frame| match &frame.span {
+                    Span::Code(span) => Some(span),
+                    Span::Builtin => None,
+                }),
+        );
+        let frames = (pause.frames.iter().rev())
+            .zip(locations)
+            .enumerate()
+            .map(|(i, (frame, span))| {
+                let name = match &frame.function {
+                    FunctionId::Named(name) => name.to_string(),
+                    id => id.to_string(),
+                };
+                stack_frame(i, name, span)
+            })
+            .collect();
+        let stack = (pause.stack.iter().rev())
+            .enumerate()
+            .map(|(i, value)| {
+                json!({
+                    "name": (i + 1).to_string(),
+                    "value": format!("{value:?}"),
+                    "type": value.type_name(),
+                    "variablesReference": 0,
+                })
+            })
+            .collect();
+        let paused = Paused {
+            reason: pause.reason,
+            frames,
+            stack,
+        };
+        if self.events.send(Input::Paused(paused)).is_err() {
+            return DebugStep::Stop;
+        }
+        self.steps.recv().unwrap_or(DebugStep::Stop)
+    }
+}
+
+fn stack_frame(id: usize, name: String, span: Option<&CodeSpan>) -> Json {
+    let mut frame = json!({ "id": id, "name": name, "line": 0, "column": 0 });
+    if let Some(span) = span {
+        frame["line"] = span.start.line.into();
+        frame["column"] = span.start.col.into();
+        frame["endLine"] = span.end.line.into();
+        frame["endColumn"] = span.end.col.into();
+        if let Some(path) = &span.path {
+            frame["source"] = json!({ "path": path.to_string_lossy() });
+        }
+    }
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, io::Cursor, sync::mpsc::channel};
+
+    use super::*;
+
+    /// One end of an in-memory pipe
+    struct ChannelReader {
+        recv: Receiver<Vec<u8>>,
+        buffer: Cursor<Vec<u8>>,
+    }
+
+    impl Read for ChannelReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.buffer.position() as usize == self.buffer.get_ref().len() {
+                match self.recv.recv() {
+                    Ok(bytes) => self.buffer = Cursor::new(bytes),
+                    Err(_) => return Ok(0),
+                }
+            }
+            self.buffer.read(buf)
+        }
+    }
+
+    struct ChannelWriter(Sender<Vec<u8>>);
+
+    impl Write for ChannelWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            _ = self.0.send(buf.to_vec());
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn pipe() -> (ChannelWriter, ChannelReader) {
+        let (send, recv) = channel();
+        let reader = ChannelReader {
+            recv,
+            buffer: Cursor::new(Vec::new()),
+        };
+        (ChannelWriter(send), reader)
+    }
+
+    struct Client {
+        seq: u64,
+        input: ChannelWriter,
+        output: BufReader<ChannelReader>,
+    }
+
+    impl Client {
+        fn request(&mut self, command: &str, arguments: Json) -> Json {
+            self.seq += 1;
+            let body = json!({
+                "seq": self.seq,
+                "type": "request",
+                "command": command,
+                "arguments": arguments,
+            })
+            .to_string();
+            write!(self.input, "Content-Length: {}\r\n\r\n{body}", body.len()).unwrap();
+            let seq = self.seq;
+            self.expect(|message| message["request_seq"] == seq)
+        }
+        /// Wait for a message, skipping any that don't match
+        fn expect(&mut self, f: impl Fn(&Json) -> bool) -> Json {
+            loop {
+                let message = read_message(&mut self.output).unwrap().unwrap();
+                if f(&message) {
+                    return message;
+                }
+            }
+        }
+        fn expect_event(&mut self, event: &str) -> Json {
+            self.expect(|message| message["event"] == event)
+        }
+    }
+
+    #[test]
+    fn dap() {
+        let path = env::temp_dir().join(format!("uiua_dap_test_{}.ua", std::process::id()));
+        fs::write(&path, "F ← +1\nX ← F 5\nF X").unwrap();
+        let (input, server_input) = pipe();
+        let (server_output, output) = pipe();
+        let server = thread::spawn(move || serve(server_input, server_output));
+        let mut client = Client {
+            seq: 0,
+            input,
+            output: BufReader::new(output),
+        };
+
+        let res = client.request("initialize", json!({ "adapterID": "uiua" }));
+        assert_eq!(res["success"], true);
+        client.expect_event("initialized");
+        let res = client.request("launch", json!({ "program": path }));
+        assert_eq!(res["success"], true);
+        let res = client.request(
+            "setBreakpoints",
+            json!({ "source": { "path": path }, "breakpoints": [{ "line": 3 }] }),
+        );
+        assert_eq!(res["body"]["breakpoints"][0]["verified"], true);
+        client.request("configurationDone", json!({}));
+
+        let stopped = client.expect_event("stopped");
+        assert_eq!(stopped["body"]["reason"], "breakpoint");
+        let res = client.request("stackTrace", json!({ "threadId": THREAD_ID }));
+        let frames = &res["body"]["stackFrames"];
+        assert_eq!(frames[0]["line"], 3);
+        assert_eq!(frames[0]["source"]["path"], path.to_string_lossy().as_ref());
+        let res = client.request("variables", json!({ "variablesReference": STACK_REF }));
+        let variables = res["body"]["variables"].as_array().unwrap();
+        assert_eq!(variables.len(), 2);
+        assert_eq!(variables[1]["value"], "6");
+
+        client.request("stepIn", json!({ "threadId": THREAD_ID }));
+        let stopped = client.expect_event("stopped");
+        assert_eq!(stopped["body"]["reason"], "step");
+        let res = client.request("stackTrace", json!({ "threadId": THREAD_ID }));
+        let frames = res["body"]["stackFrames"].as_array().unwrap();
+        assert_eq!(frames[0]["name"], "F");
+        assert_eq!(frames[0]["line"], 1);
+        assert_eq!(frames[1]["line"], 3);
+
+        client.request("continue", json!({ "threadId": THREAD_ID }));
+        let output = client.expect_event("output");
+        assert_eq!(output["body"]["output"], "7\n");
+        let exited = client.expect_event("exited");
+        assert_eq!(exited["body"]["exitCode"], 0);
+        client.expect_event("terminated");
+        client.request("disconnect", json!({}));
+        server.join().unwrap().unwrap();
+        _ = fs::remove_file(&path);
+    }
+}
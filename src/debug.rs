@@ -0,0 +1,96 @@
+//! Types for stepping through code with a debugger
+
+use std::{path::Path, sync::Arc};
+
+use crate::{
+    function::FunctionId,
+    lex::{CodeSpan, Span},
+    value::Value,
+};
+
+/// How execution should continue after a debugger pause
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum DebugStep {
+    /// Run until the next breakpoint
+    #[default]
+    Continue,
+    /// Pause at the next instruction, entering any function it calls
+    Into,
+    /// Pause at the next instruction in the current function or one of its callers
+    Over,
+    /// Pause at the next instruction after the current function returns
+    Out,
+    /// Stop execution with an error
+    Stop,
+}
+
+/// Why execution paused
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PauseReason {
+    /// The program is about to run its first instruction
+    Entry,
+    /// A line with a breakpoint was reached
+    Breakpoint,
+    /// A step finished
+    Step,
+}
+
+/// A function in the call stack at a debugger pause
+#[derive(Debug, Clone)]
+pub struct DebugFrame {
+    pub function: FunctionId,
+    /// The span the function was called from
+    pub span: Span,
+}
+
+/// The state of a program paused by a debugger
+#[derive(Debug)]
+pub struct DebugPause<'a> {
+    pub reason: PauseReason,
+    /// The span of the instruction about to be run
+    pub span: &'a CodeSpan,
+    /// The stack, with the top value last
+    pub stack: &'a [Value],
+    /// The call stack, with the innermost function last
+    pub frames: Vec<DebugFrame>,
+}
+
+/// A handler that controls a program being debugged
+///
+/// Set one with [`Uiua::with_debug_hook`](crate::Uiua::with_debug_hook).
+/// Execution can only pause on instructions that have a span in code.
+pub trait DebugHook: Send {
+    /// Decide how to start execution
+    ///
+    /// Return [`DebugStep::Into`] to pause at the first instruction.
+    fn entry(&mut self) -> DebugStep {
+        DebugStep::Continue
+    }
+    /// Check if there is a breakpoint on a line
+    ///
+    /// This is called whenever execution moves to a new line. Lines start at 1.
+    fn is_breakpoint(&mut self, path: Option<&Path>, line: usize) -> bool;
+    /// Handle a pause, returning once the program should continue
+    fn pause(&mut self, pause: &DebugPause) -> DebugStep;
+}
+
+pub(crate) struct Debugger {
+    pub hook: Box<dyn DebugHook>,
+    /// The last step returned by the hook, or `None` before the first instruction
+    pub step: Option<DebugStep>,
+    /// The call depth of the last pause
+    pub depth: usize,
+    /// The line of the last instruction
+    pub line: Option<(Option<Arc<Path>>, usize)>,
+}
+
+impl Debugger {
+    pub fn new(hook: impl DebugHook + 'static) -> Self {
+        Debugger {
+            hook: Box::new(hook),
+            step: None,
+            depth: 0,
+            line: None,
+        }
+    }
+}
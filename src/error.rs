@@ -30,6 +30,10 @@ pub enum UiuaError {
     Throw(Box<Value>, Span),
     Break(usize, Span),
     Timeout(Span),
+    MemoryLimit(Span),
+    StackLimit(Span),
+    ArrayLimit(Span),
+    RecursionLimit(Span),
     Fill(Box<Self>),
 }
 
@@ -76,6 +80,10 @@ impl fmt::Display for UiuaError {
             UiuaError::Throw(value, span) => write!(f, "{span}: {value}"),
             UiuaError::Break(_, span) => write!(f, "{span}: break outside of loop"),
             UiuaError::Timeout(_) => write!(f, "Maximum execution time exceeded"),
+            UiuaError::MemoryLimit(_) => write!(f, "Maximum memory usage exceeded"),
+            UiuaError::StackLimit(_) => write!(f, "Maximum stack size exceeded"),
+            UiuaError::ArrayLimit(_) => write!(f, "Maximum array size exceeded"),
+            UiuaError::RecursionLimit(_) => write!(f, "Maximum recursion depth exceeded"),
             UiuaError::Fill(error) => error.fmt(f),
         }
     }
@@ -204,15 +212,162 @@ impl UiuaError {
             }
             UiuaError::Throw(message, span) => report([(&message, span.clone())], color),
             UiuaError::Break(_, span) => report([("break outside of loop", span.clone())], color),
-            UiuaError::Timeout(span) => {
-                report([("Maximum execution time exceeded", span.clone())], color)
-            }
+            UiuaError::Timeout(span)
+            | UiuaError::MemoryLimit(span)
+            | UiuaError::StackLimit(span)
+            | UiuaError::ArrayLimit(span)
+            | UiuaError::RecursionLimit(span) => report([(self.to_string(), span.clone())], color),
             UiuaError::Fill(error) => error.show(color),
             UiuaError::Load(..) | UiuaError::Format(..) => self.to_string(),
         }
     }
 }
 
+/// The kind of an [`ErrorReport`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ErrorKind {
+    Load,
+    Format,
+    Parse,
+    Run,
+    Throw,
+    Break,
+    Timeout,
+    MemoryLimit,
+    StackLimit,
+    ArrayLimit,
+    RecursionLimit,
+    Fill,
+}
+
+/// A machine-readable description of a single error
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ErrorReport {
+    pub kind: ErrorKind,
+    pub message: String,
+    /// Where the error occurred, if it occurred in code
+    pub span: Option<ReportSpan>,
+    /// The functions the error passed through, innermost first
+    pub trace: Vec<ReportFrame>,
+}
+
+/// A file and a range of lines and columns in it
+///
+/// Lines and columns start at 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ReportSpan {
+    pub file: Option<String>,
+    pub start_line: usize,
+    pub start_col: usize,
+    pub end_line: usize,
+    pub end_col: usize,
+}
+
+/// A function in an [`ErrorReport`]'s trace
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ReportFrame {
+    pub function: String,
+    pub span: Option<ReportSpan>,
+}
+
+impl ReportSpan {
+    fn new(span: &Span) -> Option<Self> {
+        let Span::Code(span) = span else {
+            return None;
+        };
+        Some(ReportSpan {
+            file: (span.path.as_ref()).map(|path| path.to_string_lossy().into_owned()),
+            start_line: span.start.line,
+            start_col: span.start.col,
+            end_line: span.end.line,
+            end_col: span.end.col,
+        })
+    }
+}
+
+impl UiuaError {
+    /// Get machine-readable descriptions of the error
+    ///
+    /// Parse errors produce one report per error. All other errors produce a single report.
+    pub fn reports(&self) -> Vec<ErrorReport> {
+        let report = |kind, message: String, span: Option<&Span>| ErrorReport {
+            kind,
+            message,
+            span: span.and_then(ReportSpan::new),
+            trace: Vec::new(),
+        };
+        match self {
+            UiuaError::Load(..) => vec![report(ErrorKind::Load, self.to_string(), None)],
+            UiuaError::Format(..) => vec![report(ErrorKind::Format, self.to_string(), None)],
+            UiuaError::Parse(errors) => (errors.iter())
+                .map(|error| {
+                    let span = error.span.clone().into();
+                    report(ErrorKind::Parse, error.value.to_string(), Some(&span))
+                })
+                .collect(),
+            UiuaError::Run(error) => {
+                vec![report(
+                    ErrorKind::Run,
+                    error.value.clone(),
+                    Some(&error.span),
+                )]
+            }
+            UiuaError::Traced { error, trace } => {
+                let mut reports = error.reports();
+                for report in &mut reports {
+                    let frames = trace.iter().filter(|frame| frame.id != FunctionId::Main);
+                    report.trace.extend(frames.map(|frame| ReportFrame {
+                        function: match &frame.id {
+                            FunctionId::Named(name) => name.to_string(),
+                            id => id.to_string(),
+                        },
+                        span: ReportSpan::new(&frame.span),
+                    }));
+                }
+                reports
+            }
+            UiuaError::Throw(value, span) => {
+                vec![report(ErrorKind::Throw, value.to_string(), Some(span))]
+            }
+            UiuaError::Break(_, span) => vec![report(
+                ErrorKind::Break,
+                "break outside of loop".into(),
+                Some(span),
+            )],
+            UiuaError::Timeout(span) => {
+                vec![report(ErrorKind::Timeout, self.to_string(), Some(span))]
+            }
+            UiuaError::MemoryLimit(span) => {
+                vec![report(ErrorKind::MemoryLimit, self.to_string(), Some(span))]
+            }
+            UiuaError::StackLimit(span) => {
+                vec![report(ErrorKind::StackLimit, self.to_string(), Some(span))]
+            }
+            UiuaError::ArrayLimit(span) => {
+                vec![report(ErrorKind::ArrayLimit, self.to_string(), Some(span))]
+            }
+            UiuaError::RecursionLimit(span) => {
+                vec![report(
+                    ErrorKind::RecursionLimit,
+                    self.to_string(),
+                    Some(span),
+                )]
+            }
+            UiuaError::Fill(error) => {
+                let mut reports = error.reports();
+                for report in &mut reports {
+                    report.kind = ErrorKind::Fill;
+                }
+                reports
+            }
+        }
+    }
+}
+
 fn report<I, T>(errors: I, complex_output: bool) -> String
 where
     I: IntoIterator<Item = (T, Span)>,
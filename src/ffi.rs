@@ -0,0 +1,262 @@
+//! Types for calling functions in C shared libraries
+
+use std::{fmt, str::FromStr};
+
+/// A C type that can appear in a foreign function signature
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FfiType {
+    /// No value. Only valid as a return type.
+    Void,
+    /// A C `int`
+    Int,
+    /// A C `long`
+    Long,
+    /// A C `double`
+    Double,
+    /// An untyped pointer, represented as a number
+    Ptr,
+    /// A pointer to a byte buffer that the function may modify. Only valid as an argument type.
+    Bytes,
+    /// A pointer to a null-terminated string
+    Str,
+}
+
+impl FromStr for FfiType {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "void" => FfiType::Void,
+            "int" => FfiType::Int,
+            "long" => FfiType::Long,
+            "double" => FfiType::Double,
+            "ptr" => FfiType::Ptr,
+            "bytes" => FfiType::Bytes,
+            "str" => FfiType::Str,
+            _ => return Err(format!("Unknown ffi type `{s}`")),
+        })
+    }
+}
+
+impl fmt::Display for FfiType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FfiType::Void => write!(f, "void"),
+            FfiType::Int => write!(f, "int"),
+            FfiType::Long => write!(f, "long"),
+            FfiType::Double => write!(f, "double"),
+            FfiType::Ptr => write!(f, "ptr"),
+            FfiType::Bytes => write!(f, "bytes"),
+            FfiType::Str => write!(f, "str"),
+        }
+    }
+}
+
+/// The location and signature of a foreign function
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FfiSpec {
+    /// The path to the shared library
+    pub lib: String,
+    /// The return type
+    pub result: FfiType,
+    /// The name of the function's symbol
+    pub name: String,
+    /// The argument types
+    pub args: Vec<FfiType>,
+}
+
+impl FfiSpec {
+    /// Parse a spec from a library path, a return type, a symbol name, and any number of argument types
+    pub fn from_strings(strings: &[String]) -> Result<Self, String> {
+        let [lib, result, name, args @ ..] = strings else {
+            return Err(
+                "An ffi spec must have a library path, a return type, and a function name".into(),
+            );
+        };
+        let result: FfiType = result.parse()?;
+        if result == FfiType::Bytes {
+            return Err("`bytes` cannot be used as a return type".into());
+        }
+        let args = (args.iter())
+            .map(|arg| match arg.parse()? {
+                FfiType::Void => Err("`void` cannot be used as an argument type".to_string()),
+                ty => Ok(ty),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(FfiSpec {
+            lib: lib.clone(),
+            result,
+            name: name.clone(),
+            args,
+        })
+    }
+}
+
+/// An argument to a foreign function
+#[derive(Debug, Clone, PartialEq)]
+pub enum FfiArg {
+    Int(i32),
+    Long(i64),
+    Double(f64),
+    Ptr(usize),
+    Bytes(Vec<u8>),
+    Str(String),
+}
+
+/// Call a function in a loaded library
+///
+/// Returns the function's return value and the contents of any `bytes` arguments after the call.
+#[cfg(feature = "ffi")]
+pub(crate) fn call(
+    lib: &libloading::Library,
+    spec: &FfiSpec,
+    args: Vec<FfiArg>,
+) -> Result<(crate::value::Value, Vec<Vec<u8>>), String> {
+    use std::ffi::{c_char, c_int, c_long, c_void, CStr, CString};
+
+    use libffi::middle::{Arg, Cif, CodePtr, Type};
+
+    use crate::value::Value;
+
+    enum Storage {
+        Int(c_int),
+        Long(c_long),
+        Double(f64),
+        Ptr(*mut c_void),
+    }
+
+    fn ffi_type(ty: FfiType) -> Type {
+        match ty {
+            FfiType::Void => Type::void(),
+            FfiType::Int => Type::c_int(),
+            FfiType::Long => Type::c_long(),
+            FfiType::Double => Type::f64(),
+            FfiType::Ptr | FfiType::Bytes | FfiType::Str => Type::pointer(),
+        }
+    }
+
+    if args.len() != spec.args.len() {
+        return Err(format!(
+            "`{}` expects {} arguments, but {} were given",
+            spec.name,
+            spec.args.len(),
+            args.len()
+        ));
+    }
+
+    // Move buffers and strings somewhere they will outlive the call
+    let mut buffers = Vec::new();
+    let mut strings = Vec::new();
+    let mut storage = Vec::with_capacity(args.len());
+    for arg in args {
+        storage.push(match arg {
+            FfiArg::Int(i) => Storage::Int(i as c_int),
+            FfiArg::Long(i) => Storage::Long(i as c_long),
+            FfiArg::Double(f) => Storage::Double(f),
+            FfiArg::Ptr(p) => Storage::Ptr(p as *mut c_void),
+            FfiArg::Bytes(mut bytes) => {
+                let ptr = bytes.as_mut_ptr() as *mut c_void;
+                buffers.push(bytes);
+                Storage::Ptr(ptr)
+            }
+            FfiArg::Str(s) => {
+                let s = CString::new(s).map_err(|_| "Strings passed to ffi cannot contain null")?;
+                let ptr = s.as_ptr() as *mut c_void;
+                strings.push(s);
+                Storage::Ptr(ptr)
+            }
+        });
+    }
+    let ffi_args: Vec<Arg> = (storage.iter())
+        .map(|s| match s {
+            Storage::Int(i) => Arg::new(i),
+            Storage::Long(i) => Arg::new(i),
+            Storage::Double(f) => Arg::new(f),
+            Storage::Ptr(p) => Arg::new(p),
+        })
+        .collect();
+
+    let cif = Cif::new(
+        spec.args.iter().copied().map(ffi_type),
+        ffi_type(spec.result),
+    );
+    // Safety: there is no way to verify that the symbol is a function with the given signature.
+    // This is the responsibility of the caller.
+    let value = unsafe {
+        let symbol = lib
+            .get::<unsafe extern "C" fn()>(spec.name.as_bytes())
+            .map_err(|e| e.to_string())?;
+        let fun = CodePtr::from_fun(*symbol);
+        match spec.result {
+            FfiType::Void => {
+                // Integer results are written as at least a full register
+                cif.call::<u64>(fun, &ffi_args);
+                Value::default()
+            }
+            FfiType::Int => (cif.call::<i64>(fun, &ffi_args) as c_int as f64).into(),
+            FfiType::Long => (cif.call::<c_long>(fun, &ffi_args) as f64).into(),
+            FfiType::Double => cif.call::<f64>(fun, &ffi_args).into(),
+            FfiType::Ptr => (cif.call::<*mut c_void>(fun, &ffi_args) as usize as f64).into(),
+            FfiType::Str => {
+                let s = cif.call::<*const c_char>(fun, &ffi_args);
+                if s.is_null() {
+                    return Err(format!("`{}` returned a null string", spec.name));
+                }
+                CStr::from_ptr(s).to_string_lossy().into_owned().into()
+            }
+            FfiType::Bytes => return Err("`bytes` cannot be used as a return type".into()),
+        }
+    };
+    drop(strings);
+    Ok((value, buffers))
+}
+
+#[cfg(all(test, feature = "ffi", unix))]
+mod tests {
+    use std::{env, process::Command};
+
+    use crate::Uiua;
+
+    #[test]
+    fn ffi() {
+        let lib = env::temp_dir().join(format!("uiua_ffi_test_{}.so", std::process::id()));
+        let status = Command::new("cc")
+            .args(["-shared", "-fPIC", "-o"])
+            .arg(&lib)
+            .arg("tests/ffi/ffi_test.c")
+            .status();
+        let Ok(status) = status else {
+            eprintln!("Skipping ffi test because cc is not available");
+            return;
+        };
+        assert!(status.success());
+        let code = format!(
+            r#"
+Lib ← "{}"
+Call ← &ffi ⊂□Lib
+⍤.≅5 Call {{"int" "add" "int" "int"}} [2 3]
+⍤.≅¯7 Call {{"long" "negate" "long"}} [7]
+⍤.≅2.5 Call {{"double" "mean" "double" "double"}} [2 3]
+⍤.≅4 Call {{"int" "str_len" "str"}} {{"uiua"}}
+⍤.≅"Hello, World!" Call {{"str" "greeting"}} []
+Rev ← Call {{"void" "reverse" "bytes" "int"}} {{[1 2 3] 3}}
+⍤.≅[3 2 1] !⊢↘1 Rev
+"#,
+            lib.display()
+        );
+        let res = Uiua::with_native_sys().load_str(&code).map(drop);
+        // Ints that do not fit in a C `int` are an error rather than wrapping
+        let big = format!(
+            r#"&ffi ⊂□"{}" {{"int" "add" "int" "int"}} [3000000000 1]"#,
+            lib.display()
+        );
+        let big_res = Uiua::with_native_sys().load_str(&big).map(drop);
+        _ = std::fs::remove_file(&lib);
+        if let Err(e) = res {
+            panic!("{}", e.show(false));
+        }
+        match big_res {
+            Ok(()) => panic!("out of range int should fail"),
+            Err(e) => assert!(e.to_string().contains("32 bits"), "{e}"),
+        }
+    }
+}
@@ -167,12 +167,17 @@ impl<'a> Formatter<'a> {
             }
             Item::Binding(binding) => {
                 self.output.push_str(&binding.name.value);
-                self.output.push_str(" ← ");
+                self.output
+                    .push_str(if binding.private { " ←? " } else { " ← " });
                 if let Some(sig) = &binding.signature {
                     self.format_signature(sig.value);
                 }
                 self.format_words(&binding.words, true, 0);
             }
+            Item::Import(import) => {
+                self.output.push_str(&import.name.value);
+                self.output.push_str(&format!(" ? {:?}", import.path.value));
+            }
             Item::ExtraNewlines(_) => {}
         }
     }
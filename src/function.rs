@@ -6,17 +6,22 @@ use std::{
 };
 
 use crate::{
-    check::instrs_signature, lex::CodeSpan, primitive::Primitive, value::Value, Ident, Uiua,
-    UiuaResult,
+    check::instrs_signature, lex::CodeSpan, optimize::Fused, primitive::Primitive, value::Value,
+    Ident, Uiua, UiuaResult,
 };
 
 #[derive(Debug, Clone)]
 pub enum Instr {
     Push(Box<Value>),
     BeginArray,
-    EndArray { constant: bool, span: usize },
+    EndArray {
+        constant: bool,
+        span: usize,
+    },
     Prim(Primitive, usize),
     Call(usize),
+    /// Instructions fused into a kernel by the optimizer
+    Fused(Arc<Fused>),
 }
 
 impl PartialEq for Instr {
@@ -27,6 +32,7 @@ impl PartialEq for Instr {
             (Self::EndArray { .. }, Self::EndArray { .. }) => true,
             (Self::Prim(a, s_span), Self::Prim(b, b_span)) => a == b && s_span == b_span,
             (Self::Call(a), Self::Call(b)) => a == b,
+            (Self::Fused(a), Self::Fused(b)) => a.instrs == b.instrs,
             _ => false,
         }
     }
@@ -50,6 +56,7 @@ impl Ord for Instr {
                 a.cmp(b).then_with(|| a_span.cmp(b_span))
             }
             (Self::Call(a), Self::Call(b)) => a.cmp(b),
+            (Self::Fused(a), Self::Fused(b)) => a.instrs.cmp(&b.instrs),
             (Self::Push(_), _) => Ordering::Less,
             (Self::BeginArray, Self::Push(_)) => Ordering::Greater,
             (Self::BeginArray, _) => Ordering::Less,
@@ -59,7 +66,9 @@ impl Ord for Instr {
                 Ordering::Greater
             }
             (Self::Prim(_, _), _) => Ordering::Less,
+            (Self::Call(_), Self::Fused(_)) => Ordering::Less,
             (Self::Call(_), _) => Ordering::Greater,
+            (Self::Fused(_), _) => Ordering::Greater,
         }
     }
 }
@@ -82,6 +91,10 @@ impl Hash for Instr {
                 4u8.hash(state);
                 span.hash(state);
             }
+            Instr::Fused(fused) => {
+                5u8.hash(state);
+                fused.instrs.hash(state);
+            }
         }
     }
 }
@@ -106,6 +119,12 @@ impl fmt::Display for Instr {
             Instr::EndArray { .. } => write!(f, "["),
             Instr::Prim(prim, _) => write!(f, "{prim}"),
             Instr::Call(_) => write!(f, "!"),
+            Instr::Fused(fused) => {
+                for instr in fused.instrs.iter().rev() {
+                    write!(f, "{instr}")?;
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -160,6 +179,8 @@ impl fmt::Display for Signature {
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum FunctionKind {
     Normal,
+    /// A format string, which interleaves its fragments with its arguments
+    Format(Arc<[String]>),
     Dynamic(DynamicFunctionKind),
 }
 
@@ -239,7 +260,7 @@ impl fmt::Display for Function {
         if let Some((prim, _)) = self.as_primitive() {
             return write!(f, "{prim}");
         }
-        if let FunctionKind::Dynamic { .. } = self.kind {
+        if let FunctionKind::Format(_) | FunctionKind::Dynamic(_) = self.kind {
             return write!(f, "<dynamic>");
         }
         write!(f, "(")?;
@@ -286,7 +307,7 @@ impl Function {
         if let Some((prim, _)) = self.as_primitive() {
             return prim.to_string();
         }
-        if let FunctionKind::Dynamic { .. } = self.kind {
+        if let FunctionKind::Format(_) | FunctionKind::Dynamic(_) = self.kind {
             return "<dynamic>".into();
         }
         let mut s = String::new();
@@ -348,6 +369,13 @@ impl Function {
             _ => None,
         }
     }
+    /// Whether calling the function can only produce values, so that calls can be made on
+    /// separate threads in any order
+    ///
+    /// Functions with side effects, randomness, threads, tracing, or breaks are not pure.
+    pub(crate) fn is_pure(&self) -> bool {
+        !matches!(self.kind, FunctionKind::Dynamic(_)) && instrs_are_pure(&self.instrs)
+    }
     pub(crate) fn as_flipped_primitive(&self) -> Option<(Primitive, bool)> {
         match &self.id {
             FunctionId::Primitive(prim) => Some((*prim, false)),
@@ -360,6 +388,21 @@ impl Function {
     }
 }
 
+fn instrs_are_pure(instrs: &[Instr]) -> bool {
+    use Primitive::*;
+    instrs.iter().all(|instr| match instr {
+        Instr::Push(val) => match &**val {
+            Value::Func(fs) => fs.data.iter().all(|f| f.is_pure()),
+            _ => true,
+        },
+        Instr::Prim(prim, _) => {
+            !(prim.is_impure() || matches!(prim, Trace | InvTrace | Wait | Break))
+        }
+        Instr::Fused(fused) => instrs_are_pure(&fused.instrs),
+        Instr::BeginArray | Instr::EndArray { .. } | Instr::Call(_) => true,
+    })
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum FunctionId {
     Named(Ident),
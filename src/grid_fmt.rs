@@ -48,6 +48,14 @@ impl GridFmt for u8 {
     }
 }
 
+impl GridFmt for i64 {
+    fn fmt_grid(&self, boxed: bool) -> Grid {
+        let minus = if *self < 0 { "¯" } else { "" };
+        let s = format!("{minus}{}", self.unsigned_abs());
+        vec![boxed_scalar(boxed).chain(s.chars()).collect()]
+    }
+}
+
 impl GridFmt for f64 {
     fn fmt_grid(&self, boxed: bool) -> Grid {
         let positive = self.abs();
@@ -130,8 +138,10 @@ impl GridFmt for Value {
         match self {
             Value::Num(array) => array.fmt_grid(boxed),
             Value::Byte(array) => array.fmt_grid(boxed),
+            Value::Int(array) => array.fmt_grid(boxed),
             Value::Char(array) => array.fmt_grid(boxed),
             Value::Func(array) => array.fmt_grid(boxed),
+            Value::Mask(mask) => mask.unpacked().fmt_grid(boxed),
         }
     }
 }
@@ -337,6 +337,7 @@ pub enum AsciiToken {
     Backtick,
     TripleMinus,
     TripleTilde,
+    Question,
 }
 
 impl fmt::Display for AsciiToken {
@@ -361,6 +362,7 @@ impl fmt::Display for AsciiToken {
             AsciiToken::Backtick => write!(f, "`"),
             AsciiToken::TripleMinus => write!(f, "---"),
             AsciiToken::TripleTilde => write!(f, "~~~"),
+            AsciiToken::Question => write!(f, "?"),
         }
     }
 }
@@ -469,6 +471,7 @@ impl Lexer {
                 '_' => self.end(Underscore, start),
                 '|' => self.end(Bar, start),
                 ':' => self.end(Colon, start),
+                '?' => self.end(Question, start),
                 '`' => {
                     if self.number('-') {
                         self.end(Number, start)
@@ -573,8 +576,22 @@ impl Lexer {
                     while let Some(c) = self.next_char_if(is_ident_char) {
                         ident.push(c);
                     }
+                    let prims = Primitive::from_format_name_multi(&ident);
+                    // Collect the parts of a name qualified by modules
+                    let mut qualified = false;
+                    while prims.is_none()
+                        && self.peek_char() == Some('?')
+                        && (self.input_chars.get(self.loc.char_pos + 1))
+                            .is_some_and(|&c| is_ident_char(c))
+                    {
+                        qualified = true;
+                        self.next_char();
+                        while self.next_char_if(is_ident_char).is_some() {}
+                    }
                     // Try to parse as primitives
-                    if let Some(prims) = Primitive::from_format_name_multi(&ident) {
+                    if qualified {
+                        self.end(Ident, start)
+                    } else if let Some(prims) = prims {
                         let mut start = start;
                         for (prim, frag) in prims {
                             let end = Loc {
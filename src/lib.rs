@@ -1,8 +1,67 @@
 /*!
 The Uiua programming language
 
-This currently exists as a library only to reserve the name on crates.io.
-The current API should be considered deeply unstable.
+This crate can be used to embed a Uiua interpreter in a Rust program.
+
+# Running code
+[`Uiua::load_str`] and [`Uiua::load_file`] run code. Values left on the stack can be retrieved with [`Uiua::take_stack`].
+```
+use uiua::Uiua;
+
+let mut uiua = Uiua::with_native_sys();
+uiua.load_str("+ 1 2").unwrap();
+let stack = uiua.take_stack();
+assert_eq!(f64::try_from(stack[0].clone()).unwrap(), 3.0);
+```
+
+# Calling functions
+Names bound by loaded code can be looked up with [`Uiua::bound_value`] and [`Uiua::bound_function`].
+[`Uiua::call_function`] calls a bound function with some arguments and returns its outputs.
+The number of arguments must match the function's [`Signature`](function::Signature).
+```
+use uiua::{value::Value, Uiua};
+
+let mut uiua = Uiua::with_native_sys();
+uiua.load_str("Avg ← ÷⧻:/+.").unwrap();
+let outputs = uiua.call_function("Avg", [vec![1.0, 2.0, 6.0]]).unwrap();
+assert_eq!(f64::try_from(outputs[0].clone()).unwrap(), 3.0);
+assert!(uiua.call_function("Avg", Vec::<Value>::new()).is_err());
+```
+
+# Native functions
+Rust functions can be bound to names with [`Uiua::bind_native`].
+```
+use uiua::{function::Signature, Uiua};
+
+let mut uiua = Uiua::with_native_sys();
+uiua.bind_native("Square", Signature::new(1, 1), |env| {
+    let n = env.pop(1)?.as_num(env, "Square expects a number")?;
+    env.push(n * n);
+    Ok(())
+});
+uiua.load_str("Square 4").unwrap();
+assert_eq!(f64::try_from(uiua.take_stack().remove(0)).unwrap(), 16.0);
+```
+
+# Converting values
+[`Value`](value::Value) can be made from numbers, strings, [`Vec`]s of those, and shape/data pairs with `From` and `TryFrom`.
+It can be converted back the same way.
+```
+use uiua::{value::Value, Uiua};
+
+let mut uiua = Uiua::with_native_sys();
+uiua.load_str("Lens ← ≡⧻").unwrap();
+let words = Value::from(vec!["ab".to_string(), "cde".to_string()]);
+let outputs = uiua.call_function("Lens", [words]).unwrap();
+let lens = Vec::<f64>::try_from(outputs[0].clone()).unwrap();
+assert_eq!(lens, [2.0, 3.0]);
+
+let table = Value::try_from((vec![2, 3], vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0])).unwrap();
+let rows = Vec::<Vec<f64>>::try_from(table).unwrap();
+assert_eq!(rows, [[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]);
+```
+
+The API is still evolving and may change between versions.
 */
 
 #![allow(clippy::single_match, clippy::needless_range_loop)]
@@ -10,25 +69,35 @@ The current API should be considered deeply unstable.
 mod algorithm;
 pub mod array;
 pub mod ast;
+mod bytecode;
 mod check;
 mod cowslice;
+#[cfg(feature = "dap")]
+pub mod dap;
+mod debug;
 mod error;
+mod ffi;
 pub mod format;
 pub mod function;
 mod grid_fmt;
 pub mod lex;
 pub mod lsp;
+pub mod mask;
+mod optimize;
+#[cfg(feature = "package")]
+pub mod package;
 pub mod parse;
 pub mod primitive;
 #[doc(hidden)]
 pub mod profile;
 pub mod run;
+mod sandbox;
 mod sys;
 pub mod value;
 
 use std::sync::Arc;
 
-pub use {error::*, run::Uiua, sys::*};
+pub use {debug::*, error::*, ffi::*, run::Uiua, sandbox::*, sys::*};
 
 pub type Ident = Arc<str>;
 
@@ -44,3 +113,364 @@ fn suite() {
         }
     }
 }
+
+#[test]
+fn call_function() {
+    use value::Value;
+    let mut uiua = Uiua::with_native_sys();
+    uiua.load_str("F ← ⊃+-\nX ← 5").unwrap();
+    uiua.push(10);
+    let outputs = uiua.call_function("F", [3, 2]).unwrap();
+    let outputs: Vec<f64> = outputs.into_iter().map(|v| v.try_into().unwrap()).collect();
+    assert_eq!(outputs, [5.0, -1.0]);
+    assert_eq!(uiua.take_stack().len(), 1);
+    let err = uiua.call_function("F", [1]).unwrap_err();
+    assert!(err.to_string().contains("|2.2"), "{err}");
+    let x = uiua.call_function("X", Vec::<Value>::new()).unwrap();
+    assert_eq!(f64::try_from(x[0].clone()).unwrap(), 5.0);
+    assert!(uiua.call_function("Y", Vec::<Value>::new()).is_err());
+
+    let words = vec!["a".to_string(), "bcd".to_string()];
+    let boxed = Value::from(words.clone());
+    assert_eq!(Vec::<String>::try_from(boxed).unwrap(), words);
+    assert!(Value::try_from(vec![vec![1.0], vec![1.0, 2.0]]).is_err());
+    assert!(Value::try_from((vec![2, 2], vec![1.0])).is_err());
+}
+
+#[test]
+fn bind_native() {
+    use function::Signature;
+    let mut uiua = Uiua::with_native_sys();
+    uiua.bind_native("Double", Signature::new(1, 1), |env| {
+        let n = env.pop(1)?.as_num(env, "Argument must be a number")?;
+        env.push(n * 2.0);
+        Ok(())
+    });
+    uiua.bind_native("Bad", Signature::new(0, 1), |_| Ok(()));
+    uiua.load_str("F ← Double\nDouble F 5").unwrap();
+    assert_eq!(uiua.bound_function("F").unwrap().signature(), (1, 1));
+    let stack = uiua.take_stack();
+    assert_eq!(f64::try_from(stack[0].clone()).unwrap(), 20.0);
+    assert!(uiua.load_str("Double").is_err());
+    assert!(uiua.load_str("Bad").is_err());
+}
+
+#[test]
+fn resource_limits() {
+    fn root(err: UiuaError) -> UiuaError {
+        match err {
+            UiuaError::Traced { error, .. } => root(*error),
+            err => err,
+        }
+    }
+    let run = |mut uiua: Uiua, code: &str| root(uiua.load_str(code).map(drop).unwrap_err());
+    let err = run(Uiua::with_native_sys().with_array_limit(100), "⇡1000");
+    assert!(matches!(err, UiuaError::ArrayLimit(_)), "{err}");
+    let err = run(Uiua::with_native_sys().with_array_limit(100), "↯20_20 1");
+    assert!(matches!(err, UiuaError::ArrayLimit(_)), "{err}");
+    let err = run(Uiua::with_native_sys().with_array_limit(100), "⊞+.⇡20");
+    assert!(matches!(err, UiuaError::ArrayLimit(_)), "{err}");
+    let err = run(Uiua::with_native_sys().with_stack_limit(10), "⍥.20 1");
+    assert!(matches!(err, UiuaError::StackLimit(_)), "{err}");
+    let err = run(
+        Uiua::with_native_sys().with_memory_limit(1000),
+        "X ← ÷2⇡100\nX X",
+    );
+    assert!(matches!(err, UiuaError::MemoryLimit(_)), "{err}");
+    let err = run(
+        Uiua::with_native_sys().with_memory_limit(100_000),
+        "[⍥(□⇡1000)100]",
+    );
+    assert!(matches!(err, UiuaError::MemoryLimit(_)), "{err}");
+    let err = run(
+        Uiua::with_native_sys().with_recursion_limit(10),
+        "F ← (|0 ↬1 1)\nF",
+    );
+    assert!(matches!(err, UiuaError::RecursionLimit(_)), "{err}");
+    let mut uiua = Uiua::with_native_sys()
+        .with_array_limit(100)
+        .with_stack_limit(10)
+        .with_memory_limit(2000)
+        .with_recursion_limit(10);
+    uiua.load_str("⊞+.⇡10 ⇡100").unwrap();
+}
+
+#[test]
+fn error_reports() {
+    let err = (Uiua::with_native_sys().load_str("(1\n[").map(drop)).unwrap_err();
+    let reports = err.reports();
+    assert_eq!(reports.len(), 2);
+    assert!(reports.iter().all(|r| r.kind == ErrorKind::Parse));
+
+    let err = (Uiua::with_native_sys().load_str("F ← (⍤\"nope\" 0)\nF"))
+        .map(drop)
+        .unwrap_err();
+    let [report] = err.reports().try_into().unwrap();
+    assert_eq!(report.kind, ErrorKind::Throw);
+    assert_eq!(report.message, "nope");
+    let span = report.span.unwrap();
+    assert_eq!((span.start_line, span.start_col), (1, 6));
+    let functions: Vec<_> = report.trace.iter().map(|f| f.function.as_str()).collect();
+    assert_eq!(functions, ["⍤", "F"]);
+}
+
+#[test]
+fn modules() {
+    if let Err(e) = Uiua::with_native_sys().load_file("tests/modules/main.ua") {
+        panic!("{}", e.show(false));
+    }
+    for (code, message) in [
+        ("Lib?Secret 1", "`Secret` is private to module `Lib`"),
+        ("Lib?Triple 1", "Module `Lib` has no binding `Triple`"),
+        ("Lib?Foo?Bar", "Module `Lib` has no module `Foo`"),
+        ("Foo?Bar", "Unknown module `Foo`"),
+    ] {
+        let code = format!("Lib ? \"lib.ua\"\n{code}");
+        let err = (Uiua::with_native_sys().load_str_path(&code, "tests/modules/test.ua"))
+            .map(drop)
+            .unwrap_err();
+        assert!(err.to_string().contains(message), "{err}");
+    }
+    let code = "Lib ? \"lib.ua\"\nX ←? Lib?Square 2\n";
+    let formatted = format::format_str(code, &Default::default()).unwrap();
+    assert_eq!(formatted.output, code);
+    // `~` between identifiers is still trace
+    let mut uiua = Uiua::with_native_sys();
+    uiua.load_str("X ← 5\nY ← 2\n+X~Y").unwrap();
+    assert_eq!(f64::try_from(uiua.take_stack().remove(0)).unwrap(), 7.0);
+}
+
+#[test]
+fn compiled_files() {
+    use std::{env, fs};
+
+    let dir = env::temp_dir().join(format!("uiua_compile_test_{}", std::process::id()));
+    _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    // The second load runs the cached files
+    let cache = dir.join("cache");
+    for _ in 0..2 {
+        let mut env = Uiua::with_native_sys().with_cache_dir(&cache);
+        if let Err(e) = env.load_file("tests/modules/main.ua") {
+            panic!("{}", e.show(false));
+        }
+        assert_eq!(fs::read_dir(&cache).unwrap().count(), 5);
+    }
+
+    let path = dir.join("format.ua");
+    fs::write(&path, "F ← $\"_ and _\"\nF 1 2").unwrap();
+    let bytes = Uiua::with_native_sys().compile_file(&path).unwrap();
+    let mut env = Uiua::with_native_sys();
+    env.load_compiled(&bytes).unwrap();
+    let stack: Vec<String> = env.take_stack().iter().map(|v| v.to_string()).collect();
+    assert_eq!(stack, ["1 and 2"]);
+
+    let err = Uiua::with_native_sys().load_compiled(b"uiua").map(drop);
+    assert!(err
+        .unwrap_err()
+        .to_string()
+        .contains("Not a compiled Uiua file"));
+
+    let path = dir.join("impure.ua");
+    fs::write(&path, "X ← ⚂\nY ← 5").unwrap();
+    let err = Uiua::with_native_sys().compile_file(&path).unwrap_err();
+    assert!(err.to_string().contains("`X` is bound after"), "{err}");
+    _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn check_without_running() {
+    let check = Uiua::with_native_sys().check_str("X ← ⊢[]\nF ← |2.1 +1\nF X Y");
+    let sigs: Vec<String> = (check.signatures.iter())
+        .map(|(_, sig)| sig.to_string())
+        .collect();
+    assert_eq!(sigs, ["|0.1", "|2.1"]);
+    let errors = check.errors;
+    let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+    assert_eq!(messages.len(), 2, "{messages:?}");
+    assert!(messages[0].contains("signature mismatch"));
+    assert!(messages[1].contains("Unknown identifier `Y`"));
+    let mut env = Uiua::with_native_sys();
+    assert!(env.check_str("&p \"hi\"\nX ← 5").errors.is_empty());
+    assert!(env.take_stack().is_empty());
+}
+
+#[test]
+fn debug_hook() {
+    use std::{collections::VecDeque, path::Path};
+
+    use parking_lot::Mutex;
+
+    struct Script {
+        steps: VecDeque<DebugStep>,
+        breakpoint: usize,
+        pauses: Arc<Mutex<Vec<(PauseReason, usize, usize)>>>,
+    }
+    impl DebugHook for Script {
+        fn entry(&mut self) -> DebugStep {
+            self.steps.pop_front().unwrap()
+        }
+        fn is_breakpoint(&mut self, _: Option<&Path>, line: usize) -> bool {
+            line == self.breakpoint
+        }
+        fn pause(&mut self, pause: &DebugPause) -> DebugStep {
+            (self.pauses.lock()).push((pause.reason, pause.span.start.line, pause.stack.len()));
+            self.steps.pop_front().unwrap_or(DebugStep::Continue)
+        }
+    }
+    let run = |steps: &[DebugStep], breakpoint: usize| {
+        let pauses = Arc::new(Mutex::new(Vec::new()));
+        let script = Script {
+            steps: steps.iter().copied().collect(),
+            breakpoint,
+            pauses: pauses.clone(),
+        };
+        let code = "F ← (+1 ×2)\nF 1\nF 2";
+        let mut uiua = Uiua::with_native_sys().with_debug_hook(script);
+        let res = uiua.load_str(code).map(drop);
+        let pauses = pauses.lock().clone();
+        (res, pauses)
+    };
+    use {DebugStep::*, PauseReason::*};
+    let (res, pauses) = run(&[Into, Into, Into, Out], 0);
+    assert!(res.is_ok());
+    assert_eq!(
+        pauses,
+        [(Entry, 2, 2), (Step, 1, 2), (Step, 1, 2), (Step, 3, 3)]
+    );
+    let (res, pauses) = run(&[Continue, Over], 3);
+    assert!(res.is_ok());
+    assert_eq!(pauses, [(Breakpoint, 3, 3)]);
+    let (res, _) = run(&[Into, Stop], 0);
+    assert!(res.is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn poll() {
+    let code = r#"
+L ← &tcpl "127.0.0.1:0"
+⍤.≅[[0 0]] &poll 0 [L]
+C ← &tcpc &tcpaddr L
+⍤.≅[[1 0] [0 1]] &poll 1 [L C]
+S ← &tcpa L
+⍤.≅[[0 1]] &poll 0 [S]
+&w "hi" C
+&cl C
+⍤.≅1 ⊢⊢&poll 1 [S]
+⍤.≅"hi" &rs 2 S
+&cl S
+&cl L
+"#;
+    if let Err(e) = Uiua::with_native_sys().load_str(code) {
+        panic!("{}", e.show(false));
+    }
+    // A timeout too long to represent waits forever instead of panicking
+    if let Err(e) = Uiua::with_native_sys().load_str("⍤.≅1 ⊡1⊢&poll 1e300 [1]") {
+        panic!("{}", e.show(false));
+    }
+    for code in ["&poll ¯5 []", "&poll ÷0 0 []"] {
+        let err = Uiua::with_native_sys()
+            .load_str(code)
+            .map(drop)
+            .unwrap_err();
+        assert!(err.to_string().contains("non-negative"), "{err}");
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn run_stream() {
+    let code = r#"
+P ← &runs {"cat"}
+&w "hello\n" ⊡0 P
+⍤.≅[[1 0]] &poll 1 [⊡1 P]
+⍤.≅"hello\n" &ru "\n" ⊡1 P
+&w "partial" ⊡0 P
+⍤.≅"partial" &rs 100 ⊡1 P
+⍤.≅0 &runw ⊡3 P
+⍤.≅3 &runw ⊡3 &runs {"sh" "-c" "exit 3"}
+⍤.≅"oops\n" &ru "\n" ⊡2 &runs {"sh" "-c" "echo oops >&2"}
+"#;
+    if let Err(e) = Uiua::with_native_sys().load_str(code) {
+        panic!("{}", e.show(false));
+    }
+}
+
+#[test]
+fn threads_give_same_results() {
+    let code = "
+X ← ÷7-50000⇡100000
+Y ← ↯1000_100 X
+×2 X
++1×2 X
+/+ Y
+/+◫3 X
+⊞+ ⇡300 ⇡300
+⌂ ◿10 X
+≡(/+×.) Y
+∵(×2) X
+≡⊂ Y Y
+";
+    let run = |threads: usize| {
+        let mut uiua = Uiua::with_native_sys().with_thread_count(threads);
+        uiua.load_str(code).unwrap();
+        uiua.take_stack()
+    };
+    assert_eq!(run(1), run(4));
+    // Errors are those of the first row to fail
+    let error = |threads: usize| {
+        let mut uiua = Uiua::with_native_sys().with_thread_count(threads);
+        let code = "≡(⍤\"big\" <1000 /+.) ↯1000_100 ÷7-50000⇡100000";
+        match uiua.load_str(code) {
+            Ok(_) => panic!("rows should fail"),
+            Err(e) => e.to_string(),
+        }
+    };
+    assert_eq!(error(1), error(4));
+}
+
+#[test]
+fn integers_and_masks() {
+    use value::Value;
+    let code = "
++1 9007199254740993
+×2 4611686018427387904
+⍘⋯⋯ 9007199254740993
++0.5 9007199254740993
+";
+    let mut uiua = Uiua::with_native_sys();
+    uiua.load_str(code).unwrap();
+    let [sum, overflow, bits, num] = <[Value; 4]>::try_from(uiua.take_stack()).unwrap();
+    assert_eq!(i64::try_from(sum).unwrap(), 9007199254740994);
+    assert_eq!(overflow, Value::from(2f64.powi(63)));
+    assert_eq!(
+        bits.as_int_array().map(|a| a.data[0]),
+        Some(9007199254740993)
+    );
+    assert!(num.as_num_array().is_some());
+
+    // Masks give the same results as the byte arrays they stand for
+    let code = "
+X ← ⇡3000
+▽ <5 X X
+/+ ↧ >10 X <20 X
+/+ = >1000 X ¬ <1001 X
+⊢ ⍜⊢(+1) <5 X
+≅ <3 X ⊢[<3 X]
+";
+    let run = |optimize: bool| {
+        let mut uiua = Uiua::with_native_sys().with_optimization(optimize);
+        uiua.load_str(code).unwrap();
+        uiua.take_stack()
+    };
+    let expected: Vec<Value> = vec![
+        vec![0.0, 1.0, 2.0, 3.0, 4.0].into(),
+        9.0.into(),
+        3000.0.into(),
+        2.0.into(),
+        1.0.into(),
+    ];
+    assert_eq!(run(true), expected);
+    assert_eq!(run(false), expected);
+}
@@ -36,6 +36,7 @@ fn items_spans(items: &[Item]) -> Vec<Sp<SpanKind>> {
                 }
                 spans.extend(words_spans(&binding.words));
             }
+            Item::Import(import) => spans.push(import.path.span.clone().sp(SpanKind::String)),
             Item::ExtraNewlines(span) => spans.push(span.clone().sp(SpanKind::Whitespace)),
         }
     }
@@ -102,7 +103,14 @@ pub use server::run_server;
 
 #[cfg(feature = "lsp")]
 mod server {
-    use std::{collections::BTreeMap, sync::Arc};
+    use std::{
+        collections::{BTreeMap, HashMap, HashSet},
+        path::Path,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+    };
 
     use dashmap::DashMap;
     use tower_lsp::{jsonrpc::Result, lsp_types::*, *};
@@ -111,90 +119,573 @@ mod server {
 
     use crate::{
         format::{format_str, FormatConfig},
-        lex::Loc,
+        function::Signature,
+        lex::{is_ident_char, Loc},
         primitive::PrimDocFragment,
-        Ident, Uiua,
+        Ident, ReportSpan, SysOp, Uiua, UiuaError,
     };
 
     pub struct LspDoc {
         pub input: String,
         pub spans: Vec<Sp<SpanKind>>,
         pub bindings: BindingsInfo,
+        /// Paths of files imported with `&i`
+        pub imports: Vec<String>,
+        /// Names passed to `use` as string literals
+        pub uses: Vec<Sp<Ident>>,
+        pub symbols: Vec<DocumentSymbol>,
+        pub diagnostics: Vec<Diagnostic>,
+        /// Inferred signatures of bindings that do not declare one
+        pub inferred_signatures: Vec<(Loc, Signature)>,
+        /// The stack height after each line
+        pub stack_heights: Vec<(CodeSpan, usize)>,
+        /// Bindings in imported files, by name
+        pub imported: HashMap<Ident, Arc<BindingInfo>>,
     }
 
+    /// Every binding name and every identifier that refers to a binding
     type BindingsInfo = BTreeMap<Sp<Ident>, Arc<BindingInfo>>;
 
     impl LspDoc {
-        fn new(input: String) -> Self {
-            let (items, _) = parse(&input, None);
+        /// Load a document, given the bindings in the files it imports
+        fn new(
+            input: String,
+            path: Option<&Path>,
+            imported: HashMap<Ident, Arc<BindingInfo>>,
+        ) -> Self {
+            let (items, _) = parse(&input, path);
             let spans = items_spans(&items);
-            let bindings = bindings_info(&items);
+            let imported_signatures =
+                (imported.iter()).filter_map(|(name, info)| Some((name.clone(), info.signature?)));
+            let mut env = Uiua::with_native_sys().with_imported_signatures(imported_signatures);
+            let check = match path {
+                Some(path) => env.check_str_path(&input, path),
+                None => env.check_str(&input),
+            };
+            let mut collector = BindingCollector {
+                signatures: check.signatures,
+                imported,
+                ..Default::default()
+            };
+            collector.items(&items);
+            let symbols = items_symbols(&items);
+            let diagnostics = diagnostics(check.errors, path);
             Self {
                 input,
                 spans,
-                bindings,
+                bindings: collector.bindings,
+                imports: collector.imports,
+                uses: collector.uses,
+                symbols,
+                diagnostics,
+                inferred_signatures: collector.inferred,
+                stack_heights: check.stack_heights,
+                imported: collector.imported,
             }
         }
+        /// Get the bindings that other files can `use`, by name
+        fn exports(&self) -> HashMap<Ident, Arc<BindingInfo>> {
+            let mut exports: HashMap<Ident, Arc<BindingInfo>> = HashMap::new();
+            for (ident, info) in &self.bindings {
+                if ident.span != info.span {
+                    continue;
+                }
+                match exports.get(&ident.value) {
+                    Some(prev) if prev.span.start > ident.span.start => {}
+                    _ => {
+                        exports.insert(ident.value.clone(), info.clone());
+                    }
+                }
+            }
+            exports
+        }
+        /// Get the binding declared or referenced at a position
+        fn binding_at(&self, line: usize, col: usize) -> Option<(&Sp<Ident>, &Arc<BindingInfo>)> {
+            (self.bindings.iter()).find(|(ident, _)| ident.span.contains_line_col(line, col))
+        }
+        /// Get the declaration and references of a binding
+        fn binding_spans(&self, binding: &Arc<BindingInfo>) -> Vec<&CodeSpan> {
+            (self.bindings.iter())
+                .filter(|(_, info)| Arc::ptr_eq(info, binding))
+                .map(|(ident, _)| &ident.span)
+                .collect()
+        }
+        /// Get the top-level binding with the given name
+        fn binding_named(&self, name: &str) -> Option<&Arc<BindingInfo>> {
+            (self.bindings.iter())
+                .filter(|(ident, info)| &*ident.value == name && ident.span == info.span)
+                .max_by_key(|(ident, _)| ident.span.start.char_pos)
+                .map(|(_, info)| info)
+        }
     }
 
     pub struct BindingInfo {
+        pub name: Ident,
         pub span: CodeSpan,
         pub comment: Option<String>,
+        /// The inferred or declared signature
+        pub signature: Option<Signature>,
     }
 
-    fn bindings_info(items: &[Item]) -> BindingsInfo {
-        let mut bindings = BindingsInfo::new();
-        let mut scope_bindings = Vec::new();
-        let mut last_comment: Option<String> = None;
-        for item in items {
-            match item {
-                Item::Scoped { items, .. } => scope_bindings.push(bindings_info(items)),
-                Item::Words(words) => {
-                    if let [Sp {
-                        value: Word::Comment(comment),
-                        ..
-                    }] = words.as_slice()
-                    {
-                        let full = last_comment.get_or_insert_with(String::new);
-                        if !full.is_empty() {
-                            if comment.trim().is_empty() {
-                                full.push('\n');
-                                full.push('\n');
-                            } else {
-                                full.push(' ');
+    #[derive(Default)]
+    struct BindingCollector {
+        bindings: BindingsInfo,
+        /// The bindings that are currently in scope, most recent last
+        visible: Vec<(Ident, Arc<BindingInfo>)>,
+        imports: Vec<String>,
+        uses: Vec<Sp<Ident>>,
+        /// Binding signatures found by checking the code, by the span of the binding's name
+        signatures: Vec<(CodeSpan, Signature)>,
+        /// Where the signatures of bindings that do not declare one would go
+        inferred: Vec<(Loc, Signature)>,
+        /// Bindings in imported files, by name
+        imported: HashMap<Ident, Arc<BindingInfo>>,
+        /// A line and column at which to record the visible bindings
+        cursor: Option<(usize, usize)>,
+        visible_at_cursor: Option<Vec<(Ident, Arc<BindingInfo>)>>,
+    }
+
+    impl BindingCollector {
+        fn items(&mut self, items: &[Item]) {
+            let mut last_comment: Option<String> = None;
+            for item in items {
+                if let (Some(cursor), None, Some(start)) =
+                    (self.cursor, &self.visible_at_cursor, item_start(item))
+                {
+                    if (start.line, start.col) >= cursor {
+                        self.visible_at_cursor = Some(self.visible.clone());
+                    }
+                }
+                match item {
+                    Item::Scoped { items, .. } => {
+                        let visible = self.visible.len();
+                        self.items(items);
+                        self.visible.truncate(visible);
+                    }
+                    Item::Words(words) => {
+                        if let [Sp {
+                            value: Word::Comment(comment),
+                            ..
+                        }] = words.as_slice()
+                        {
+                            let full = last_comment.get_or_insert_with(String::new);
+                            if !full.is_empty() {
+                                if comment.trim().is_empty() {
+                                    full.push('\n');
+                                    full.push('\n');
+                                } else {
+                                    full.push(' ');
+                                }
                             }
+                            full.push_str(comment.trim());
+                        } else {
+                            last_comment = None;
+                            self.words(words);
                         }
-                        full.push_str(comment.trim());
-                    } else {
-                        last_comment = None;
-                        for word in words {
-                            if let Word::Ident(ident) = &word.value {
-                                if let Some((_, info)) =
-                                    bindings.iter().rev().find(|(name, _)| name.value == *ident)
-                                {
-                                    let info = info.clone();
-                                    bindings.insert(word.span.clone().sp(ident.clone()), info);
-                                }
+                    }
+                    Item::Binding(binding) => {
+                        // A binding's words cannot refer to the binding itself
+                        let uses = self.uses.len();
+                        self.words(&binding.words);
+                        // Bindings of imported functions get their documentation
+                        if last_comment.is_none() {
+                            last_comment = (self.uses.get(uses))
+                                .and_then(|name| self.imported.get(&name.value)?.comment.clone());
+                        }
+                        let signature = (self.signatures.iter())
+                            .find(|(span, _)| *span == binding.name.span)
+                            .map(|(_, sig)| *sig);
+                        if let (None, Some(sig), Some(first)) =
+                            (&binding.signature, signature, binding.words.first())
+                        {
+                            // Constants are not worth annotating
+                            if sig != Signature::new(0, 1) {
+                                self.inferred.push((first.span.start, sig));
                             }
                         }
+                        let info = Arc::new(BindingInfo {
+                            name: binding.name.value.clone(),
+                            comment: last_comment.take(),
+                            span: binding.name.span.clone(),
+                            signature,
+                        });
+                        self.bindings.insert(binding.name.clone(), info.clone());
+                        self.visible.push((binding.name.value.clone(), info));
                     }
+                    Item::Import(import) => self.imports.push(import.path.value.clone()),
+                    Item::ExtraNewlines(_) => {}
                 }
-                Item::Binding(binding) => {
-                    let comment = last_comment.take();
-                    bindings.insert(
-                        binding.name.clone(),
-                        BindingInfo {
-                            comment,
-                            span: binding.name.span.clone(),
+            }
+        }
+        fn words(&mut self, words: &[Sp<Word>]) {
+            let mut prev_prim = None;
+            for word in words {
+                match &word.value {
+                    Word::Ident(ident) => {
+                        if let Some((_, info)) =
+                            self.visible.iter().rev().find(|(name, _)| name == ident)
+                        {
+                            (self.bindings)
+                                .insert(word.span.clone().sp(ident.clone()), info.clone());
                         }
-                        .into(),
-                    );
+                    }
+                    Word::String(s) => match prev_prim {
+                        Some(Primitive::Sys(SysOp::Import)) => self.imports.push(s.clone()),
+                        Some(Primitive::Use) => {
+                            self.uses.push(word.span.clone().sp(s.as_str().into()))
+                        }
+                        _ => {}
+                    },
+                    Word::Strand(items) => self.words(items),
+                    Word::Array(arr) => arr.lines.iter().for_each(|line| self.words(line)),
+                    Word::Func(func) => func.lines.iter().for_each(|line| self.words(line)),
+                    Word::Modified(m) => self.words(&m.operands),
+                    _ => {}
+                }
+                match &word.value {
+                    Word::Spaces | Word::Comment(_) => {}
+                    Word::Primitive(prim) => prev_prim = Some(*prim),
+                    _ => prev_prim = None,
+                }
+            }
+        }
+    }
+
+    /// Get the paths of the files imported by some code
+    fn imports(input: &str) -> Vec<String> {
+        let (items, _) = parse(input, None);
+        let mut collector = BindingCollector::default();
+        collector.items(&items);
+        collector.imports
+    }
+
+    /// Get the URIs of the files a document imports
+    ///
+    /// Import paths are resolved relative to the importing document.
+    fn import_uris(uri: &Url, imports: &[String]) -> Vec<Url> {
+        let Some(dir) = (uri.to_file_path().ok()).and_then(|path| Some(path.parent()?.to_owned()))
+        else {
+            return Vec::new();
+        };
+        (imports.iter())
+            .filter_map(|import| Url::from_file_path(dir.join(import)).ok())
+            .collect()
+    }
+
+    /// Apply an edit from the editor to a document's text
+    fn apply_change(input: &mut String, change: TextDocumentContentChangeEvent) {
+        let Some(range) = change.range else {
+            *input = change.text;
+            return;
+        };
+        let start = byte_offset(input, range.start);
+        let end = byte_offset(input, range.end).max(start);
+        input.replace_range(start..end, &change.text);
+    }
+
+    /// Get the byte offset of a position, whose column counts chars
+    ///
+    /// The server asks for UTF-32 positions, like the other position conversions use.
+    fn byte_offset(input: &str, pos: Position) -> usize {
+        let mut line_start = 0;
+        for _ in 0..pos.line {
+            match input[line_start..].find('\n') {
+                Some(i) => line_start += i + 1,
+                None => return input.len(),
+            }
+        }
+        for (col, (i, c)) in input[line_start..].char_indices().enumerate() {
+            if col >= pos.character as usize || c == '\n' {
+                return line_start + i;
+            }
+        }
+        input.len()
+    }
+
+    fn item_start(item: &Item) -> Option<Loc> {
+        match item {
+            Item::Scoped { items, .. } => items.iter().find_map(item_start),
+            Item::Words(words) => words.first().map(|word| word.span.start),
+            Item::Binding(binding) => Some(binding.name.span.start),
+            Item::Import(import) => Some(import.name.span.start),
+            Item::ExtraNewlines(span) => Some(span.start),
+        }
+    }
+
+    /// Get completions for the partial word before a position
+    ///
+    /// Primitives are matched by the start of their names like when formatting,
+    /// and complete to their glyphs.
+    fn completions(doc: &LspDoc, line: usize, col: usize) -> Vec<CompletionItem> {
+        let Some(text) = doc.input.lines().nth(line - 1) else {
+            return Vec::new();
+        };
+        let before: Vec<char> = text.chars().take(col - 1).collect();
+        let mut start = before.len();
+        while start > 0 && is_ident_char(before[start - 1]) {
+            start -= 1;
+        }
+        let prefix: String = before[start..].iter().collect();
+        if prefix.is_empty() {
+            return Vec::new();
+        }
+        let range = Range::new(
+            Position::new(line as u32 - 1, start as u32),
+            Position::new(line as u32 - 1, before.len() as u32),
+        );
+        let item = |label: &str, kind, new_text: String, detail: String, doc: Option<String>| {
+            CompletionItem {
+                label: label.into(),
+                kind: Some(kind),
+                detail: Some(detail),
+                documentation: doc.map(Documentation::String),
+                text_edit: Some(CompletionTextEdit::Edit(TextEdit::new(range, new_text))),
+                ..Default::default()
+            }
+        };
+        let mut items = Vec::new();
+        if prefix.starts_with('&') {
+            for op in SysOp::ALL {
+                if op.name().starts_with(&prefix) {
+                    items.push(item(
+                        op.name(),
+                        CompletionItemKind::FUNCTION,
+                        op.name().into(),
+                        op.long_name().into(),
+                        op.doc().map(|doc| doc.short_text().into_owned()),
+                    ));
                 }
-                Item::ExtraNewlines(_) => {}
             }
+            return items;
         }
-        scope_bindings.push(bindings);
-        scope_bindings.into_iter().flatten().collect()
+        for prim in Primitive::all() {
+            let Some(names) = prim.names() else {
+                continue;
+            };
+            if !names.text.starts_with(&prefix) || matches!(prim, Primitive::Sys(_)) {
+                continue;
+            }
+            let new_text = if names.is_name_formattable() {
+                names.unicode.unwrap().to_string()
+            } else if names.ascii.is_none() && names.unicode.is_none() {
+                names.text.into()
+            } else {
+                continue;
+            };
+            items.push(item(
+                names.text,
+                CompletionItemKind::FUNCTION,
+                new_text.clone(),
+                new_text,
+                prim.doc().map(|doc| doc.short_text().into_owned()),
+            ));
+        }
+        let (items_ast, _) = parse(&doc.input, None);
+        let mut collector = BindingCollector {
+            cursor: Some((line, col)),
+            ..Default::default()
+        };
+        collector.items(&items_ast);
+        let visible = (collector.visible_at_cursor).unwrap_or(collector.visible);
+        let mut seen = Vec::new();
+        for (name, info) in visible.iter().rev() {
+            if !name.starts_with(&prefix) || seen.contains(&name) {
+                continue;
+            }
+            seen.push(name);
+            // Get the signature from the binding found when the document was loaded
+            let declaration = info.span.clone().sp(name.clone());
+            let info = doc.bindings.get(&declaration).unwrap_or(info);
+            let kind = match info.signature {
+                Some(sig) if sig.args == 0 && sig.outputs == 1 => CompletionItemKind::CONSTANT,
+                _ => CompletionItemKind::FUNCTION,
+            };
+            items.push(item(
+                name,
+                kind,
+                name.to_string(),
+                (info.signature).map_or_else(String::new, |sig| sig.to_string()),
+                info.comment.clone(),
+            ));
+        }
+        items
+    }
+
+    /// Get the inlay hints for a document
+    fn inlay_hints(doc: &LspDoc, stack_heights: bool) -> Vec<InlayHint> {
+        let mut hints = Vec::new();
+        for (loc, sig) in &doc.inferred_signatures {
+            let position = uiua_loc_to_lsp(*loc);
+            hints.push(InlayHint {
+                position,
+                label: InlayHintLabel::String(sig.to_string()),
+                kind: Some(InlayHintKind::TYPE),
+                text_edits: Some(vec![TextEdit::new(
+                    Range::new(position, position),
+                    format!("{sig} "),
+                )]),
+                tooltip: None,
+                padding_left: None,
+                padding_right: Some(true),
+                data: None,
+            });
+        }
+        if stack_heights {
+            for (span, height) in &doc.stack_heights {
+                hints.push(InlayHint {
+                    position: uiua_loc_to_lsp(span.end),
+                    label: InlayHintLabel::String(format!("height {height}")),
+                    kind: None,
+                    text_edits: None,
+                    tooltip: Some(InlayHintTooltip::String(
+                        "The height of the stack after this line".into(),
+                    )),
+                    padding_left: Some(true),
+                    padding_right: None,
+                    data: None,
+                });
+            }
+        }
+        hints
+    }
+
+    /// Get the signature of the function at or before a position
+    fn signature_help(doc: &LspDoc, line: usize, col: usize) -> Option<SignatureHelp> {
+        let before = |span: &CodeSpan| {
+            span.start.line == line && span.start.col <= col || span.contains_line_col(line, col)
+        };
+        let prim = (doc.spans.iter())
+            .filter(|sp| before(&sp.span))
+            .filter_map(|sp| match sp.value {
+                SpanKind::Primitive(prim) => Some((sp.span.start.col, prim)),
+                _ => None,
+            })
+            .max_by_key(|(col, _)| *col);
+        let binding = (doc.bindings.iter())
+            .filter(|(ident, info)| before(&ident.span) && ident.span != info.span)
+            .map(|(ident, info)| (ident.span.start.col, info))
+            .max_by_key(|(col, _)| *col);
+        let (label, doc) = match binding {
+            Some((binding_col, info))
+                if prim.map_or(true, |(prim_col, _)| binding_col > prim_col) =>
+            {
+                let label = format!("{} {}", info.name, info.signature?);
+                (label, info.comment.clone())
+            }
+            _ => {
+                let (_, prim) = prim?;
+                let name = prim.name()?;
+                let mut label = match prim.unicode() {
+                    Some(c) => format!("{c} {name}"),
+                    None => name.into(),
+                };
+                if let (Some(args), Some(outputs)) = (prim.args(), prim.outputs()) {
+                    let sig = Signature::new(args as usize, outputs as usize);
+                    label.push_str(&format!(" {sig}"));
+                }
+                (label, prim.doc().map(|doc| doc.short_text().into_owned()))
+            }
+        };
+        Some(SignatureHelp {
+            signatures: vec![SignatureInformation {
+                label,
+                documentation: doc.map(Documentation::String),
+                parameters: None,
+                active_parameter: None,
+            }],
+            active_signature: None,
+            active_parameter: None,
+        })
+    }
+
+    /// Convert errors found by checking code to diagnostics
+    ///
+    /// Errors in other files, such as imported modules, are skipped.
+    fn diagnostics(errors: Vec<UiuaError>, path: Option<&Path>) -> Vec<Diagnostic> {
+        let file = path.map(|path| path.to_string_lossy().into_owned());
+        let mut diagnostics = Vec::new();
+        for error in errors {
+            for report in error.reports() {
+                let Some(span) = report.span.filter(|span| span.file == file) else {
+                    continue;
+                };
+                diagnostics.push(Diagnostic {
+                    range: report_span_to_lsp(&span),
+                    severity: Some(DiagnosticSeverity::ERROR),
+                    source: Some("uiua".into()),
+                    message: report.message,
+                    ..Default::default()
+                });
+            }
+        }
+        diagnostics
+    }
+
+    #[allow(deprecated)]
+    fn items_symbols(items: &[Item]) -> Vec<DocumentSymbol> {
+        let mut symbols = Vec::new();
+        for item in items {
+            match item {
+                Item::Scoped { items, test } => {
+                    let children = items_symbols(items);
+                    let (Some(first), Some(last)) = (children.first(), children.last()) else {
+                        continue;
+                    };
+                    let range = Range::new(first.range.start, last.range.end);
+                    symbols.push(DocumentSymbol {
+                        name: if *test { "~~~" } else { "---" }.into(),
+                        detail: Some(if *test { "test scope" } else { "scope" }.into()),
+                        kind: SymbolKind::NAMESPACE,
+                        tags: None,
+                        deprecated: None,
+                        range,
+                        selection_range: range,
+                        children: Some(children),
+                    });
+                }
+                Item::Binding(binding) => {
+                    let is_function = binding.signature.is_some()
+                        || (binding.words.iter()).any(|word| {
+                            matches!(
+                                word.value,
+                                Word::Func(_) | Word::Primitive(_) | Word::Modified(_)
+                            )
+                        });
+                    let span = (binding.words.last()).map_or(binding.name.span.clone(), |word| {
+                        binding.name.span.clone().merge(word.span.clone())
+                    });
+                    symbols.push(DocumentSymbol {
+                        name: binding.name.value.to_string(),
+                        detail: None,
+                        kind: if is_function {
+                            SymbolKind::FUNCTION
+                        } else {
+                            SymbolKind::CONSTANT
+                        },
+                        tags: None,
+                        deprecated: None,
+                        range: uiua_span_to_lsp(&span),
+                        selection_range: uiua_span_to_lsp(&binding.name.span),
+                        children: None,
+                    });
+                }
+                Item::Import(import) => {
+                    let span = import.name.span.clone().merge(import.path.span.clone());
+                    symbols.push(DocumentSymbol {
+                        name: import.name.value.to_string(),
+                        detail: Some(import.path.value.clone()),
+                        kind: SymbolKind::MODULE,
+                        tags: None,
+                        deprecated: None,
+                        range: uiua_span_to_lsp(&span),
+                        selection_range: uiua_span_to_lsp(&import.name.span),
+                        children: None,
+                    });
+                }
+                Item::Words(_) | Item::ExtraNewlines(_) => {}
+            }
+        }
+        symbols
     }
 
     pub fn run_server() {
@@ -207,28 +698,269 @@ mod server {
                 let stdin = tokio::io::stdin();
                 let stdout = tokio::io::stdout();
 
-                let (service, socket) = LspService::new(|client| Backend {
-                    client,
-                    docs: DashMap::new(),
-                });
+                let (service, socket) = LspService::new(Backend::new);
                 Server::new(stdin, stdout, socket).serve(service).await;
             });
     }
 
     struct Backend {
         client: Client,
-        docs: DashMap<Url, LspDoc>,
+        docs: DashMap<Url, Arc<LspDoc>>,
+        /// Documents that are imported but not open, loaded from disk
+        imported: DashMap<Url, Arc<LspDoc>>,
+        /// The URIs that each loaded document imports
+        import_graph: DashMap<Url, Vec<Url>>,
+        /// Whether to show the stack height at the end of each line
+        ///
+        /// Enabled with the `stackHeightHints` initialization option.
+        stack_height_hints: AtomicBool,
+    }
+
+    impl Backend {
+        fn new(client: Client) -> Self {
+            Backend {
+                client,
+                docs: DashMap::new(),
+                imported: DashMap::new(),
+                import_graph: DashMap::new(),
+                stack_height_hints: AtomicBool::new(false),
+            }
+        }
+        /// Get the text of a document from the editor if it is open, or else from disk
+        fn text(&self, uri: &Url) -> Option<String> {
+            if let Some(doc) = self.docs.get(uri) {
+                return Some(doc.input.clone());
+            }
+            std::fs::read_to_string(uri.to_file_path().ok()?).ok()
+        }
+        /// Get an open document, or load it from disk
+        fn doc(&self, uri: &Url) -> Option<Arc<LspDoc>> {
+            if let Some(doc) = self.docs.get(uri) {
+                return Some(doc.clone());
+            }
+            let input = self.text(uri)?;
+            Some(Arc::new(self.load(uri, input, &mut Vec::new())))
+        }
+        /// Load a document along with the files it imports
+        ///
+        /// `loading` holds the documents whose imports are being loaded, to avoid cycles.
+        fn load(&self, uri: &Url, input: String, loading: &mut Vec<Url>) -> LspDoc {
+            loading.push(uri.clone());
+            let import_uris = import_uris(uri, &imports(&input));
+            let mut imported = HashMap::new();
+            for import in &import_uris {
+                if loading.contains(import) {
+                    continue;
+                }
+                let Some(import_doc) = self.imported_doc(import, loading) else {
+                    continue;
+                };
+                for (name, info) in import_doc.exports() {
+                    imported.entry(name).or_insert(info);
+                }
+            }
+            loading.pop();
+            self.import_graph.insert(uri.clone(), import_uris);
+            LspDoc::new(input, uri.to_file_path().ok().as_deref(), imported)
+        }
+        /// Get a document that another imports
+        ///
+        /// Documents that are not open are loaded from disk once and then cached.
+        fn imported_doc(&self, uri: &Url, loading: &mut Vec<Url>) -> Option<Arc<LspDoc>> {
+            if let Some(doc) = self.docs.get(uri) {
+                return Some(doc.clone());
+            }
+            if let Some(doc) = self.imported.get(uri) {
+                return Some(doc.clone());
+            }
+            let input = std::fs::read_to_string(uri.to_file_path().ok()?).ok()?;
+            let doc = Arc::new(self.load(uri, input, loading));
+            self.imported.insert(uri.clone(), doc.clone());
+            Some(doc)
+        }
+        /// Get the URIs that a document imports
+        fn import_uris(&self, uri: &Url) -> Vec<Url> {
+            if let Some(imports) = self.import_graph.get(uri) {
+                return imports.clone();
+            }
+            let Some(input) = self.text(uri) else {
+                return Vec::new();
+            };
+            let uris = import_uris(uri, &imports(&input));
+            self.import_graph.insert(uri.clone(), uris.clone());
+            uris
+        }
+        /// Check if a document imports another, directly or through other imports
+        fn depends_on(&self, uri: &Url, target: &Url) -> bool {
+            let mut seen = HashSet::new();
+            let mut queue = vec![uri.clone()];
+            while let Some(uri) = queue.pop() {
+                if !seen.insert(uri.clone()) {
+                    continue;
+                }
+                for import in self.import_uris(&uri) {
+                    if import == *target {
+                        return true;
+                    }
+                    queue.push(import);
+                }
+            }
+            false
+        }
+        /// Apply changes to an open document
+        ///
+        /// Returns the document and every open document that depends on it,
+        /// which are reloaded so that they see the changes.
+        fn update(
+            &self,
+            uri: &Url,
+            changes: Vec<TextDocumentContentChangeEvent>,
+        ) -> Vec<(Url, Arc<LspDoc>)> {
+            let mut input = self.text(uri).unwrap_or_default();
+            for change in changes {
+                apply_change(&mut input, change);
+            }
+            let doc = Arc::new(self.load(uri, input, &mut Vec::new()));
+            self.docs.insert(uri.clone(), doc.clone());
+            let mut updated = vec![(uri.clone(), doc)];
+            updated.extend(self.reload_dependents(uri));
+            updated
+        }
+        /// Close an open document
+        ///
+        /// Returns every open document that depends on it,
+        /// which are reloaded so that they see the file on disk.
+        fn close(&self, uri: &Url) -> Vec<(Url, Arc<LspDoc>)> {
+            self.docs.remove(uri);
+            self.import_graph.remove(uri);
+            self.reload_dependents(uri)
+        }
+        /// Reload the open documents that depend on a document that has changed
+        fn reload_dependents(&self, uri: &Url) -> Vec<(Url, Arc<LspDoc>)> {
+            // Cached imports may have seen the old version of the document
+            let stale: Vec<Url> = (self.imported.iter())
+                .map(|entry| entry.key().clone())
+                .collect();
+            for other in stale {
+                if other == *uri || self.depends_on(&other, uri) {
+                    self.imported.remove(&other);
+                }
+            }
+            let open: Vec<Url> = (self.docs.iter())
+                .map(|entry| entry.key().clone())
+                .collect();
+            let dependents =
+                (open.into_iter()).filter(|other| other != uri && self.depends_on(other, uri));
+            let mut updated = Vec::new();
+            for dependent in dependents {
+                let input = self.text(&dependent).unwrap_or_default();
+                let doc = Arc::new(self.load(&dependent, input, &mut Vec::new()));
+                self.docs.insert(dependent.clone(), doc.clone());
+                updated.push((dependent, doc));
+            }
+            updated
+        }
+        /// Publish the diagnostics of updated documents
+        ///
+        /// The version, if given, is that of the first document.
+        async fn publish(&self, updated: Vec<(Url, Arc<LspDoc>)>, version: Option<i32>) {
+            for (i, (uri, doc)) in updated.into_iter().enumerate() {
+                // Only the edited document's version is known
+                let version = version.filter(|_| i == 0);
+                (self.client)
+                    .publish_diagnostics(uri, doc.diagnostics.clone(), version)
+                    .await;
+            }
+        }
+        /// Find the binding declared or referenced at a position
+        ///
+        /// Returns the URI of the document that declares the binding
+        fn resolve(
+            &self,
+            uri: &Url,
+            pos: Position,
+        ) -> Option<(Url, Arc<LspDoc>, Arc<BindingInfo>)> {
+            let doc = self.doc(uri)?;
+            let (line, col) = lsp_pos_to_uiua(pos);
+            if let Some((_, info)) = doc.binding_at(line, col) {
+                let info = info.clone();
+                return Some((uri.clone(), doc, info));
+            }
+            let name = doc
+                .uses
+                .iter()
+                .find(|name| name.span.contains_line_col(line, col))?;
+            for import in import_uris(uri, &doc.imports) {
+                let Some(import_doc) = self.doc(&import) else {
+                    continue;
+                };
+                if let Some(info) = import_doc.binding_named(&name.value) {
+                    let info = info.clone();
+                    return Some((import, import_doc, info));
+                }
+            }
+            let info = doc.binding_named(&name.value)?.clone();
+            Some((uri.clone(), doc, info))
+        }
+        /// Find the declaration and references of a binding
+        ///
+        /// This includes names passed to `use` in open documents that import the binding's document.
+        /// Each location is marked with whether it is a `use` string.
+        fn binding_locations(
+            &self,
+            uri: &Url,
+            doc: &Arc<LspDoc>,
+            info: &Arc<BindingInfo>,
+        ) -> Vec<(Url, CodeSpan, bool)> {
+            let mut locations: Vec<_> = (doc.binding_spans(info).into_iter())
+                .map(|span| (uri.clone(), span.clone(), false))
+                .collect();
+            let mut docs: Vec<(Url, Arc<LspDoc>)> = (self.docs.iter())
+                .map(|entry| (entry.key().clone(), entry.value().clone()))
+                .collect();
+            if !self.docs.contains_key(uri) {
+                docs.push((uri.clone(), doc.clone()));
+            }
+            for (other_uri, other) in docs {
+                if other_uri != *uri && !import_uris(&other_uri, &other.imports).contains(uri) {
+                    continue;
+                }
+                for name in &other.uses {
+                    if name.value == info.name {
+                        locations.push((other_uri.clone(), name.span.clone(), true));
+                    }
+                }
+            }
+            locations
+        }
     }
 
     #[tower_lsp::async_trait]
     impl LanguageServer for Backend {
-        async fn initialize(&self, _params: InitializeParams) -> Result<InitializeResult> {
+        async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
+            let stack_heights = (params.initialization_options.as_ref())
+                .and_then(|options| options.get("stackHeightHints")?.as_bool());
+            (self.stack_height_hints).store(stack_heights.unwrap_or(false), Ordering::Relaxed);
             Ok(InitializeResult {
                 capabilities: ServerCapabilities {
+                    position_encoding: Some(PositionEncodingKind::UTF32),
                     text_document_sync: Some(TextDocumentSyncCapability::Kind(
-                        TextDocumentSyncKind::FULL,
+                        TextDocumentSyncKind::INCREMENTAL,
                     )),
                     hover_provider: Some(HoverProviderCapability::Simple(true)),
+                    definition_provider: Some(OneOf::Left(true)),
+                    references_provider: Some(OneOf::Left(true)),
+                    rename_provider: Some(OneOf::Left(true)),
+                    document_symbol_provider: Some(OneOf::Left(true)),
+                    inlay_hint_provider: Some(OneOf::Left(true)),
+                    signature_help_provider: Some(SignatureHelpOptions {
+                        trigger_characters: Some(vec![" ".into()]),
+                        ..Default::default()
+                    }),
+                    completion_provider: Some(CompletionOptions {
+                        trigger_characters: Some(vec!["&".into()]),
+                        ..Default::default()
+                    }),
                     document_formatting_provider: Some(OneOf::Left(true)),
                     semantic_tokens_provider: Some(
                         SemanticTokensServerCapabilities::SemanticTokensOptions(
@@ -260,26 +992,146 @@ mod server {
         }
 
         async fn did_open(&self, param: DidOpenTextDocumentParams) {
-            self.docs.insert(
-                param.text_document.uri,
-                LspDoc::new(param.text_document.text),
-            );
+            let document = param.text_document;
+            let change = TextDocumentContentChangeEvent {
+                range: None,
+                range_length: None,
+                text: document.text,
+            };
+            let updated = self.update(&document.uri, vec![change]);
+            self.publish(updated, Some(document.version)).await;
         }
 
         async fn did_change(&self, params: DidChangeTextDocumentParams) {
-            self.docs.insert(
-                params.text_document.uri,
-                LspDoc::new(params.content_changes[0].text.clone()),
-            );
+            let document = params.text_document;
+            let updated = self.update(&document.uri, params.content_changes);
+            self.publish(updated, Some(document.version)).await;
         }
 
-        async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
-            let doc = if let Some(doc) = self
-                .docs
-                .get(&params.text_document_position_params.text_document.uri)
+        async fn did_close(&self, params: DidCloseTextDocumentParams) {
+            let uri = params.text_document.uri;
+            let updated = self.close(&uri);
+            self.client.publish_diagnostics(uri, Vec::new(), None).await;
+            self.publish(updated, None).await;
+        }
+
+        async fn goto_definition(
+            &self,
+            params: GotoDefinitionParams,
+        ) -> Result<Option<GotoDefinitionResponse>> {
+            let position = params.text_document_position_params;
+            let Some((uri, _, info)) = self.resolve(&position.text_document.uri, position.position)
+            else {
+                return Ok(None);
+            };
+            Ok(Some(GotoDefinitionResponse::Scalar(Location {
+                uri,
+                range: uiua_span_to_lsp(&info.span),
+            })))
+        }
+
+        async fn references(&self, params: ReferenceParams) -> Result<Option<Vec<Location>>> {
+            let position = params.text_document_position;
+            let Some((uri, doc, info)) =
+                self.resolve(&position.text_document.uri, position.position)
+            else {
+                return Ok(None);
+            };
+            let locations = (self.binding_locations(&uri, &doc, &info).into_iter())
+                .filter(|(loc_uri, span, _)| {
+                    params.context.include_declaration || *loc_uri != uri || *span != info.span
+                })
+                .map(|(uri, span, _)| Location {
+                    uri,
+                    range: uiua_span_to_lsp(&span),
+                })
+                .collect();
+            Ok(Some(locations))
+        }
+
+        async fn rename(&self, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
+            let name = params.new_name;
+            if name.is_empty()
+                || name.starts_with('&')
+                || !name.chars().all(is_ident_char)
+                || Primitive::from_format_name_multi(&name).is_some()
             {
-                doc
-            } else {
+                return Err(jsonrpc::Error::invalid_params(format!(
+                    "`{name}` is not a valid binding name"
+                )));
+            }
+            let position = params.text_document_position;
+            let Some((uri, doc, info)) =
+                self.resolve(&position.text_document.uri, position.position)
+            else {
+                return Ok(None);
+            };
+            let mut changes: HashMap<Url, Vec<TextEdit>> = HashMap::new();
+            for (uri, span, is_use) in self.binding_locations(&uri, &doc, &info) {
+                let new_text = if is_use {
+                    format!("{name:?}")
+                } else {
+                    name.clone()
+                };
+                changes.entry(uri).or_default().push(TextEdit {
+                    range: uiua_span_to_lsp(&span),
+                    new_text,
+                });
+            }
+            Ok(Some(WorkspaceEdit {
+                changes: Some(changes),
+                ..Default::default()
+            }))
+        }
+
+        async fn document_symbol(
+            &self,
+            params: DocumentSymbolParams,
+        ) -> Result<Option<DocumentSymbolResponse>> {
+            let Some(doc) = self.docs.get(&params.text_document.uri) else {
+                return Ok(None);
+            };
+            Ok(Some(DocumentSymbolResponse::Nested(doc.symbols.clone())))
+        }
+
+        async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
+            let position = params.text_document_position;
+            let Some(doc) = self.doc(&position.text_document.uri) else {
+                return Ok(None);
+            };
+            let (line, col) = lsp_pos_to_uiua(position.position);
+            Ok(Some(CompletionResponse::Array(completions(
+                &doc, line, col,
+            ))))
+        }
+
+        async fn signature_help(
+            &self,
+            params: SignatureHelpParams,
+        ) -> Result<Option<SignatureHelp>> {
+            let position = params.text_document_position_params;
+            let Some(doc) = self.doc(&position.text_document.uri) else {
+                return Ok(None);
+            };
+            let (line, col) = lsp_pos_to_uiua(position.position);
+            Ok(signature_help(&doc, line, col))
+        }
+
+        async fn inlay_hint(&self, params: InlayHintParams) -> Result<Option<Vec<InlayHint>>> {
+            let Some(doc) = self.doc(&params.text_document.uri) else {
+                return Ok(None);
+            };
+            let stack_heights = self.stack_height_hints.load(Ordering::Relaxed);
+            let range = params.range;
+            let hints = (inlay_hints(&doc, stack_heights).into_iter())
+                .filter(|hint| range.start <= hint.position && hint.position <= range.end)
+                .collect();
+            Ok(Some(hints))
+        }
+
+        async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
+            let Some(doc) = self.doc(&params.text_document_position_params.text_document.uri)
+            else {
                 return Ok(None);
             };
             let (line, col) = lsp_pos_to_uiua(params.text_document_position_params.position);
@@ -302,6 +1154,13 @@ mod server {
                     binding_range = Some((ident, binding, uiua_span_to_lsp(&ident.span)));
                 }
             }
+            for name in &doc.uses {
+                if let Some(binding) = (doc.imported.get(&name.value))
+                    .filter(|_| name.span.contains_line_col(line, col))
+                {
+                    binding_range = Some((name, binding, uiua_span_to_lsp(&name.span)));
+                }
+            }
             Ok(Some(if let Some((prim, range)) = prim_range {
                 let mut contents = vec![MarkedString::String(prim.name().unwrap().into())];
                 if let Some(doc) = prim.doc() {
@@ -335,7 +1194,10 @@ mod server {
                     range: Some(range),
                 }
             } else if let Some((ident, binding, range)) = binding_range {
-                let mut contents = vec![MarkedString::String(ident.value.as_ref().into())];
+                let mut contents = vec![MarkedString::String(match binding.signature {
+                    Some(sig) => format!("{} {sig}", ident.value),
+                    None => ident.value.as_ref().into(),
+                })];
                 if let Some(comment) = &binding.comment {
                     contents.push(MarkedString::String(comment.clone()))
                 }
@@ -474,4 +1336,235 @@ mod server {
     fn uiua_span_to_lsp(span: &CodeSpan) -> Range {
         uiua_locs_to_lsp(span.start, span.end)
     }
+
+    fn report_span_to_lsp(span: &ReportSpan) -> Range {
+        let loc = |line, col| Loc {
+            line,
+            col,
+            ..Loc::default()
+        };
+        uiua_locs_to_lsp(
+            loc(span.start_line, span.start_col),
+            loc(span.end_line, span.end_col),
+        )
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn binding_references() {
+            let doc = LspDoc::new(
+                "\
+# Adds one
+F ← +1
+G ← (F F)
+---
+F ← ×2
+H ← F
+---
+F 5
+M ← &i \"lib.ua\"
+S ← use \"Square\" M"
+                    .into(),
+                None,
+                HashMap::new(),
+            );
+            let (_, outer) = doc.binding_at(2, 1).unwrap();
+            assert_eq!(outer.span.start.line, 2);
+            assert_eq!(outer.comment.as_deref(), Some("Adds one"));
+            let lines: Vec<usize> = (doc.binding_spans(outer).iter())
+                .map(|span| span.start.line)
+                .collect();
+            assert_eq!(lines, [2, 3, 3, 8]);
+            let (ident, inner) = doc.binding_at(6, 5).unwrap();
+            assert_eq!(&*ident.value, "F");
+            assert_eq!(inner.span.start.line, 5);
+            assert_eq!(doc.imports, ["lib.ua"]);
+            assert_eq!(&*doc.uses[0].value, "Square");
+            let names: Vec<&str> = doc.symbols.iter().map(|s| s.name.as_str()).collect();
+            assert_eq!(names, ["F", "G", "---", "M", "S"]);
+        }
+
+        #[test]
+        fn completion() {
+            let doc = LspDoc::new(
+                "\
+# Squares a number
+Square ← ×.
+---
+Sum ← /+
+---
+Sq sq
+rev &fr"
+                    .into(),
+                None,
+                HashMap::new(),
+            );
+            let labels = |line, col| -> Vec<(String, String)> {
+                (completions(&doc, line, col).into_iter())
+                    .map(|item| {
+                        let Some(CompletionTextEdit::Edit(edit)) = item.text_edit else {
+                            panic!("completion has no edit")
+                        };
+                        (item.label, edit.new_text)
+                    })
+                    .collect()
+            };
+            assert_eq!(labels(6, 3), [("Square".into(), "Square".into())]);
+            assert_eq!(labels(6, 6), [("sqrt".into(), "√".into())]);
+            assert!(!labels(6, 2).iter().any(|(label, _)| label == "Sum"));
+            assert_eq!(labels(7, 4), [("reverse".into(), "⇌".into())]);
+            let fr = completions(&doc, 7, 8);
+            let fras = fr.iter().find(|item| item.label == "&fras").unwrap();
+            assert_eq!(fras.detail.as_deref(), Some("file - read all to string"));
+            let square = completions(&doc, 6, 3);
+            let square = square.iter().find(|item| item.label == "Square").unwrap();
+            assert_eq!(square.detail.as_deref(), Some("|1.1"));
+            assert!(completions(&doc, 7, 4)[0].documentation.is_some());
+        }
+
+        #[test]
+        fn hints() {
+            let doc = LspDoc::new(
+                "\
+# Adds one
+F ← +1
+G ← |2.1 +
+X ← 5
+1 2 3
+F X
+---
+G
+---
+⊂ 1 F 2"
+                    .into(),
+                None,
+                HashMap::new(),
+            );
+            let hints = inlay_hints(&doc, false);
+            assert_eq!(hints.len(), 1);
+            assert_eq!(hints[0].position, Position::new(1, 4));
+            assert!(matches!(&hints[0].label, InlayHintLabel::String(s) if s == "|1.1"));
+            let heights: Vec<(u32, String)> = (inlay_hints(&doc, true).into_iter().skip(1))
+                .map(|hint| match hint.label {
+                    InlayHintLabel::String(s) => (hint.position.line, s),
+                    _ => panic!("hint label is not a string"),
+                })
+                .collect();
+            assert_eq!(
+                heights,
+                [
+                    (4, "height 3".into()),
+                    (5, "height 4".into()),
+                    (7, "height 3".into()),
+                    (9, "height 4".into())
+                ]
+            );
+            let help = |line, col| {
+                let help = signature_help(&doc, line, col).unwrap();
+                let info = help.signatures.into_iter().next().unwrap();
+                (info.label, info.documentation.is_some())
+            };
+            assert_eq!(help(10, 2), ("⊂ join |2.1".into(), true));
+            assert_eq!(help(10, 6), ("F |1.1".into(), true));
+            assert_eq!(help(8, 2), ("G |2.1".into(), false));
+            assert!(signature_help(&doc, 5, 1).is_none());
+        }
+
+        #[test]
+        fn workspace() {
+            let dir = std::env::temp_dir().join(format!("uiua_lsp_test_{}", std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("lib.ua"), "# Squares a number\nSquare ← ×.\n").unwrap();
+            let (service, _) = LspService::new(Backend::new);
+            let backend = service.inner();
+            let main = Url::from_file_path(dir.join("main.ua")).unwrap();
+            let lib = Url::from_file_path(dir.join("lib.ua")).unwrap();
+            let full = |text: &str| TextDocumentContentChangeEvent {
+                range: None,
+                range_length: None,
+                text: text.into(),
+            };
+            let code = "&i \"lib.ua\"\nsq ← use \"Square\"\nF ← |1.1 sq\nG ← |1.2 sq";
+            let updated = backend.update(&main, vec![full(code)]);
+            assert_eq!(updated.len(), 1);
+            let doc = &updated[0].1;
+            assert_eq!(doc.diagnostics.len(), 1, "{:?}", doc.diagnostics);
+            assert_eq!(doc.diagnostics[0].range.start.line, 3);
+            let (_, sq) = doc.binding_at(3, 10).unwrap();
+            assert_eq!(sq.comment.as_deref(), Some("Squares a number"));
+            assert_eq!(sq.signature, Some(Signature::new(1, 1)));
+            assert!(doc.imported.contains_key("Square"));
+
+            // Editing the imported file refreshes the file that imports it
+            let lib_input = backend.text(&lib).unwrap();
+            backend.update(&lib, vec![full(&lib_input)]);
+            let edit = TextDocumentContentChangeEvent {
+                range: Some(Range::new(Position::new(1, 10), Position::new(1, 11))),
+                range_length: None,
+                text: String::new(),
+            };
+            let updated = backend.update(&lib, vec![edit]);
+            assert_eq!(updated[0].1.input, "# Squares a number\nSquare ← ×\n");
+            assert_eq!(updated.len(), 2);
+            assert_eq!(updated[1].0, main);
+            assert_eq!(updated[1].1.diagnostics.len(), 2);
+
+            // Closing the imported file reloads the file that imports it from disk
+            let updated = backend.close(&lib);
+            assert_eq!(updated.len(), 1);
+            assert_eq!(updated[0].0, main);
+            assert_eq!(updated[0].1.diagnostics.len(), 1);
+            _ = std::fs::remove_dir_all(&dir);
+            assert!(!backend.docs.contains_key(&lib));
+
+            let mut input = "a😀b\nc".to_string();
+            apply_change(
+                &mut input,
+                TextDocumentContentChangeEvent {
+                    range: Some(Range::new(Position::new(0, 2), Position::new(1, 0))),
+                    range_length: None,
+                    text: "-".into(),
+                },
+            );
+            assert_eq!(input, "a😀-c");
+        }
+
+        #[test]
+        fn diagnostics() {
+            let doc = LspDoc::new(
+                "\
+F ← |2.1 +1
+G ← (|1 ×.)
+&p Foo
+H ← +Bar
+I ← F
+[1 2"
+                    .into(),
+                None,
+                HashMap::new(),
+            );
+            let mut diagnostics: Vec<(u32, &str)> = (doc.diagnostics.iter())
+                .map(|diag| (diag.range.start.line, diag.message.as_str()))
+                .collect();
+            diagnostics.sort();
+            assert_eq!(diagnostics.len(), 4, "{diagnostics:?}");
+            assert!(diagnostics[0].1.contains("declared |2.1 but inferred |1.1"));
+            assert_eq!(diagnostics[1].0, 2);
+            assert_eq!(diagnostics[1].1, "Unknown identifier `Foo`");
+            assert_eq!(diagnostics[2].0, 3);
+            assert_eq!(diagnostics[2].1, "Unknown identifier `Bar`");
+            assert_eq!(diagnostics[3].0, 5);
+            let range = doc.diagnostics.iter().find(|d| d.message.contains("Foo"));
+            assert_eq!(
+                range.unwrap().range,
+                Range::new(Position::new(2, 3), Position::new(2, 6))
+            );
+            assert!(LspDoc::new("F ← +1\nF 2".into(), None, HashMap::new())
+                .diagnostics
+                .is_empty());
+        }
+    }
 }
@@ -2,10 +2,12 @@
 compile_error!("To compile the uiua interpreter binary, you must enable the `binary` feature flag");
 
 use std::{
+    collections::BTreeSet,
     env, fs,
     io::{self, stderr, Write},
     path::{Path, PathBuf},
     process::{exit, Child, Command},
+    str::FromStr,
     sync::mpsc::channel,
     thread::sleep,
     time::Duration,
@@ -16,10 +18,15 @@ use instant::Instant;
 use notify::{EventKind, RecursiveMode, Watcher};
 use once_cell::sync::Lazy;
 use parking_lot::Mutex;
+use rustyline::{error::ReadlineError, DefaultEditor};
 use uiua::{
-    format::{format_file, FormatConfig},
+    format::{format_file, format_str, FormatConfig},
+    lex::Span,
+    package::{install, Manifest, PackageInfo, MANIFEST_FILE, MODULES_DIR},
+    primitive::CONSTANTS,
     run::RunMode,
-    Uiua, UiuaError, UiuaResult,
+    value::Value,
+    DebugHook, DebugPause, DebugStep, PauseReason, Uiua, UiuaError, UiuaResult,
 };
 
 fn main() {
@@ -63,7 +70,32 @@ fn run() -> UiuaResult {
                     } else {
                         fs::write("main.ua", "\"Hello, World!\"").unwrap();
                     }
+                    if !Path::new(MANIFEST_FILE).exists() {
+                        let name = (env::current_dir().ok())
+                            .and_then(|dir| Some(dir.file_name()?.to_string_lossy().into_owned()))
+                            .unwrap_or_else(|| "main".into());
+                        let manifest = Manifest {
+                            package: PackageInfo {
+                                name,
+                                entry: Some("main.ua".into()),
+                            },
+                            dependencies: Default::default(),
+                        };
+                        fs::write(MANIFEST_FILE, toml::to_string(&manifest).unwrap()).unwrap();
+                    }
+                    ignore_generated_files();
                 }
+                App::Install { update } => match install(Path::new("."), update) {
+                    Ok(lock) => {
+                        for package in lock.packages {
+                            println!("Installed {} from {}", package.name, package.source);
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!("{e}");
+                        exit(1);
+                    }
+                },
                 App::Fmt { path } => {
                     if let Some(path) = path {
                         format_file(path, &config)?;
@@ -77,20 +109,51 @@ fn run() -> UiuaResult {
                     path,
                     no_format,
                     mode,
+                    cache,
+                    no_optimize,
+                    error_format,
                     #[cfg(feature = "audio")]
                     audio_options,
                 } => {
                     if let Some(path) = path.or_else(working_file_path) {
-                        if !no_format {
-                            format_file(&path, &config)?;
-                        }
-                        let mode = mode.unwrap_or(RunMode::Normal);
-                        #[cfg(feature = "audio")]
-                        setup_audio(audio_options);
-                        let mut rt = Uiua::with_native_sys().with_mode(mode);
-                        rt.load_file(path)?;
-                        for value in rt.take_stack() {
-                            println!("{}", value.show());
+                        let res = (|| {
+                            let compiled = path.extension().is_some_and(|ext| ext == "uac");
+                            if !no_format && !compiled {
+                                format_file(&path, &config)?;
+                            }
+                            let mode = mode.unwrap_or(RunMode::Normal);
+                            #[cfg(feature = "audio")]
+                            setup_audio(audio_options);
+                            let mut rt = Uiua::with_native_sys()
+                                .with_mode(mode)
+                                .with_optimization(!no_optimize);
+                            if cache {
+                                rt = rt.with_cache_dir(CACHE_DIR);
+                            }
+                            if compiled {
+                                let bytes = fs::read(&path)
+                                    .map_err(|e| UiuaError::Load(path.clone(), e.into()))?;
+                                rt.load_compiled(&bytes)?;
+                            } else {
+                                rt.load_file(&path)?;
+                            }
+                            for value in rt.take_stack() {
+                                println!("{}", value.show());
+                            }
+                            Ok(())
+                        })();
+                        error_format.handle(res)?;
+                    } else {
+                        eprintln!("{NO_UA_FILE}");
+                    }
+                }
+                App::Compile { path, output } => {
+                    if let Some(path) = path.or_else(working_file_path) {
+                        let output = output.unwrap_or_else(|| path.with_extension("uac"));
+                        let bytes = Uiua::with_native_sys().compile_file(&path)?;
+                        if let Err(e) = fs::write(&output, bytes) {
+                            eprintln!("Failed to write {}: {e}", output.display());
+                            exit(1);
                         }
                     } else {
                         eprintln!("{NO_UA_FILE}");
@@ -98,23 +161,27 @@ fn run() -> UiuaResult {
                 }
                 App::Eval {
                     code,
+                    error_format,
                     #[cfg(feature = "audio")]
                     audio_options,
                 } => {
                     #[cfg(feature = "audio")]
                     setup_audio(audio_options);
                     let mut rt = Uiua::with_native_sys().with_mode(RunMode::Normal);
-                    rt.load_str(&code)?;
+                    error_format.handle(rt.load_str(&code).map(drop))?;
                     for value in rt.take_stack() {
                         println!("{}", value.show());
                     }
                 }
-                App::Test { path } => {
+                App::Test { path, error_format } => {
                     if let Some(path) = path.or_else(working_file_path) {
-                        format_file(&path, &config)?;
-                        Uiua::with_native_sys()
-                            .with_mode(RunMode::Test)
-                            .load_file(path)?;
+                        let res = format_file(&path, &config).and_then(|_| {
+                            Uiua::with_native_sys()
+                                .with_mode(RunMode::Test)
+                                .load_file(&path)
+                                .map(drop)
+                        });
+                        error_format.handle(res)?;
                         println!("No failures!");
                     } else {
                         eprintln!("{NO_UA_FILE}");
@@ -126,8 +193,28 @@ fn run() -> UiuaResult {
                         eprintln!("Error watching file: {e}");
                     }
                 }
+                App::Debug { path, breakpoints } => {
+                    if let Some(path) = path.or_else(working_file_path) {
+                        let debugger = CliDebugger {
+                            path: path.clone(),
+                            start_paused: breakpoints.is_empty(),
+                            breakpoints: breakpoints.into_iter().collect(),
+                            last_step: DebugStep::Into,
+                        };
+                        let mut rt = Uiua::with_native_sys().with_debug_hook(debugger);
+                        rt.load_file(path)?;
+                        for value in rt.take_stack() {
+                            println!("{}", value.show());
+                        }
+                    } else {
+                        eprintln!("{NO_UA_FILE}");
+                    }
+                }
+                App::Repl => repl(),
                 #[cfg(feature = "lsp")]
                 App::Lsp => uiua::lsp::run_server(),
+                #[cfg(feature = "dap")]
+                App::Dap => uiua::dap::run_server(),
             }
         }
         Err(e) if e.kind() == ErrorKind::DisplayHelpOnMissingArgumentOrSubcommand => {
@@ -143,7 +230,45 @@ fn run() -> UiuaResult {
 const NO_UA_FILE: &str =
     "No .ua file found nearby. Initialize one in the current directory with `uiua init`";
 
+/// Where `uiua run --cache` caches compiled files
+const CACHE_DIR: &str = ".uiua_cache";
+
+/// Add the directories that uiua generates to the .gitignore file
+fn ignore_generated_files() {
+    let path = Path::new(".gitignore");
+    let mut text = fs::read_to_string(path).unwrap_or_default();
+    let missing: Vec<String> = [CACHE_DIR, MODULES_DIR]
+        .into_iter()
+        .map(|dir| format!("/{dir}/"))
+        .filter(|entry| !text.lines().any(|line| line.trim() == entry))
+        .collect();
+    if missing.is_empty() {
+        return;
+    }
+    if !text.is_empty() && !text.ends_with('\n') {
+        text.push('\n');
+    }
+    for entry in missing {
+        text.push_str(&entry);
+        text.push('\n');
+    }
+    if let Err(e) = fs::write(path, text) {
+        eprintln!("Failed to write .gitignore: {e}");
+    }
+}
+
 fn working_file_path() -> Option<PathBuf> {
+    if Path::new(MANIFEST_FILE).exists() {
+        match Manifest::read(Path::new(".")) {
+            Ok(manifest) => {
+                let entry = PathBuf::from(manifest.entry());
+                if entry.exists() {
+                    return Some(entry);
+                }
+            }
+            Err(e) => eprintln!("{e}"),
+        }
+    }
     let main_in_src = PathBuf::from("src/main.ua");
     let main = if main_in_src.exists() {
         main_in_src
@@ -287,10 +412,239 @@ fn watch(initial_path: Option<&Path>, format: bool) -> io::Result<()> {
     }
 }
 
+const REPL_HELP: &str = "\
+Enter Uiua code to run it. ASCII glyph names are formatted to glyphs.
+The stack is kept between lines and shown after each one.
+
+Commands:
+  )stack     Show the stack
+  )clear     Clear the stack
+  )bindings  Show the names bound in this session
+  )help      Show this message
+  )quit      Exit the repl";
+
+fn repl() {
+    let mut rt = Uiua::with_native_sys();
+    let mut line_reader = match DefaultEditor::new() {
+        Ok(editor) => editor,
+        Err(e) => {
+            eprintln!("Failed to start repl: {e}");
+            return;
+        }
+    };
+    let config = FormatConfig {
+        trailing_newline: false,
+        ..Default::default()
+    };
+    println!(
+        "Uiua {} repl (use `)help` to see commands)",
+        env!("CARGO_PKG_VERSION")
+    );
+    loop {
+        let line = match line_reader.readline("   ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted | ReadlineError::Eof) => break,
+            Err(e) => {
+                eprintln!("Failed to read line: {e}");
+                break;
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        _ = line_reader.add_history_entry(&line);
+        match line.trim() {
+            ")help" => println!("{REPL_HELP}"),
+            ")quit" | ")exit" => break,
+            ")stack" => print_stack(&mut rt),
+            ")clear" => drop(rt.take_stack()),
+            ")bindings" => {
+                let mut bindings: Vec<_> = rt
+                    .bound_values()
+                    .into_iter()
+                    .filter(|(name, value)| {
+                        !CONSTANTS
+                            .iter()
+                            .any(|def| def.name == name.as_ref() && def.value == *value)
+                    })
+                    .collect();
+                bindings.sort_by(|(a, _), (b, _)| a.cmp(b));
+                for (name, value) in bindings {
+                    println!("{name} ← {value:?}");
+                }
+            }
+            command if command.starts_with(')') => {
+                println!("Unknown command `{command}` (use `)help` to see commands)")
+            }
+            _ => {
+                let code = match format_str(&line, &config) {
+                    Ok(formatted) => formatted.output,
+                    Err(e) => {
+                        println!("{}", e.show(true));
+                        continue;
+                    }
+                };
+                if code != line {
+                    println!("   {code}");
+                }
+                let backup = rt.take_stack();
+                for value in &backup {
+                    rt.push(value.clone());
+                }
+                match rt.load_str(&code) {
+                    Ok(_) => print_stack(&mut rt),
+                    Err(e) => {
+                        println!("{}", e.show(true));
+                        rt.take_stack();
+                        for value in backup {
+                            rt.push(value);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+const DEBUG_HELP: &str = "\
+Commands:
+  s, step      Run the next instruction, stepping into function calls
+  n, next      Run the next instruction, stepping over function calls
+  o, out       Run until the current function returns
+  c, continue  Run until the next breakpoint
+  b <line>     Set a breakpoint
+  d <line>     Delete a breakpoint
+  p, stack     Show the stack
+  bt           Show the call stack
+  q, quit      Stop the program
+  h, help      Show this message
+An empty line repeats the last step.";
+
+struct CliDebugger {
+    path: PathBuf,
+    breakpoints: BTreeSet<usize>,
+    start_paused: bool,
+    last_step: DebugStep,
+}
+
+impl DebugHook for CliDebugger {
+    fn entry(&mut self) -> DebugStep {
+        if self.start_paused {
+            println!("Paused at start (use `help` to see commands)");
+            DebugStep::Into
+        } else {
+            DebugStep::Continue
+        }
+    }
+    fn is_breakpoint(&mut self, path: Option<&Path>, line: usize) -> bool {
+        path == Some(&self.path) && self.breakpoints.contains(&line)
+    }
+    fn pause(&mut self, pause: &DebugPause) -> DebugStep {
+        let span = pause.span;
+        if pause.reason == PauseReason::Breakpoint {
+            println!("Breakpoint at {span}");
+        }
+        if let Some(line) = span.input.lines().nth(span.start.line - 1) {
+            println!("{:>4} │ {line}", span.start.line);
+            let width = if span.start.line == span.end.line {
+                span.end.col.saturating_sub(span.start.col).max(1)
+            } else {
+                1
+            };
+            println!(
+                "     │ {}{}",
+                " ".repeat(span.start.col - 1),
+                "^".repeat(width)
+            );
+        }
+        print_debug_stack(pause.stack);
+        let stdin = io::stdin();
+        loop {
+            print!("(debug) ");
+            _ = io::stdout().flush();
+            let mut line = String::new();
+            if stdin.read_line(&mut line).map_or(true, |n| n == 0) {
+                return DebugStep::Stop;
+            }
+            let mut words = line.split_whitespace();
+            let step = match words.next() {
+                None => self.last_step,
+                Some("s" | "step") => DebugStep::Into,
+                Some("n" | "next") => DebugStep::Over,
+                Some("o" | "out") => DebugStep::Out,
+                Some("c" | "continue") => DebugStep::Continue,
+                Some("q" | "quit") => return DebugStep::Stop,
+                Some(command @ ("b" | "d")) => {
+                    match words.next().map(str::parse::<usize>) {
+                        Some(Ok(line)) if command == "b" => {
+                            self.breakpoints.insert(line);
+                        }
+                        Some(Ok(line)) => {
+                            self.breakpoints.remove(&line);
+                        }
+                        _ => println!("Expected a line number"),
+                    }
+                    continue;
+                }
+                Some("p" | "stack") => {
+                    print_debug_stack(pause.stack);
+                    continue;
+                }
+                Some("bt") => {
+                    for frame in pause.frames.iter().rev() {
+                        match &frame.span {
+                            Span::Code(span) => println!("  in {} at {span}", frame.function),
+                            Span::Builtin => println!("  in {}", frame.function),
+                        }
+                    }
+                    continue;
+                }
+                Some("h" | "help") => {
+                    println!("{DEBUG_HELP}");
+                    continue;
+                }
+                Some(command) => {
+                    println!("Unknown command `{command}` (use `help` to see commands)");
+                    continue;
+                }
+            };
+            self.last_step = step;
+            return step;
+        }
+    }
+}
+
+fn print_debug_stack(stack: &[Value]) {
+    if stack.is_empty() {
+        println!("stack is empty");
+    }
+    for value in stack.iter().rev() {
+        println!("  {value:?}");
+    }
+}
+
+fn print_stack(rt: &mut Uiua) {
+    let stack = rt.take_stack();
+    for value in &stack {
+        println!("{}", value.show());
+    }
+    for value in stack {
+        rt.push(value);
+    }
+}
+
 #[derive(Parser)]
 enum App {
-    #[clap(about = "Initialize a new main.ua file")]
+    #[clap(about = "Initialize a new main.ua file and uiua.toml manifest")]
     Init,
+    #[clap(about = "Fetch the dependencies in uiua.toml into uiua_modules and write uiua.lock")]
+    Install {
+        #[clap(
+            long,
+            help = "Fetch the latest commits of git dependencies instead of locked ones"
+        )]
+        update: bool,
+    },
     #[clap(about = "Format and run a file")]
     Run {
         path: Option<PathBuf>,
@@ -298,19 +652,41 @@ enum App {
         no_format: bool,
         #[clap(long, help = "Run the file in a specific mode")]
         mode: Option<RunMode>,
+        #[clap(long, help = "Cache compiled files in .uiua_cache")]
+        cache: bool,
+        #[clap(long, help = "Don't optimize the compiled code")]
+        no_optimize: bool,
+        #[clap(long, default_value = "human", help = ERROR_FORMAT_HELP)]
+        error_format: ErrorFormat,
         #[cfg(feature = "audio")]
         #[clap(flatten)]
         audio_options: AudioOptions,
     },
+    #[clap(about = "Compile a file into a .uac file that can be run without its source")]
+    Compile {
+        path: Option<PathBuf>,
+        #[clap(
+            short,
+            long,
+            help = "The file to write. Defaults to the input with a .uac extension"
+        )]
+        output: Option<PathBuf>,
+    },
     #[clap(about = "Evaluate an expression and print its output")]
     Eval {
         code: String,
+        #[clap(long, default_value = "human", help = ERROR_FORMAT_HELP)]
+        error_format: ErrorFormat,
         #[cfg(feature = "audio")]
         #[clap(flatten)]
         audio_options: AudioOptions,
     },
     #[clap(about = "Format and test a file")]
-    Test { path: Option<PathBuf> },
+    Test {
+        path: Option<PathBuf>,
+        #[clap(long, default_value = "human", help = ERROR_FORMAT_HELP)]
+        error_format: ErrorFormat,
+    },
     #[clap(about = "Run .ua files in the current directory when they change")]
     Watch {
         #[clap(long, help = "Don't format the file before running")]
@@ -318,9 +694,58 @@ enum App {
     },
     #[clap(about = "Format a uiua file or all files in the current directory")]
     Fmt { path: Option<PathBuf> },
+    #[clap(about = "Run a file in the step debugger")]
+    Debug {
+        path: Option<PathBuf>,
+        #[clap(
+            short,
+            long = "break",
+            help = "Set a breakpoint on a line. If none are set, execution pauses at the start"
+        )]
+        breakpoints: Vec<usize>,
+    },
+    #[clap(about = "Start an interactive read-eval-print loop")]
+    Repl,
     #[cfg(feature = "lsp")]
     #[clap(about = "Run the Language Server")]
     Lsp,
+    #[cfg(feature = "dap")]
+    #[clap(about = "Run the Debug Adapter Protocol server")]
+    Dap,
+}
+
+const ERROR_FORMAT_HELP: &str =
+    "How to print errors, either `human` or `json`. JSON errors are printed to stderr";
+
+/// How errors are printed by commands that run code
+#[derive(Clone, Copy)]
+enum ErrorFormat {
+    Human,
+    Json,
+}
+
+impl FromStr for ErrorFormat {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "human" => Ok(ErrorFormat::Human),
+            "json" => Ok(ErrorFormat::Json),
+            _ => Err(format!("unknown error format `{}`", s)),
+        }
+    }
+}
+
+impl ErrorFormat {
+    /// Print an error as JSON and exit, or pass it on to be shown normally
+    fn handle(self, res: UiuaResult) -> UiuaResult {
+        match (self, res) {
+            (ErrorFormat::Json, Err(e)) => {
+                eprintln!("{}", serde_json::to_string(&e.reports()).unwrap());
+                exit(1)
+            }
+            (_, res) => res,
+        }
+    }
 }
 
 #[cfg(feature = "audio")]
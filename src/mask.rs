@@ -0,0 +1,265 @@
+//! Packed boolean masks
+//!
+//! Comparisons of large arrays produce a [`Mask`], which stores each 0 or 1 in a single bit
+//! rather than a byte. A mask stands for the byte array that it packs. It stays packed while
+//! it is moved around the stack, and is unpacked when it is popped by an operation that does
+//! not handle masks itself. Operations that do handle masks, like `keep`, and `minimum` or
+//! `maximum` of two masks, work on a whole word of bits at a time.
+
+use std::{fmt, sync::OnceLock};
+
+use ecow::EcoVec;
+
+use crate::{
+    array::{Array, ArrayValue, Shape},
+    value::Value,
+    Uiua, UiuaResult,
+};
+
+/// The number of elements at or above which the result of a comparison is packed
+pub const PACK_LEN: usize = 1 << 10;
+
+/// A boolean array with one bit per element
+#[derive(Clone)]
+pub struct Mask {
+    shape: Shape,
+    /// Element `i` is bit `i % 64` of word `i / 64`. Bits past the end are always 0.
+    words: EcoVec<u64>,
+    /// The unpacked bytes, made the first time they are needed
+    ///
+    /// This is boxed to keep [`Value`] small.
+    bytes: OnceLock<Box<Array<u8>>>,
+}
+
+impl Mask {
+    /// Pack a byte array, if all of its elements are 0 or 1
+    pub fn pack(bytes: &Array<u8>) -> Option<Self> {
+        let mut words = EcoVec::with_capacity(bytes.data.len().div_ceil(64));
+        for chunk in bytes.data.chunks(64) {
+            let mut word = 0;
+            for (i, &b) in chunk.iter().enumerate() {
+                if b > 1 {
+                    return None;
+                }
+                word |= u64::from(b) << i;
+            }
+            words.push(word);
+        }
+        Some(Mask {
+            shape: bytes.shape.clone(),
+            words,
+            bytes: OnceLock::new(),
+        })
+    }
+    pub fn shape(&self) -> &[usize] {
+        &self.shape
+    }
+    pub fn flat_len(&self) -> usize {
+        self.shape.iter().product()
+    }
+    pub fn row_count(&self) -> usize {
+        self.shape.first().copied().unwrap_or(1)
+    }
+    /// Get the element at an index into the flat data
+    pub fn get(&self, i: usize) -> bool {
+        i < self.flat_len() && self.words[i / 64] >> (i % 64) & 1 == 1
+    }
+    /// The number of elements that are 1
+    pub fn count(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+    /// Iterate over the indices of the elements that are 1
+    pub fn ones(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(i, &word)| {
+            let mut word = word;
+            std::iter::from_fn(move || {
+                (word != 0).then(|| {
+                    let bit = word.trailing_zeros() as usize;
+                    word &= word - 1;
+                    i * 64 + bit
+                })
+            })
+        })
+    }
+    /// Get the byte array the mask stands for
+    pub fn unpacked(&self) -> &Array<u8> {
+        self.bytes.get_or_init(|| self.unpack().into())
+    }
+    /// Turn the mask into the byte array it stands for
+    pub fn into_unpacked(mut self) -> Array<u8> {
+        match self.bytes.take() {
+            Some(bytes) => *bytes,
+            None => self.unpack(),
+        }
+    }
+    fn unpack(&self) -> Array<u8> {
+        let data: EcoVec<u8> = (self.words.iter())
+            .flat_map(|&word| (0..64).map(move |i| (word >> i & 1) as u8))
+            .take(self.flat_len())
+            .collect();
+        Array::new(self.shape.clone(), data)
+    }
+    /// Combine two masks with the same shape a word at a time
+    pub fn zip(&self, other: &Self, f: impl Fn(u64, u64) -> u64) -> Option<Self> {
+        if self.shape != other.shape {
+            return None;
+        }
+        let mut words: EcoVec<u64> = (self.words.iter().zip(&other.words))
+            .map(|(&a, &b)| f(a, b))
+            .collect();
+        // Keep the bits past the end 0
+        let tail = self.flat_len() % 64;
+        if tail != 0 {
+            let last = words.len() - 1;
+            words.make_mut()[last] &= (1 << tail) - 1;
+        }
+        Some(Mask {
+            shape: self.shape.clone(),
+            words,
+            bytes: OnceLock::new(),
+        })
+    }
+}
+
+impl PartialEq for Mask {
+    fn eq(&self, other: &Self) -> bool {
+        self.shape == other.shape && self.words == other.words
+    }
+}
+
+impl fmt::Debug for Mask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.unpacked(), f)
+    }
+}
+
+impl fmt::Display for Mask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.unpacked(), f)
+    }
+}
+
+impl From<Mask> for Value {
+    fn from(mask: Mask) -> Self {
+        Value::Mask(mask)
+    }
+}
+
+impl Value {
+    /// Pack a large byte array of 0s and 1s into a mask
+    pub(crate) fn packed(self) -> Self {
+        match self {
+            Value::Byte(bytes) if bytes.flat_len() >= PACK_LEN => match Mask::pack(&bytes) {
+                Some(mask) => mask.into(),
+                None => bytes.into(),
+            },
+            val => val,
+        }
+    }
+    /// Turn a mask into the byte array it stands for
+    pub fn unpacked(self) -> Self {
+        match self {
+            Value::Mask(mask) => mask.into_unpacked().into(),
+            val => val,
+        }
+    }
+    /// Replace a mask with the byte array it stands for
+    pub(crate) fn unpack(&mut self) {
+        if let Value::Mask(_) = self {
+            *self = std::mem::take(self).unpacked();
+        }
+    }
+}
+
+/// Run a dyadic pervasive primitive that may take or produce masks
+///
+/// If both arguments are masks with the same shape and the primitive has a bitwise form,
+/// the result is computed a word at a time. Otherwise the masks are unpacked, and if
+/// `pack` is set, a large result is packed.
+pub(crate) fn dyadic(
+    env: &mut Uiua,
+    f: fn(&Value, &Value, &Uiua) -> UiuaResult<Value>,
+    bits: Option<fn(u64, u64) -> u64>,
+    pack: bool,
+) -> UiuaResult {
+    let a = env.pop_packed(1)?;
+    let b = env.pop_packed(2)?;
+    if let (Some(bits), Value::Mask(ma), Value::Mask(mb)) = (bits, &a, &b) {
+        if let Some(mask) = ma.zip(mb, bits) {
+            env.push(mask);
+            return Ok(());
+        }
+    }
+    let res = f(&a.unpacked(), &b.unpacked(), env)?;
+    env.push(if pack { res.packed() } else { res });
+    Ok(())
+}
+
+/// Run [`Primitive::Keep`](crate::primitive::Primitive::Keep), selecting rows with the set bits of a mask
+pub(crate) fn keep(env: &mut Uiua) -> UiuaResult {
+    let counts = env.pop_packed(1)?;
+    let kept = env.pop(2)?;
+    match counts {
+        Value::Mask(mask) if mask.shape().len() == 1 && mask.flat_len() == kept.row_count() => {
+            let kept: Value = match kept {
+                Value::Num(a) => keep_rows(a, &mask).into(),
+                Value::Byte(a) => keep_rows(a, &mask).into(),
+                Value::Int(a) => keep_rows(a, &mask).into(),
+                Value::Char(a) => keep_rows(a, &mask).into(),
+                Value::Func(a) => keep_rows(a, &mask).into(),
+                Value::Mask(a) => keep_rows(a.into_unpacked(), &mask).into(),
+            };
+            env.push(kept);
+        }
+        counts => env.push(counts.unpacked().keep(kept, env)?),
+    }
+    Ok(())
+}
+
+fn keep_rows<T: ArrayValue>(arr: Array<T>, mask: &Mask) -> Array<T> {
+    let row_len = arr.row_len();
+    let mut data = EcoVec::with_capacity(mask.count() * row_len);
+    for i in mask.ones() {
+        data.extend_from_slice(&arr.data[i * row_len..(i + 1) * row_len]);
+    }
+    let mut shape = arr.shape;
+    shape[0] = mask.count();
+    Array::new(shape, data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mask_matches_bytes() {
+        let bytes: Vec<u8> = (0..200).map(|i| (i % 3 == 0 || i % 7 == 0) as u8).collect();
+        let arr = Array::new(Shape::from(&[2, 100][..]), bytes.clone());
+        let mask = Mask::pack(&arr).unwrap();
+        assert_eq!(mask.unpacked(), &arr);
+        assert_eq!(mask.count(), bytes.iter().filter(|&&b| b == 1).count());
+        let ones: Vec<usize> = mask.ones().collect();
+        let expected: Vec<usize> = (0..200).filter(|&i| bytes[i] == 1).collect();
+        assert_eq!(ones, expected);
+        assert!(Mask::pack(&Array::from(vec![0u8, 2])).is_none());
+
+        // Bits past the end stay 0 after combining
+        let not = mask.zip(&mask, |a, b| !(a ^ b)).unwrap();
+        assert_eq!(not.count(), 200);
+        assert_eq!(not.unpacked().data.iter().filter(|&&b| b == 1).count(), 200);
+    }
+
+    #[test]
+    fn comparisons_pack() {
+        let mut env = Uiua::with_native_sys().with_optimization(false);
+        env.load_str("↧ . >5 ⇡3000 <5 ⇡10 ≠0 ◿3 ⇡3000").unwrap();
+        let count = |val| match val {
+            Ok(Value::Mask(mask)) => mask.count(),
+            val => panic!("expected a mask, got {val:?}"),
+        };
+        assert_eq!(count(env.pop_packed(1)), 2994);
+        // Small results stay bytes
+        assert!(matches!(env.pop_packed(2), Ok(Value::Byte(_))));
+        assert_eq!(count(env.pop_packed(3)), 2000);
+    }
+}
@@ -0,0 +1,808 @@
+//! Optimization of compiled instructions
+//!
+//! After code is compiled, constant expressions are evaluated, and some patterns
+//! of instructions are fused into kernels that do the same work with fewer passes
+//! over the data.
+
+use std::{any::Any, mem::take, sync::Arc};
+
+use ecow::EcoVec;
+
+use crate::{
+    algorithm::{loops, parallel, pervade::*},
+    array::{Array, ArrayValue},
+    function::Instr,
+    primitive::{PrimClass, Primitive},
+    value::Value,
+    Uiua, UiuaResult,
+};
+
+/// The most elements a folded constant may have
+///
+/// Larger arrays are made when the code runs, so that compiled code stays small.
+const MAX_FOLDED_LEN: usize = 1000;
+
+/// Instructions fused into a kernel
+#[derive(Debug)]
+pub struct Fused {
+    pub(crate) kernel: Kernel,
+    /// The instructions the kernel replaces
+    pub instrs: Vec<Instr>,
+}
+
+#[derive(Debug)]
+pub(crate) enum Kernel {
+    /// A chain of pervasive primitives, each with an optional scalar first argument
+    Pervasive {
+        /// A dyadic primitive that combines two arrays before the chain
+        binary: Option<Primitive>,
+        steps: Vec<(Primitive, Option<f64>)>,
+    },
+    /// `≡f◫` with a scalar window size
+    RowsWindows,
+    /// `/f◫` with a scalar window size
+    ReduceWindows,
+}
+
+/// Optimize compiled instructions
+pub(crate) fn optimize(instrs: Vec<Instr>, env: &mut Uiua) -> Vec<Instr> {
+    fuse(fold_constants(instrs, env))
+}
+
+/// Evaluate primitives whose arguments are all constants
+fn fold_constants(instrs: Vec<Instr>, env: &mut Uiua) -> Vec<Instr> {
+    let mut folded: Vec<Instr> = Vec::with_capacity(instrs.len());
+    for instr in instrs {
+        let args = match &instr {
+            Instr::Prim(prim, _) => foldable_args(*prim),
+            _ => None,
+        };
+        let start = (args.and_then(|args| folded.len().checked_sub(args)))
+            .filter(|&start| folded[start..].iter().all(is_foldable_push));
+        if let Some(start) = start {
+            let mut code = folded[start..].to_vec();
+            code.push(instr.clone());
+            if let Some(values) = eval(code, env) {
+                folded.truncate(start);
+                folded.extend(values.into_iter().map(Instr::push));
+                continue;
+            }
+        }
+        folded.push(instr);
+    }
+    folded
+}
+
+/// Get the number of arguments of a primitive if it can be folded
+///
+/// Only primitives whose results cannot depend on a fill value are folded,
+/// because a fill may be set when the code runs.
+fn foldable_args(prim: Primitive) -> Option<usize> {
+    use Primitive::*;
+    let foldable = match prim.class() {
+        PrimClass::Constant | PrimClass::MonadicPervasive | PrimClass::DyadicPervasive => true,
+        _ => matches!(
+            prim,
+            Len | Rank
+                | Shape
+                | Range
+                | Reverse
+                | Deshape
+                | Transpose
+                | Bits
+                | Grade
+                | Classify
+                | Deduplicate
+        ),
+    };
+    if foldable {
+        prim.args().map(Into::into)
+    } else {
+        None
+    }
+}
+
+fn is_foldable_push(instr: &Instr) -> bool {
+    match instr {
+        Instr::Push(val) => !matches!(**val, Value::Func(_)) && val.flat_len() <= MAX_FOLDED_LEN,
+        _ => false,
+    }
+}
+
+/// Run constant code, returning the values it produces if it succeeds
+fn eval(code: Vec<Instr>, env: &mut Uiua) -> Option<Vec<Value>> {
+    // Range is the only foldable primitive whose result can be much larger than its argument
+    if let [Instr::Push(n), Instr::Prim(Primitive::Range, _)] = code.as_slice() {
+        if n.rank() > 0 || !matches!(n.as_num(env, ""), Ok(n) if n <= MAX_FOLDED_LEN as f64) {
+            return None;
+        }
+    }
+    let stack = env.take_stack();
+    let res = env.exec_global_instrs(code);
+    let values = env.take_stack();
+    for val in stack {
+        env.push(val);
+    }
+    res.ok()?;
+    (values.iter())
+        .all(|val| val.flat_len() <= MAX_FOLDED_LEN)
+        .then_some(values)
+}
+
+/// Fuse patterns of instructions into kernels
+pub(crate) fn fuse(instrs: Vec<Instr>) -> Vec<Instr> {
+    let mut fused = Vec::with_capacity(instrs.len());
+    let mut i = 0;
+    while i < instrs.len() {
+        if let Some((kernel, len)) = kernel(&instrs[i..]) {
+            let instrs = instrs[i..i + len].to_vec();
+            fused.push(Instr::Fused(Arc::new(Fused { kernel, instrs })));
+            i += len;
+        } else {
+            fused.push(instrs[i].clone());
+            i += 1;
+        }
+    }
+    fused
+}
+
+/// Expand fused kernels back into the instructions they replace, if there are any
+pub(crate) fn unfuse(instrs: &[Instr]) -> Option<Vec<Instr>> {
+    if !instrs.iter().any(|instr| matches!(instr, Instr::Fused(_))) {
+        return None;
+    }
+    let mut unfused = Vec::with_capacity(instrs.len());
+    for instr in instrs {
+        match instr {
+            Instr::Fused(fused) => unfused.extend(fused.instrs.iter().cloned()),
+            instr => unfused.push(instr.clone()),
+        }
+    }
+    Some(unfused)
+}
+
+/// Find a kernel at the start of some instructions, along with how many instructions it replaces
+fn kernel(instrs: &[Instr]) -> Option<(Kernel, usize)> {
+    use Primitive::*;
+    // Windows
+    if let [Instr::Prim(Windows, _), Instr::Push(f), Instr::Prim(modifier, _), ..] = instrs {
+        if let Some(f) = f.as_function() {
+            let sig = f.signature();
+            match modifier {
+                Rows if sig.args == 1 && sig.outputs <= 1 => return Some((Kernel::RowsWindows, 3)),
+                Reduce => return Some((Kernel::ReduceWindows, 3)),
+                _ => {}
+            }
+        }
+    }
+    // Pervasive chains
+    let binary = match instrs {
+        [Instr::Prim(prim, _), ..] if is_fusable_dyadic(*prim) => Some(*prim),
+        _ => None,
+    };
+    let mut steps = Vec::new();
+    let mut len = binary.is_some() as usize;
+    loop {
+        match &instrs[len..] {
+            [Instr::Prim(prim, _), ..] if prim.class() == PrimClass::MonadicPervasive => {
+                steps.push((*prim, None));
+                len += 1;
+            }
+            [Instr::Push(arg), Instr::Prim(prim, _), ..] if is_fusable_dyadic(*prim) => {
+                let Value::Num(arg) = &**arg else {
+                    break;
+                };
+                let Some(&arg) = arg.as_scalar() else {
+                    break;
+                };
+                steps.push((*prim, Some(arg)));
+                len += 2;
+            }
+            _ => break,
+        }
+    }
+    (binary.is_some() as usize + steps.len() >= 2)
+        .then_some((Kernel::Pervasive { binary, steps }, len))
+}
+
+fn is_fusable_dyadic(prim: Primitive) -> bool {
+    prim.class() == PrimClass::DyadicPervasive && prim != Primitive::Atan
+}
+
+impl Fused {
+    pub(crate) fn run(&self, env: &mut Uiua) -> UiuaResult {
+        match &self.kernel {
+            Kernel::Pervasive {
+                binary: None,
+                steps,
+            } if env.stack_size() > 0 => match pervasive(steps, env.pop(1)?, env.thread_count()) {
+                Ok(val) => {
+                    env.push(val.packed());
+                    return Ok(());
+                }
+                Err(val) => env.push(val),
+            },
+            &Kernel::Pervasive {
+                binary: Some(prim),
+                ref steps,
+            } if env.stack_size() > 1 => {
+                let a = env.pop(1)?;
+                let b = env.pop(2)?;
+                match binary_pervasive(prim, steps, a, b, env.thread_count()) {
+                    Ok(val) => {
+                        env.push(val.packed());
+                        return Ok(());
+                    }
+                    Err(args) => {
+                        let (a, b) = *args;
+                        env.push(b);
+                        env.push(a);
+                    }
+                }
+            }
+            Kernel::RowsWindows | Kernel::ReduceWindows if env.stack_size() > 1 => {
+                let size = env.pop(1)?;
+                let xs = env.pop(2)?;
+                if let Some(size) = window_size(&size, &xs) {
+                    let [_, Instr::Push(f), Instr::Prim(modifier, span)] = self.instrs.as_slice()
+                    else {
+                        unreachable!("window kernels replace three instructions")
+                    };
+                    let f = Value::clone(f);
+                    env.push_span(*span, Some(*modifier));
+                    if let Kernel::RowsWindows = self.kernel {
+                        loops::rows_windows(f, size, xs, env)?;
+                    } else {
+                        loops::reduce_windows(f, size, xs, env)?;
+                    }
+                    env.pop_span();
+                    return Ok(());
+                }
+                env.push(xs);
+                env.push(size);
+            }
+            _ => {}
+        }
+        env.run_unfused(&self.instrs)
+    }
+}
+
+/// The number of elements a fused chain works on at a time
+///
+/// Each step runs over a whole chunk, so that its loop can be vectorized.
+const CHUNK: usize = 256;
+
+/// Run some code with `$f` bound to the function of a fusable monadic primitive
+macro_rules! with_monadic {
+    ($prim:expr, |$f:ident| $body:expr) => {
+        match $prim {
+            Not => {
+                let $f = not::num;
+                $body
+            }
+            Neg => {
+                let $f = neg::num;
+                $body
+            }
+            Abs => {
+                let $f = abs::num;
+                $body
+            }
+            Sign => {
+                let $f = sign::num;
+                $body
+            }
+            Sqrt => {
+                let $f = sqrt::num;
+                $body
+            }
+            Sin => {
+                let $f = sin::num;
+                $body
+            }
+            Cos => {
+                let $f = cos::num;
+                $body
+            }
+            Asin => {
+                let $f = asin::num;
+                $body
+            }
+            Acos => {
+                let $f = acos::num;
+                $body
+            }
+            Floor => {
+                let $f = floor::num;
+                $body
+            }
+            Ceil => {
+                let $f = ceil::num;
+                $body
+            }
+            Round => {
+                let $f = round::num;
+                $body
+            }
+            prim => unreachable!("{prim} is not fused"),
+        }
+    };
+}
+
+/// Run some code with `$f` bound to the function of a fusable dyadic primitive
+macro_rules! with_dyadic {
+    ($prim:expr, |$f:ident| $body:expr) => {
+        match $prim {
+            Eq => {
+                let $f = |a, b| f64::from(is_eq::num_num(a, b));
+                $body
+            }
+            Ne => {
+                let $f = |a, b| f64::from(is_ne::num_num(a, b));
+                $body
+            }
+            Lt => {
+                let $f = |a, b| f64::from(is_lt::num_num(a, b));
+                $body
+            }
+            Le => {
+                let $f = |a, b| f64::from(is_le::num_num(a, b));
+                $body
+            }
+            Gt => {
+                let $f = |a, b| f64::from(is_gt::num_num(a, b));
+                $body
+            }
+            Ge => {
+                let $f = |a, b| f64::from(is_ge::num_num(a, b));
+                $body
+            }
+            Add => {
+                let $f = add::num_num;
+                $body
+            }
+            Sub => {
+                let $f = sub::num_num;
+                $body
+            }
+            Mul => {
+                let $f = mul::num_num;
+                $body
+            }
+            Div => {
+                let $f = div::num_num;
+                $body
+            }
+            Mod => {
+                let $f = modulus::num_num;
+                $body
+            }
+            Pow => {
+                let $f = pow::num_num;
+                $body
+            }
+            Log => {
+                let $f = log::num_num;
+                $body
+            }
+            Min => {
+                let $f = min::num_num;
+                $body
+            }
+            Max => {
+                let $f = max::num_num;
+                $body
+            }
+            prim => unreachable!("{prim} is not fused"),
+        }
+    };
+}
+
+/// Apply a chain of pervasive primitives to a number or byte array
+///
+/// Each element is computed once and written to a single buffer, which is the
+/// input's own if it is not shared. Other values are given back to be handled
+/// by the unfused instructions.
+fn pervasive(
+    steps: &[(Primitive, Option<f64>)],
+    val: Value,
+    threads: usize,
+) -> Result<Value, Value> {
+    let bytes = chain_bytes(steps, matches!(val, Value::Byte(_)));
+    Ok(match (val, bytes) {
+        (Value::Num(arr), false) => map::<_, f64>(arr, steps, threads).into(),
+        (Value::Num(arr), true) => map::<_, u8>(arr, steps, threads).into(),
+        (Value::Byte(arr), false) => map::<_, f64>(arr, steps, threads).into(),
+        (Value::Byte(arr), true) => map::<_, u8>(arr, steps, threads).into(),
+        (val, _) => return Err(val),
+    })
+}
+
+/// Combine two number or byte arrays with a dyadic primitive, then apply a chain of
+/// pervasive primitives to the result
+///
+/// The arrays must have the same shape, or one must be a scalar. Like [`pervasive`],
+/// this writes to a single buffer, reusing one of the inputs' if possible.
+/// Other arrays are given back, boxed to keep the result small.
+fn binary_pervasive(
+    prim: Primitive,
+    steps: &[(Primitive, Option<f64>)],
+    a: Value,
+    b: Value,
+    threads: usize,
+) -> Result<Value, Box<(Value, Value)>> {
+    use Primitive::*;
+    if !(a.shape() == b.shape() || a.rank() == 0 || b.rank() == 0) {
+        return Err(Box::new((a, b)));
+    }
+    let bytes = match prim {
+        Eq | Ne | Lt | Le | Gt | Ge => true,
+        Min | Max => matches!((&a, &b), (Value::Byte(_), Value::Byte(_))),
+        _ => false,
+    };
+    Ok(if chain_bytes(steps, bytes) {
+        binary::<u8>(prim, steps, a, b, threads)?.into()
+    } else {
+        binary::<f64>(prim, steps, a, b, threads)?.into()
+    })
+}
+
+fn binary<U: Elem>(
+    prim: Primitive,
+    steps: &[(Primitive, Option<f64>)],
+    a: Value,
+    b: Value,
+    threads: usize,
+) -> Result<Array<U>, Box<(Value, Value)>> {
+    Ok(match (a, b) {
+        (Value::Num(a), Value::Num(b)) => zip(a, b, prim, steps, threads),
+        (Value::Num(a), Value::Byte(b)) => zip(a, b, prim, steps, threads),
+        (Value::Byte(a), Value::Num(b)) => zip(a, b, prim, steps, threads),
+        (Value::Byte(a), Value::Byte(b)) => zip(a, b, prim, steps, threads),
+        (a, b) => return Err(Box::new((a, b))),
+    })
+}
+
+/// Whether the result of a chain is bytes, given whether its input is
+///
+/// Bytes stay bytes through comparisons and rounding.
+fn chain_bytes(steps: &[(Primitive, Option<f64>)], mut bytes: bool) -> bool {
+    use Primitive::*;
+    for (prim, _) in steps {
+        bytes = match prim {
+            Eq | Ne | Lt | Le | Gt | Ge => true,
+            Abs | Sign | Floor | Ceil | Round => bytes,
+            _ => false,
+        };
+    }
+    bytes
+}
+
+/// An element type that fused pervasive kernels work with
+trait Elem: ArrayValue + Copy + Default + Into<f64> + 'static {
+    /// Convert from a result that is known to fit
+    fn from_f64(x: f64) -> Self;
+}
+
+impl Elem for f64 {
+    fn from_f64(x: f64) -> Self {
+        x
+    }
+}
+
+impl Elem for u8 {
+    fn from_f64(x: f64) -> Self {
+        x as u8
+    }
+}
+
+/// Get an array to write the output to in place, if it has the output's shape and
+/// element type and its buffer is not shared
+fn reusable<'a, T: Elem, U: Elem>(
+    arr: &'a mut Array<T>,
+    shape: &[usize],
+) -> Option<&'a mut Array<U>> {
+    if *arr.shape != *shape || !arr.data.is_unique() {
+        return None;
+    }
+    (arr as &mut dyn Any).downcast_mut()
+}
+
+fn load<T: Elem>(src: &[T], xs: &mut [f64]) {
+    for (x, &src) in xs.iter_mut().zip(src) {
+        *x = src.into();
+    }
+}
+
+fn store<U: Elem>(xs: &[f64], dest: &mut [U]) {
+    for (dest, &x) in dest.iter_mut().zip(xs) {
+        *dest = U::from_f64(x);
+    }
+}
+
+/// Run a chain's steps over an array
+fn map<T: Elem, U: Elem>(
+    mut arr: Array<T>,
+    steps: &[(Primitive, Option<f64>)],
+    threads: usize,
+) -> Array<U> {
+    let shape = arr.shape.clone();
+    let threads = parallel::threads(threads, arr.data.len());
+    if let Some(out) = reusable::<_, U>(&mut arr, &shape) {
+        let data: &mut [U] = &mut out.data;
+        parallel::for_each_part(data, CHUNK, threads, |_, part| {
+            let mut xs = [0.0; CHUNK];
+            for chunk in part.chunks_mut(CHUNK) {
+                let xs = &mut xs[..chunk.len()];
+                load(chunk, xs);
+                run_steps(steps, xs);
+                store(xs, chunk);
+            }
+        });
+        return take(out);
+    }
+    let mut data = EcoVec::from_elem(U::default(), arr.data.len());
+    parallel::for_each_part(data.make_mut(), CHUNK, threads, |start, part| {
+        let mut xs = [0.0; CHUNK];
+        let src = &arr.data[start..][..part.len()];
+        for (src, dest) in src.chunks(CHUNK).zip(part.chunks_mut(CHUNK)) {
+            let xs = &mut xs[..src.len()];
+            load(src, xs);
+            run_steps(steps, xs);
+            store(xs, dest);
+        }
+    });
+    Array::new(shape, data)
+}
+
+/// Combine two arrays of the same shape, either of which may be a scalar, with a
+/// dyadic primitive, then run a chain's steps over the result
+fn zip<A: Elem, B: Elem, U: Elem>(
+    mut a: Array<A>,
+    mut b: Array<B>,
+    prim: Primitive,
+    steps: &[(Primitive, Option<f64>)],
+    threads: usize,
+) -> Array<U> {
+    let shape = if a.rank() == 0 {
+        b.shape.clone()
+    } else {
+        a.shape.clone()
+    };
+    let threads = parallel::threads(threads, shape.iter().product());
+    // A scalar fills its buffer once, and an array loads each chunk
+    let a_scalar = a.rank() == 0 && b.rank() > 0;
+    let b_scalar = b.rank() == 0 && a.rank() > 0;
+    let a_init = if a_scalar { a.data[0].into() } else { 0.0 };
+    let b_init = if b_scalar { b.data[0].into() } else { 0.0 };
+    if let Some(out) = reusable::<_, U>(&mut a, &shape) {
+        let data: &mut [U] = &mut out.data;
+        parallel::for_each_part(data, CHUNK, threads, |start, part| {
+            let (mut a_buf, mut b_buf, mut xs) = ([0.0; CHUNK], [b_init; CHUNK], [0.0; CHUNK]);
+            for (i, chunk) in part.chunks_mut(CHUNK).enumerate() {
+                let n = chunk.len();
+                load(chunk, &mut a_buf[..n]);
+                if !b_scalar {
+                    load(&b.data[start + i * CHUNK..][..n], &mut b_buf[..n]);
+                }
+                binary_chunk(prim, steps, &a_buf[..n], &b_buf[..n], &mut xs[..n]);
+                store(&xs[..n], chunk);
+            }
+        });
+        return take(out);
+    }
+    if let Some(out) = reusable::<_, U>(&mut b, &shape) {
+        let data: &mut [U] = &mut out.data;
+        parallel::for_each_part(data, CHUNK, threads, |start, part| {
+            let (mut a_buf, mut b_buf, mut xs) = ([a_init; CHUNK], [0.0; CHUNK], [0.0; CHUNK]);
+            for (i, chunk) in part.chunks_mut(CHUNK).enumerate() {
+                let n = chunk.len();
+                if !a_scalar {
+                    load(&a.data[start + i * CHUNK..][..n], &mut a_buf[..n]);
+                }
+                load(chunk, &mut b_buf[..n]);
+                binary_chunk(prim, steps, &a_buf[..n], &b_buf[..n], &mut xs[..n]);
+                store(&xs[..n], chunk);
+            }
+        });
+        return take(out);
+    }
+    let mut data = EcoVec::from_elem(U::default(), shape.iter().product());
+    parallel::for_each_part(data.make_mut(), CHUNK, threads, |start, part| {
+        let (mut a_buf, mut b_buf, mut xs) = ([a_init; CHUNK], [b_init; CHUNK], [0.0; CHUNK]);
+        for (i, chunk) in part.chunks_mut(CHUNK).enumerate() {
+            let n = chunk.len();
+            if !a_scalar {
+                load(&a.data[start + i * CHUNK..][..n], &mut a_buf[..n]);
+            }
+            if !b_scalar {
+                load(&b.data[start + i * CHUNK..][..n], &mut b_buf[..n]);
+            }
+            binary_chunk(prim, steps, &a_buf[..n], &b_buf[..n], &mut xs[..n]);
+            store(&xs[..n], chunk);
+        }
+    });
+    Array::new(shape, data)
+}
+
+fn binary_chunk(
+    prim: Primitive,
+    steps: &[(Primitive, Option<f64>)],
+    a: &[f64],
+    b: &[f64],
+    xs: &mut [f64],
+) {
+    use Primitive::*;
+    with_dyadic!(prim, |f| for ((x, &a), &b) in xs.iter_mut().zip(a).zip(b) {
+        *x = f(a, b);
+    });
+    run_steps(steps, xs);
+}
+
+fn run_steps(steps: &[(Primitive, Option<f64>)], xs: &mut [f64]) {
+    use Primitive::*;
+    for &(prim, arg) in steps {
+        if let Some(a) = arg {
+            with_dyadic!(prim, |f| for x in xs.iter_mut() {
+                *x = f(a, *x);
+            });
+        } else {
+            with_monadic!(prim, |f| for x in xs.iter_mut() {
+                *x = f(*x);
+            });
+        }
+    }
+}
+
+/// Get the window size for a window kernel, if the kernel can handle it
+fn window_size(size: &Value, xs: &Value) -> Option<usize> {
+    let size = match size {
+        Value::Num(arr) => {
+            let n = *arr.as_scalar()?;
+            (n >= 0.0 && n.fract() == 0.0).then_some(n as usize)?
+        }
+        Value::Byte(arr) => *arr.as_scalar()? as usize,
+        _ => return None,
+    };
+    (xs.rank() > 0 && size > 0 && size <= xs.row_count() && xs.flat_len() > 0).then_some(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Primitive::*;
+
+    fn prim(prim: Primitive) -> Instr {
+        Instr::Prim(prim, 0)
+    }
+
+    fn run(code: &str, optimize: bool) -> Result<Vec<Value>, String> {
+        let mut env = Uiua::with_native_sys().with_optimization(optimize);
+        match env.load_str(code) {
+            Ok(_) => Ok(env.take_stack()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    #[test]
+    fn fold_constants() {
+        let mut env = Uiua::with_native_sys();
+        let folded = optimize(
+            vec![Instr::push(1.0), Instr::push(2.0), prim(Add), prim(Range)],
+            &mut env,
+        );
+        let [Instr::Push(val)] = folded.as_slice() else {
+            panic!("expected a single value, got {folded:?}");
+        };
+        assert_eq!(val.show(), "[0 1 2]");
+
+        // Errors, large results, and fill-dependent primitives are left alone
+        for instrs in [
+            vec![Instr::push(1.0), Instr::push("a"), prim(Mul)],
+            vec![Instr::push(1e9), prim(Range)],
+            vec![Instr::push(2.0), Instr::push(1.0), prim(Take)],
+        ] {
+            assert_eq!(optimize(instrs.clone(), &mut env), instrs);
+        }
+        assert_eq!(env.stack_size(), 0);
+    }
+
+    #[test]
+    fn fuse_kernels() {
+        let fused = fuse(vec![
+            prim(Sin),
+            Instr::push(1.0),
+            prim(Add),
+            Instr::push(2.0),
+            prim(Mul),
+            prim(Len),
+        ]);
+        assert!(matches!(
+            fused.as_slice(),
+            [Instr::Fused(_), Instr::Prim(Len, _)]
+        ));
+
+        let binary = fuse(vec![prim(Add), Instr::push(2.0), prim(Mul)]);
+        assert!(matches!(binary.as_slice(), [Instr::Fused(_)]));
+
+        let single = vec![Instr::push(1.0), prim(Add), prim(Len)];
+        assert_eq!(fuse(single.clone()), single);
+
+        let mut env = Uiua::with_native_sys();
+        for code in ["(≡/+◫3)", "(/+◫3)"] {
+            env.load_str(code).unwrap();
+            let f = env.pop("function").unwrap();
+            let f = f.as_function().unwrap();
+            assert!(matches!(
+                f.instrs.as_slice(),
+                [Instr::Push(_), Instr::Fused(_)]
+            ));
+        }
+    }
+
+    #[test]
+    fn reuse_buffers() {
+        let arr = Array::from(vec![1.0, 2.0, 3.0]);
+        let ptr = arr.data.as_ptr();
+        let out: Array<f64> = map(arr, &[(Mul, Some(2.0))], 1);
+        assert_eq!(out.data.as_ptr(), ptr);
+        assert_eq!(out.data, [2.0, 4.0, 6.0]);
+
+        // Shared buffers are not modified
+        let shared = out.clone();
+        let new: Array<f64> = map(out, &[(Add, Some(1.0))], 1);
+        assert_ne!(new.data.as_ptr(), ptr);
+        assert_eq!(shared.data, [2.0, 4.0, 6.0]);
+
+        // The second argument is reused if the first can't be
+        let a = Array::<u8>::from(vec![1, 2, 3]);
+        let b = Array::from(vec![1.0, 2.0, 3.0]);
+        let ptr = b.data.as_ptr();
+        let out: Array<f64> = zip(a, b, Add, &[], 1);
+        assert_eq!(out.data.as_ptr(), ptr);
+        assert_eq!(out.data, [2.0, 4.0, 6.0]);
+
+        // Arrays longer than a chunk, split across threads
+        let a = Array::from((0..100_000).map(f64::from).collect::<Vec<_>>());
+        let out: Array<u8> = zip(a, Array::unit(500.0), Lt, &[(Not, None)], 4);
+        assert!((out.data.iter().enumerate()).all(|(i, &x)| x == u8::from(i <= 500)));
+    }
+
+    #[test]
+    fn optimized_results_match() {
+        // Arguments are passed to functions so that they are not folded
+        for (f, args) in [
+            ("≡/+◫3", "⇡10"),
+            ("/+◫2", "[1 2 3 4]"),
+            ("/(⊂□)◫2", "[1 2 3]"),
+            ("≡(⊂.)◫2", "\"abcd\""),
+            ("≡/+◫", "[2] [1 2 3]"),
+            ("≡/+◫", "4 [1 2 3]"),
+            ("≡/+◫", "0 [1 2 3]"),
+            ("/+◫2", "[]"),
+            ("≡/+◫", "2 5"),
+            ("×2+1○", "[0 1 2]"),
+            ("<3¯⌊", "[1 2 5]"),
+            ("⌊¯=1", "[1 2 3]"),
+            ("+1=1", "[1 2 3]"),
+            ("+1¯", "\"ab\""),
+            ("×2+1", "\"ab\""),
+            ("-1×2", "{1 2}"),
+            ("+1×2", "[]"),
+            ("×2+", "[1 2 3] [4 5 6]"),
+            ("¬=", "[1 2 3] [1 5 3]"),
+            ("+1↥", "[1 2] [3 0]"),
+            ("⌊↧", "⌊=1[1 2] =2[1 2]"),
+            ("+1×2", "⌊=1[1 2 3]"),
+            ("<2⌊", "⌊=1[1 2 3]"),
+            ("×2-", "1 [1 2 3]"),
+            ("×2-", "[1 2 3] 1"),
+            ("×2+", "[1 2] [1 2 3]"),
+            ("×2+", "\"ab\" 1"),
+            ("×2+", "⇡3 ↯3_2 ⇡6"),
+        ] {
+            let code = format!("F ← {f}\nF {args}");
+            assert_eq!(run(&code, true), run(&code, false), "{code}");
+        }
+    }
+}
@@ -0,0 +1,541 @@
+//! Package manifests, lockfiles, and fetching dependencies
+//!
+//! A package is a directory with a `uiua.toml` manifest:
+//!
+//! ```toml
+//! [package]
+//! name = "example"
+//! entry = "main.ua"
+//!
+//! [dependencies]
+//! local = { path = "../local" }
+//! remote = { git = "https://github.com/user/remote", rev = "v1.0" }
+//! ```
+//!
+//! [`install`] copies every dependency, including those of dependencies, into the
+//! `uiua_modules` directory and records what it fetched in `uiua.lock`.
+//! [`&i`](https://uiua.org/docs/&i) and module imports can then name a dependency directly.
+//! `&i "remote"` imports the dependency's entry file, and `&i "remote/other.ua"` imports one of its files.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::SysBackend;
+
+/// The name of a package's manifest file
+pub const MANIFEST_FILE: &str = "uiua.toml";
+/// The name of a package's lockfile
+pub const LOCK_FILE: &str = "uiua.lock";
+/// The name of the directory dependencies are fetched into
+pub const MODULES_DIR: &str = "uiua_modules";
+
+const DEFAULT_ENTRY: &str = "main.ua";
+
+/// The contents of a `uiua.toml` file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    pub package: PackageInfo,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub dependencies: BTreeMap<String, Dependency>,
+}
+
+/// The `[package]` section of a manifest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PackageInfo {
+    pub name: String,
+    /// The file that is run, or imported when the package is a dependency
+    ///
+    /// Defaults to `main.ua`
+    pub entry: Option<String>,
+}
+
+/// A dependency declared in a manifest
+///
+/// Exactly one of `path` and `git` must be set.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Dependency {
+    /// A directory, relative to the package that declares the dependency
+    pub path: Option<String>,
+    /// The url of a git repository
+    pub git: Option<String>,
+    /// A commit, branch, or tag of the git repository
+    ///
+    /// Defaults to the repository's `HEAD`
+    pub rev: Option<String>,
+}
+
+/// The contents of a `uiua.lock` file
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lockfile {
+    #[serde(default, rename = "package")]
+    pub packages: Vec<LockedPackage>,
+}
+
+/// A dependency fetched into the modules directory
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedPackage {
+    pub name: String,
+    /// Where the package was fetched from, like `path+../local` or `git+<url>?rev=v1.0`
+    pub source: String,
+    /// The commit that was checked out, for git dependencies
+    pub commit: Option<String>,
+    /// The package's entry file, relative to its directory in the modules directory
+    pub entry: String,
+}
+
+impl Manifest {
+    /// Read the manifest in a package directory
+    pub fn read(dir: &Path) -> Result<Self, String> {
+        let path = dir.join(MANIFEST_FILE);
+        let text = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+        let manifest: Self =
+            toml::from_str(&text).map_err(|e| format!("Invalid {}: {e}", path.display()))?;
+        for name in manifest.dependencies.keys() {
+            check_name(name).map_err(|e| format!("Invalid {}: {e}", path.display()))?;
+        }
+        Ok(manifest)
+    }
+    /// Get the package's entry file, relative to its directory
+    pub fn entry(&self) -> &str {
+        self.package.entry.as_deref().unwrap_or(DEFAULT_ENTRY)
+    }
+}
+
+impl Dependency {
+    /// Get the lockfile source of this dependency when declared by a package in `dir`
+    fn source(&self, name: &str, dir: &Path) -> Result<Source, String> {
+        match (&self.path, &self.git) {
+            (Some(path), None) => {
+                if self.rev.is_some() {
+                    return Err(format!("Path dependency `{name}` cannot have a `rev`"));
+                }
+                Ok(Source::Path(dir.join(path)))
+            }
+            (None, Some(url)) => Ok(Source::Git {
+                url: url.clone(),
+                rev: self.rev.clone(),
+            }),
+            _ => Err(format!(
+                "Dependency `{name}` must have exactly one of `path` or `git`"
+            )),
+        }
+    }
+}
+
+enum Source {
+    Path(PathBuf),
+    Git { url: String, rev: Option<String> },
+}
+
+impl Source {
+    fn to_lock_string(&self) -> String {
+        match self {
+            Source::Path(path) => {
+                let path = path.strip_prefix(".").unwrap_or(path);
+                format!("path+{}", path.display())
+            }
+            Source::Git { url, rev: None } => format!("git+{url}"),
+            Source::Git {
+                url,
+                rev: Some(rev),
+            } => format!("git+{url}?rev={rev}"),
+        }
+    }
+}
+
+impl Lockfile {
+    /// Read the lockfile in a package directory, if there is one
+    pub fn read(dir: &Path) -> Result<Option<Self>, String> {
+        let path = dir.join(LOCK_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let text = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+        Self::parse(&text, &path).map(Some)
+    }
+    fn parse(text: &str, path: &Path) -> Result<Self, String> {
+        let lock: Self =
+            toml::from_str(text).map_err(|e| format!("Invalid {}: {e}", path.display()))?;
+        for package in &lock.packages {
+            check_name(&package.name).map_err(|e| format!("Invalid {}: {e}", path.display()))?;
+        }
+        Ok(lock)
+    }
+    /// Get a locked package by name
+    pub fn get(&self, name: &str) -> Option<&LockedPackage> {
+        self.packages.iter().find(|package| package.name == name)
+    }
+}
+
+/// Check that a dependency name is a plain identifier
+///
+/// Names are used as directory names in the modules directory.
+fn check_name(name: &str) -> Result<(), String> {
+    let mut chars = name.chars();
+    if chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        Ok(())
+    } else {
+        Err(format!(
+            "Dependency name `{name}` must start with a letter \
+            and contain only letters, digits, `_`, and `-`"
+        ))
+    }
+}
+
+/// Fetch the dependencies of the package in `root` and write its lockfile
+///
+/// Git dependencies are checked out at the commit in the existing lockfile if their
+/// source has not changed. Pass `update` to fetch the latest commit instead.
+///
+/// Dependencies are fetched into a temporary directory, which only replaces the
+/// modules directory once every dependency has been fetched.
+pub fn install(root: &Path, update: bool) -> Result<Lockfile, String> {
+    let manifest = Manifest::read(root)?;
+    let old_lock = if update { None } else { Lockfile::read(root)? };
+    let staging = root.join(format!("{MODULES_DIR}.tmp"));
+    remove_dir(&staging)?;
+    fs::create_dir_all(&staging)
+        .map_err(|e| format!("Failed to create {}: {e}", staging.display()))?;
+    let lock = match fetch_all(root, manifest, old_lock.as_ref(), &staging) {
+        Ok(lock) => lock,
+        Err(e) => {
+            _ = fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+    };
+    // Swap the fetched dependencies in
+    let modules = root.join(MODULES_DIR);
+    let old = root.join(format!("{MODULES_DIR}.old"));
+    remove_dir(&old)?;
+    if modules.exists() {
+        fs::rename(&modules, &old)
+            .map_err(|e| format!("Failed to move {}: {e}", modules.display()))?;
+    }
+    fs::rename(&staging, &modules)
+        .map_err(|e| format!("Failed to move {}: {e}", staging.display()))?;
+    remove_dir(&old)?;
+    let text = toml::to_string(&lock).map_err(|e| e.to_string())?;
+    let path = root.join(LOCK_FILE);
+    fs::write(
+        &path,
+        format!("# This file is generated by `uiua install`. Do not edit it.\n\n{text}"),
+    )
+    .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+    Ok(lock)
+}
+
+/// Remove a directory if it exists
+fn remove_dir(dir: &Path) -> Result<(), String> {
+    if dir.exists() {
+        fs::remove_dir_all(dir).map_err(|e| format!("Failed to remove {}: {e}", dir.display()))?;
+    }
+    Ok(())
+}
+
+/// Fetch every dependency of a package into `modules`
+fn fetch_all(
+    root: &Path,
+    manifest: Manifest,
+    old_lock: Option<&Lockfile>,
+    modules: &Path,
+) -> Result<Lockfile, String> {
+    let mut lock = Lockfile::default();
+    // Dependencies are fetched breadth-first, each from the package that declared it
+    let mut queue: Vec<(String, Dependency, PathBuf)> = (manifest.dependencies.into_iter())
+        .map(|(name, dep)| (name, dep, root.to_path_buf()))
+        .collect();
+    let mut i = 0;
+    while i < queue.len() {
+        let (name, dep, dir) = queue[i].clone();
+        i += 1;
+        let source = dep.source(&name, &dir)?;
+        let source_string = source.to_lock_string();
+        if let Some(locked) = lock.get(&name) {
+            if locked.source == source_string {
+                continue;
+            }
+            return Err(format!(
+                "Dependency `{name}` is required from both {} and {source_string}",
+                locked.source
+            ));
+        }
+        let dest = modules.join(&name);
+        let commit = match &source {
+            Source::Path(path) => {
+                copy_package(path, &dest)?;
+                None
+            }
+            Source::Git { url, rev } => {
+                let locked_commit = old_lock
+                    .and_then(|lock| lock.get(&name))
+                    .filter(|locked| locked.source == source_string)
+                    .and_then(|locked| locked.commit.clone());
+                let rev = locked_commit.as_ref().or(rev.as_ref());
+                Some(fetch_git(url, rev.map(String::as_str), &dest)?)
+            }
+        };
+        // A dependency's own path dependencies are relative to where it came from
+        let origin = match &source {
+            Source::Path(path) => path.clone(),
+            Source::Git { .. } => dest.clone(),
+        };
+        let entry = if dest.join(MANIFEST_FILE).exists() {
+            let dep_manifest = Manifest::read(&dest)?;
+            for (sub_name, sub_dep) in dep_manifest.dependencies.iter() {
+                queue.push((sub_name.clone(), sub_dep.clone(), origin.clone()));
+            }
+            dep_manifest.entry().to_string()
+        } else {
+            DEFAULT_ENTRY.to_string()
+        };
+        lock.packages.push(LockedPackage {
+            name,
+            source: source_string,
+            commit,
+            entry,
+        });
+    }
+    lock.packages.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(lock)
+}
+
+/// Copy a package directory, leaving out its own dependencies and version control
+fn copy_package(from: &Path, to: &Path) -> Result<(), String> {
+    if !from.is_dir() {
+        return Err(format!("{} is not a directory", from.display()));
+    }
+    fs::create_dir_all(to).map_err(|e| format!("Failed to create {}: {e}", to.display()))?;
+    let entries =
+        fs::read_dir(from).map_err(|e| format!("Failed to read {}: {e}", from.display()))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let name = entry.file_name();
+        if [".git", MODULES_DIR, LOCK_FILE].iter().any(|n| name == *n) {
+            continue;
+        }
+        let path = entry.path();
+        if path.is_dir() {
+            copy_package(&path, &to.join(&name))?;
+        } else {
+            fs::copy(&path, to.join(&name))
+                .map_err(|e| format!("Failed to copy {}: {e}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Clone a git repository and check out a revision, returning the commit hash
+fn fetch_git(url: &str, rev: Option<&str>, dest: &Path) -> Result<String, String> {
+    let git = |args: &[&str], dir: Option<&Path>| -> Result<String, String> {
+        let mut command = Command::new("git");
+        if let Some(dir) = dir {
+            command.current_dir(dir);
+        }
+        let output = command
+            .args(args)
+            .output()
+            .map_err(|e| format!("Failed to run git: {e}"))?;
+        if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        } else {
+            Err(format!(
+                "git {} failed: {}",
+                args[0],
+                String::from_utf8_lossy(&output.stderr).trim()
+            ))
+        }
+    };
+    // Keep urls and revisions from being read as options
+    if url.starts_with('-') {
+        return Err(format!("Invalid git url `{url}`"));
+    }
+    if let Some(rev) = rev.filter(|rev| rev.starts_with('-')) {
+        return Err(format!("Invalid git revision `{rev}`"));
+    }
+    let dest_str = dest.to_string_lossy();
+    git(&["clone", "--quiet", "--", url, &dest_str], None)?;
+    if let Some(rev) = rev {
+        git(&["checkout", "--quiet", rev, "--"], Some(dest))?;
+    }
+    let commit = git(&["rev-parse", "HEAD"], Some(dest))?;
+    fs::remove_dir_all(dest.join(".git"))
+        .map_err(|e| format!("Failed to remove git metadata: {e}"))?;
+    Ok(commit)
+}
+
+/// Resolve an import path that names a locked dependency
+///
+/// The lockfile is looked for in the importing file's directory and its ancestors.
+/// Returns `None` if no lockfile has a dependency with the path's first component as its name.
+pub(crate) fn resolve_import(
+    backend: &dyn SysBackend,
+    importer: Option<&Path>,
+    path: &str,
+) -> Result<Option<PathBuf>, String> {
+    let (name, rest) = path.split_once('/').unwrap_or((path, ""));
+    let mut dir = importer.and_then(Path::parent).unwrap_or(Path::new(""));
+    loop {
+        let lock_path = dir.join(LOCK_FILE);
+        let lock_str = lock_path.to_string_lossy();
+        if backend.file_exists(&lock_str) {
+            let bytes = backend.file_read_all(&lock_str)?;
+            let lock = Lockfile::parse(&String::from_utf8_lossy(&bytes), &lock_path)?;
+            let Some(package) = lock.get(name) else {
+                return Ok(None);
+            };
+            let package_dir = dir.join(MODULES_DIR).join(name);
+            let file = if rest.is_empty() {
+                &package.entry
+            } else {
+                rest
+            };
+            return Ok(Some(package_dir.join(file)));
+        }
+        match dir.parent() {
+            Some(parent) => dir = parent,
+            None => return Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::Path, process::Command};
+
+    use super::*;
+    use crate::Uiua;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .expect("failed to run git");
+        assert!(status.status.success(), "git {args:?} failed: {status:?}");
+    }
+
+    /// Commit a file to a bare repository through a working clone
+    fn commit(work: &Path, file: &str, contents: &str) {
+        fs::write(work.join(file), contents).unwrap();
+        git(work, &["add", "-A"]);
+        git(work, &["commit", "--quiet", "-m", file]);
+        git(work, &["push", "--quiet", "origin", "HEAD"]);
+    }
+
+    #[test]
+    fn install_and_import() {
+        let root = env::temp_dir().join(format!("uiua_package_test_{}", std::process::id()));
+        _ = fs::remove_dir_all(&root);
+        let bare = root.join("remote.git");
+        let work = root.join("remote_work");
+        let local = root.join("local");
+        let app = root.join("app");
+        for dir in [&bare, &local, &app] {
+            fs::create_dir_all(dir).unwrap();
+        }
+        git(&bare, &["init", "--quiet", "--bare"]);
+        git(&root, &["clone", "--quiet", "remote.git", "remote_work"]);
+        commit(
+            &work,
+            "uiua.toml",
+            "[package]\nname = \"remote\"\nentry = \"lib.ua\"\n",
+        );
+        commit(&work, "lib.ua", "Answer ← 42\n");
+
+        // A path dependency that depends on the git repository
+        fs::write(
+            local.join("uiua.toml"),
+            format!(
+                "[package]\nname = \"local\"\n\n[dependencies]\nremote = {{ git = {:?} }}\n",
+                bare.display().to_string()
+            ),
+        )
+        .unwrap();
+        fs::write(
+            local.join("main.ua"),
+            "R ? \"remote\"\nDouble ← ×2 R?Answer\n",
+        )
+        .unwrap();
+        fs::write(local.join("extra.ua"), "7\n").unwrap();
+
+        fs::write(
+            app.join("uiua.toml"),
+            "[package]\nname = \"app\"\n\n[dependencies]\nlocal = { path = \"../local\" }\n",
+        )
+        .unwrap();
+        let main = "L ? \"local\"\n⍤.=84 L?Double\n⍤.=7 &i \"local/extra.ua\"\n";
+        fs::write(app.join("main.ua"), main).unwrap();
+
+        let lock = install(&app, false).unwrap();
+        let names: Vec<_> = lock.packages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["local", "remote"]);
+        assert_eq!(lock.get("remote").unwrap().entry, "lib.ua");
+        assert!(app.join("uiua_modules/remote/lib.ua").exists());
+        assert!(!app.join("uiua_modules/remote/.git").exists());
+        assert_eq!(Lockfile::read(&app).unwrap(), Some(lock.clone()));
+        if let Err(e) = Uiua::with_native_sys().load_file(app.join("main.ua")) {
+            panic!("{}", e.show(false));
+        }
+
+        // Reinstalling keeps the locked commit until updating
+        commit(&work, "lib.ua", "Answer ← 43\n");
+        let relocked = install(&app, false).unwrap();
+        assert_eq!(relocked, lock);
+        let updated = install(&app, true).unwrap();
+        assert_ne!(
+            updated.get("remote").unwrap().commit,
+            lock.get("remote").unwrap().commit
+        );
+        let answer = fs::read_to_string(app.join("uiua_modules/remote/lib.ua")).unwrap();
+        assert_eq!(answer, "Answer ← 43\n");
+
+        // Invalid dependencies
+        fs::write(
+            app.join("uiua.toml"),
+            "[package]\nname = \"app\"\n\n[dependencies]\nbad = { path = \"a\", git = \"b\" }\n",
+        )
+        .unwrap();
+        assert_eq!(
+            install(&app, false).unwrap_err(),
+            "Dependency `bad` must have exactly one of `path` or `git`"
+        );
+        // A failed install leaves the installed dependencies alone
+        assert_eq!(
+            answer,
+            fs::read_to_string(app.join("uiua_modules/remote/lib.ua")).unwrap()
+        );
+        assert!(!app.join("uiua_modules.tmp").exists());
+        for (deps, message) in [
+            ("\"../up\" = { path = \"a\" }", "Dependency name `../up`"),
+            ("opt = { git = \"--upload-pack=x\" }", "Invalid git url"),
+            (
+                "opt = { git = \"a\", rev = \"-b\" }",
+                "Invalid git revision",
+            ),
+        ] {
+            fs::write(
+                app.join("uiua.toml"),
+                format!("[package]\nname = \"app\"\n\n[dependencies]\n{deps}\n"),
+            )
+            .unwrap();
+            let err = install(&app, false).unwrap_err();
+            assert!(err.contains(message), "{err}");
+        }
+
+        _ = fs::remove_dir_all(&root);
+    }
+}
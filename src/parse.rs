@@ -17,6 +17,7 @@ pub enum ParseError {
     InvalidArgCount(String),
     InvalidOutCount(String),
     AmpersandBindingName,
+    QuestionBindingName,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -78,6 +79,7 @@ impl fmt::Display for ParseError {
             ParseError::InvalidArgCount(n) => write!(f, "Invalid argument count `{n}`"),
             ParseError::InvalidOutCount(n) => write!(f, "Invalid output count `{n}`"),
             ParseError::AmpersandBindingName => write!(f, "Binding names may not contain `&`"),
+            ParseError::QuestionBindingName => write!(f, "Binding names may not contain `?`"),
         }
     }
 }
@@ -188,7 +190,9 @@ impl Parser {
     }
     fn try_item(&mut self, parse_scopes: bool) -> Option<Item> {
         self.try_spaces();
-        Some(if let Some(binding) = self.try_binding() {
+        Some(if let Some(import) = self.try_import() {
+            Item::Import(import)
+        } else if let Some(binding) = self.try_binding() {
             Item::Binding(binding)
         } else if let Some(words) = self.try_words() {
             Item::Words(words)
@@ -225,6 +229,10 @@ impl Parser {
                 self.index = start;
                 return None;
             }
+            if ident.value.contains('?') {
+                (self.errors).push(ident.span.clone().sp(ParseError::QuestionBindingName));
+            }
+            let private = self.try_exact(Question).is_some();
             self.try_spaces();
             let sig = self.try_signature();
             let words = self.try_words().unwrap_or_default();
@@ -232,11 +240,31 @@ impl Parser {
                 name: ident,
                 words,
                 signature: sig,
+                private,
             }
         } else {
             return None;
         })
     }
+    fn try_import(&mut self) -> Option<Import> {
+        let start = self.index;
+        let name = self.try_ident()?;
+        self.try_spaces();
+        if self.try_exact(Question).is_none() {
+            self.index = start;
+            return None;
+        }
+        self.try_spaces();
+        let Some(path) = self.next_token_map(Token::as_string) else {
+            self.index = start;
+            return None;
+        };
+        let path = path.map(Into::into);
+        if name.value.contains('?') {
+            (self.errors).push(name.span.clone().sp(ParseError::QuestionBindingName));
+        }
+        Some(Import { name, path })
+    }
     fn try_ident(&mut self) -> Option<Sp<Ident>> {
         let span = self.try_exact(Token::Ident)?;
         let s = span.as_str().into();
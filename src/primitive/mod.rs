@@ -28,6 +28,7 @@ use crate::{
     algorithm::{fork, loops},
     function::{Function, Signature},
     lex::AsciiToken,
+    mask,
     run::FunctionArg,
     sys::*,
     value::*,
@@ -186,6 +187,15 @@ impl Primitive {
             _ => return None,
         })
     }
+    /// Check if the primitive may produce different values each time it is run
+    pub(crate) fn is_impure(&self) -> bool {
+        use Primitive::*;
+        match self {
+            Rand | Tag | Spawn => true,
+            Sys(op) => *op != SysOp::Import,
+            _ => false,
+        }
+    }
     /// Try to parse a primitive from a name prefix
     pub fn from_format_name(name: &str) -> Option<Self> {
         if name.chars().any(char::is_uppercase) {
@@ -243,6 +253,9 @@ impl Primitive {
         })
     }
     pub(crate) fn run(&self, env: &mut Uiua) -> UiuaResult {
+        if self.is_impure() {
+            env.record_impurity();
+        }
         match self {
             Primitive::Eta => env.push(PI / 2.0),
             Primitive::Pi => env.push(PI),
@@ -261,12 +274,12 @@ impl Primitive {
             Primitive::Floor => env.monadic_env(Value::floor)?,
             Primitive::Ceil => env.monadic_env(Value::ceil)?,
             Primitive::Round => env.monadic_env(Value::round)?,
-            Primitive::Eq => env.dyadic_rr_env(Value::is_eq)?,
-            Primitive::Ne => env.dyadic_rr_env(Value::is_ne)?,
-            Primitive::Lt => env.dyadic_rr_env(Value::is_lt)?,
-            Primitive::Le => env.dyadic_rr_env(Value::is_le)?,
-            Primitive::Gt => env.dyadic_rr_env(Value::is_gt)?,
-            Primitive::Ge => env.dyadic_rr_env(Value::is_ge)?,
+            Primitive::Eq => mask::dyadic(env, Value::is_eq, Some(|a, b| !(a ^ b)), true)?,
+            Primitive::Ne => mask::dyadic(env, Value::is_ne, Some(|a, b| a ^ b), true)?,
+            Primitive::Lt => mask::dyadic(env, Value::is_lt, None, true)?,
+            Primitive::Le => mask::dyadic(env, Value::is_le, None, true)?,
+            Primitive::Gt => mask::dyadic(env, Value::is_gt, None, true)?,
+            Primitive::Ge => mask::dyadic(env, Value::is_ge, None, true)?,
             Primitive::Add => env.dyadic_rr_env(Value::add)?,
             Primitive::Sub => env.dyadic_rr_env(Value::sub)?,
             Primitive::Mul => env.dyadic_rr_env(Value::mul)?,
@@ -274,14 +287,14 @@ impl Primitive {
             Primitive::Mod => env.dyadic_rr_env(Value::modulus)?,
             Primitive::Pow => env.dyadic_rr_env(Value::pow)?,
             Primitive::Log => env.dyadic_rr_env(Value::log)?,
-            Primitive::Min => env.dyadic_rr_env(Value::min)?,
-            Primitive::Max => env.dyadic_rr_env(Value::max)?,
+            Primitive::Min => mask::dyadic(env, Value::min, Some(|a, b| a & b), false)?,
+            Primitive::Max => mask::dyadic(env, Value::max, Some(|a, b| a | b), false)?,
             Primitive::Atan => env.dyadic_rr_env(Value::atan2)?,
             Primitive::Match => env.dyadic_rr(|a, b| a == b)?,
             Primitive::Join => env.dyadic_oo_env(Value::join)?,
             Primitive::Transpose => env.monadic_mut(Value::transpose)?,
             Primitive::InvTranspose => env.monadic_mut(Value::inv_transpose)?,
-            Primitive::Keep => env.dyadic_ro_env(Value::keep)?,
+            Primitive::Keep => mask::keep(env)?,
             Primitive::Take => env.dyadic_oo_env(Value::take)?,
             Primitive::Constant => {
                 let val = env.pop(1)?;
@@ -384,38 +397,38 @@ impl Primitive {
                 env.recur(n)?;
             }
             Primitive::Dup => {
-                let x = env.pop(1)?;
+                let x = env.pop_packed(1)?;
                 env.push(x.clone());
                 env.push(x);
             }
             Primitive::Flip => {
-                let a = env.pop(1)?;
-                let b = env.pop(2)?;
+                let a = env.pop_packed(1)?;
+                let b = env.pop_packed(2)?;
                 env.push(a);
                 env.push(b);
             }
             Primitive::Over => {
-                let a = env.pop(1)?;
-                let b = env.pop(2)?;
+                let a = env.pop_packed(1)?;
+                let b = env.pop_packed(2)?;
                 env.push(b.clone());
                 env.push(a);
                 env.push(b);
             }
             Primitive::Pop => {
-                env.pop(1)?;
+                env.pop_packed(1)?;
             }
             Primitive::Roll => {
-                let a = env.pop(1)?;
-                let b = env.pop(2)?;
-                let c = env.pop(3)?;
+                let a = env.pop_packed(1)?;
+                let b = env.pop_packed(2)?;
+                let c = env.pop_packed(3)?;
                 env.push(a);
                 env.push(c);
                 env.push(b);
             }
             Primitive::Unroll => {
-                let a = env.pop(1)?;
-                let b = env.pop(2)?;
-                let c = env.pop(3)?;
+                let a = env.pop_packed(1)?;
+                let b = env.pop_packed(2)?;
+                let c = env.pop_packed(3)?;
                 env.push(b);
                 env.push(a);
                 env.push(c);
@@ -522,7 +535,7 @@ impl Primitive {
             Primitive::Type => {
                 let val = env.pop(1)?;
                 env.push(match val {
-                    Value::Num(_) | Value::Byte(_) => 0,
+                    Value::Num(_) | Value::Byte(_) | Value::Int(_) | Value::Mask(_) => 0,
                     Value::Char(_) => 1,
                     Value::Func(_) => 2,
                 });
@@ -73,6 +73,25 @@ u ← ↥<0.2∶>0.7.+×2 ×.∶⍘⊟xy
 c ← <∶√/+ⁿ2 xy
 ⍉⊂∶-¬u c1 +0.1 ∺↧rgb c0.95",
         ),
+        (
+            "PERVADE",
+            "\
+X ← ÷7 ⇡1000000
+Y ← ⇌X
+/+ ↥X Y
+/+ ×X Y
+/+ <X Y
+/+ =X Y",
+        ),
+        (
+            "PERVADE_BYTES",
+            "\
+A ← =0◿2 ⇡1000000
+B ← =0◿3 ⇡1000000
+/+ +A B
+/+ ↧A B
+/+ <A B",
+        ),
     ];
 
     const RUNS: usize = 20;
@@ -2,11 +2,13 @@ use std::{
     collections::{hash_map::DefaultHasher, HashMap, HashSet},
     fs,
     hash::{Hash, Hasher},
-    mem::take,
+    mem::{replace, take},
+    num::NonZeroUsize,
     panic::{catch_unwind, AssertUnwindSafe},
     path::{Path, PathBuf},
     str::FromStr,
     sync::Arc,
+    thread,
 };
 
 use instant::Duration;
@@ -14,9 +16,13 @@ use parking_lot::Mutex;
 
 use crate::{
     ast::*,
+    bytecode::{self, CompiledFile, CompiledItem},
     check::instrs_signature,
+    debug::{DebugFrame, DebugHook, DebugPause, DebugStep, Debugger, PauseReason},
+    example_ua,
     function::*,
     lex::{CodeSpan, Sp, Span},
+    optimize,
     parse::parse,
     primitive::{Primitive, CONSTANTS},
     value::Value,
@@ -44,10 +50,32 @@ pub struct Uiua {
     execution_limit: Option<f64>,
     /// The time at which execution started
     execution_start: f64,
+    /// A limit on the estimated memory used by live values in bytes
+    memory_limit: Option<usize>,
+    /// A limit on the number of values on the stack
+    stack_limit: Option<usize>,
+    /// A limit on the number of elements in arrays created by some primitives
+    array_limit: Option<usize>,
+    /// A limit on the depth of the call stack when recurring
+    recursion_limit: Option<usize>,
+    /// The debugger, if one is attached
+    debugger: Option<Arc<Mutex<Debugger>>>,
+    /// The results of checking code without running it
+    check: Option<CheckOutput>,
+    /// The signatures of functions in imported modules, used when checking code
+    imported_signatures: HashMap<Ident, Signature>,
     /// The paths of files currently being imported (used to detect import cycles)
     current_imports: Arc<Mutex<HashSet<PathBuf>>>,
-    /// The stacks of imported files
-    imports: Arc<Mutex<HashMap<PathBuf, Vec<Value>>>>,
+    /// Imported files
+    imports: Arc<Mutex<HashMap<PathBuf, Arc<Module>>>>,
+    /// The directory compiled files are cached in
+    cache_dir: Option<PathBuf>,
+    /// Recordings of the files being loaded, innermost last
+    recordings: Vec<Recording>,
+    /// Whether compiled code is optimized
+    optimize: bool,
+    /// The number of threads large array operations may be split across
+    thread_count: usize,
     /// The system backend
     pub(crate) backend: Arc<dyn SysBackend>,
 }
@@ -60,6 +88,10 @@ pub struct Scope {
     call: Vec<StackFrame>,
     /// Map local names to global indices
     names: HashMap<Ident, usize>,
+    /// Names that are hidden from files that import this one
+    private: HashSet<Ident>,
+    /// Imported modules, by name
+    modules: HashMap<Ident, Arc<Module>>,
     /// Whether this scope is local
     local: bool,
     /// The current fill values
@@ -82,12 +114,55 @@ impl Default for Scope {
                 spans: Vec::new(),
             }],
             names: HashMap::new(),
+            private: HashSet::new(),
+            modules: HashMap::new(),
             local: false,
             fills: Fills::default(),
         }
     }
 }
 
+/// An imported file
+pub(crate) struct Module {
+    /// The values the file left on the stack
+    stack: Vec<Value>,
+    /// Map public names to global indices
+    names: HashMap<Ident, usize>,
+    /// Names that are hidden from importers
+    private: HashSet<Ident>,
+    /// Modules the file imported, by name
+    modules: HashMap<Ident, Arc<Module>>,
+    /// A binding evaluated after something with side effects ran, if any
+    tainted: Option<Ident>,
+    /// Files the file imported, directly or indirectly, and the hashes of their sources
+    deps: Vec<(PathBuf, u64)>,
+}
+
+/// What was compiled and run while loading a file, so that it can be cached
+#[derive(Clone, Default)]
+struct Recording {
+    /// The items run in each scope being recorded, or `None` if the file was loaded from the cache
+    items: Option<Vec<Vec<CompiledItem>>>,
+    /// Files imported, directly or indirectly, and the hashes of their sources
+    deps: Vec<(PathBuf, u64)>,
+    /// Whether something with side effects has run
+    impure: bool,
+    /// A binding evaluated after something with side effects ran, if any
+    ///
+    /// The values of bindings are compiled into the code that uses them,
+    /// so code compiled after this binding may differ between runs.
+    tainted: Option<Ident>,
+}
+
+impl Recording {
+    fn into_compiled(self) -> Option<CompiledFile> {
+        Some(CompiledFile {
+            deps: self.deps,
+            items: self.items?.pop()?,
+        })
+    }
+}
+
 #[derive(Default, Clone)]
 struct Fills {
     nums: Vec<f64>,
@@ -113,6 +188,32 @@ impl Default for Uiua {
     }
 }
 
+/// The result of checking code with [`Uiua::check_str`]
+#[derive(Debug, Clone)]
+pub struct CheckOutput {
+    /// Parse errors, unknown identifiers, and signature mismatches
+    pub errors: Vec<UiuaError>,
+    /// The signature of each binding, by the span of its name
+    pub signatures: Vec<(CodeSpan, Signature)>,
+    /// The height of the stack after each line of code that isn't a binding
+    ///
+    /// Heights are only known up to the first line whose signature cannot be inferred.
+    pub stack_heights: Vec<(CodeSpan, usize)>,
+    /// The current stack height, if it is known
+    height: Option<usize>,
+}
+
+impl Default for CheckOutput {
+    fn default() -> Self {
+        CheckOutput {
+            errors: Vec::new(),
+            signatures: Vec::new(),
+            stack_heights: Vec::new(),
+            height: Some(0),
+        }
+    }
+}
+
 /// A mode that affects how non-binding lines are run
 ///
 /// Regardless of the mode, lines with a call to `import` will always be run
@@ -161,6 +262,17 @@ impl Uiua {
             backend: Arc::new(NativeSys),
             execution_limit: None,
             execution_start: 0.0,
+            memory_limit: None,
+            stack_limit: None,
+            array_limit: None,
+            recursion_limit: None,
+            debugger: None,
+            check: None,
+            imported_signatures: HashMap::new(),
+            cache_dir: None,
+            recordings: Vec::new(),
+            optimize: true,
+            thread_count: thread::available_parallelism().map_or(1, NonZeroUsize::get),
         }
     }
     /// Create a new Uiua runtime with a custom IO backend
@@ -181,6 +293,34 @@ impl Uiua {
         self.execution_limit = Some(limit.as_millis() as f64);
         self
     }
+    /// Limit the estimated number of bytes used by values on the stack and in bindings
+    ///
+    /// Memory is estimated from the element counts of arrays, so it does not account for
+    /// allocator overhead or data shared between arrays.
+    pub fn with_memory_limit(mut self, bytes: usize) -> Self {
+        self.memory_limit = Some(bytes);
+        self
+    }
+    /// Limit the number of values on the stack
+    pub fn with_stack_limit(mut self, values: usize) -> Self {
+        self.stack_limit = Some(values);
+        self
+    }
+    /// Limit the number of elements in arrays created by [`Primitive::Reshape`], [`Primitive::Range`], and [`Primitive::Table`]
+    pub fn with_array_limit(mut self, elements: usize) -> Self {
+        self.array_limit = Some(elements);
+        self
+    }
+    /// Limit the depth of the call stack when using [`Primitive::Recur`]
+    pub fn with_recursion_limit(mut self, depth: usize) -> Self {
+        self.recursion_limit = Some(depth);
+        self
+    }
+    /// Attach a debugger that can pause execution before instructions
+    pub fn with_debug_hook(mut self, hook: impl DebugHook + 'static) -> Self {
+        self.debugger = Some(Arc::new(Mutex::new(Debugger::new(hook))));
+        self
+    }
     /// Set the [`RunMode`]
     ///
     /// Default is [`RunMode::Normal`]
@@ -196,15 +336,100 @@ impl Uiua {
     pub fn load_file<P: AsRef<Path>>(&mut self, path: P) -> UiuaResult<&mut Self> {
         let path = path.as_ref();
         let input = fs::read_to_string(path).map_err(|e| UiuaError::Load(path.into(), e.into()))?;
-        self.load_impl(&input, Some(path))
+        self.load_impl(&input, Some(path), false)?;
+        Ok(self)
     }
     /// Load a Uiua file from a string
     pub fn load_str(&mut self, input: &str) -> UiuaResult<&mut Self> {
-        self.load_impl(input, None)
+        self.load_impl(input, None, false)?;
+        Ok(self)
     }
     /// Load a Uiua file from a string with a path for error reporting
     pub fn load_str_path<P: AsRef<Path>>(&mut self, input: &str, path: P) -> UiuaResult<&mut Self> {
-        self.load_impl(input, Some(path.as_ref()))
+        self.load_impl(input, Some(path.as_ref()), false)?;
+        Ok(self)
+    }
+    /// Cache compiled files in a directory
+    ///
+    /// Loading a file whose compiled form is cached skips lexing, parsing, and compiling it,
+    /// as long as its source and the sources of the files it imports have not changed.
+    ///
+    /// A file is not cached if it evaluates a binding after running something with side effects,
+    /// like IO, because the values of bindings are compiled into the code that uses them.
+    pub fn with_cache_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = Some(dir.into());
+        self
+    }
+    /// Set whether compiled code is optimized
+    ///
+    /// Optimization evaluates constant expressions ahead of time and fuses some common
+    /// patterns of primitives into faster kernels. It is on by default.
+    pub fn with_optimization(mut self, optimize: bool) -> Self {
+        self.optimize = optimize;
+        self
+    }
+    /// Set the number of threads that large array operations may be split across
+    ///
+    /// Results are the same for any number of threads. The default is the number of
+    /// threads the system can run in parallel.
+    pub fn with_thread_count(mut self, threads: usize) -> Self {
+        self.thread_count = threads.max(1);
+        self
+    }
+    /// Load a Uiua file from a path and get its compiled form
+    ///
+    /// The compiled form can be run later with [`Uiua::load_compiled`].
+    /// Fails if the file cannot be cached (see [`Uiua::with_cache_dir`]) or uses a native function.
+    pub fn compile_file<P: AsRef<Path>>(&mut self, path: P) -> UiuaResult<Vec<u8>> {
+        let path = path.as_ref();
+        let input = fs::read_to_string(path).map_err(|e| UiuaError::Load(path.into(), e.into()))?;
+        let recording = self.load_impl(&input, Some(path), true)?.unwrap();
+        if let Some(name) = &recording.tainted {
+            return Err(self.error(format!(
+                "{} cannot be compiled because `{name}` is bound \
+                after something with side effects has run",
+                path.display()
+            )));
+        }
+        let file = recording.into_compiled().unwrap();
+        let bytes = bytecode::encode(&file, &self.spans.lock());
+        bytes.map_err(|e| self.error(e))
+    }
+    /// Run a file compiled with [`Uiua::compile_file`]
+    ///
+    /// Files that the compiled file imports are loaded from their paths, even if they have changed.
+    pub fn load_compiled(&mut self, bytes: &[u8]) -> UiuaResult<&mut Self> {
+        self.execution_start = instant::now();
+        let file = bytecode::decode(bytes, &mut self.spans.lock());
+        let file = file.map_err(|e| self.error(e))?;
+        self.replay(file.items)?;
+        Ok(self)
+    }
+    /// Set the signatures of functions in imported modules
+    ///
+    /// Modules are not imported when code is checked with [`Uiua::check_str`].
+    /// Instead, bindings of the form `F ← use "Name" Module` get the signature given for `Name`.
+    pub fn with_imported_signatures(
+        mut self,
+        signatures: impl IntoIterator<Item = (Ident, Signature)>,
+    ) -> Self {
+        self.imported_signatures.extend(signatures);
+        self
+    }
+    /// Check a Uiua file from a string without running it
+    ///
+    /// Finds all parse errors, unknown identifiers, and bindings whose
+    /// declared signature does not match their inferred one.
+    /// Bindings are compiled into functions rather than evaluated, and
+    /// compilation continues after an error in any one item.
+    pub fn check_str(&mut self, input: &str) -> CheckOutput {
+        self.check_impl(input, None)
+    }
+    /// Check a Uiua file from a string with a path for error reporting
+    ///
+    /// See [`Uiua::check_str`]
+    pub fn check_str_path<P: AsRef<Path>>(&mut self, input: &str, path: P) -> CheckOutput {
+        self.check_impl(input, Some(path.as_ref()))
     }
     /// Run in a scoped context. Names defined in this context will be removed when the scope ends.
     ///
@@ -223,18 +448,38 @@ impl Uiua {
         f(self)?;
         let end_height = self.stack.len();
         self.scope = self.higher_scopes.pop().unwrap();
-        Ok(self.stack.split_off(start_height.min(end_height)))
+        let outputs = self.stack.split_off(start_height.min(end_height));
+        Ok(outputs.into_iter().map(Value::unpacked).collect())
     }
-    fn load_impl(&mut self, input: &str, path: Option<&Path>) -> UiuaResult<&mut Self> {
+    /// Load a file, returning its recording if it was recorded
+    ///
+    /// Files with paths are recorded if `record` is set, if they can be cached,
+    /// or if a file that imports them is being recorded.
+    fn load_impl(
+        &mut self,
+        input: &str,
+        path: Option<&Path>,
+        record: bool,
+    ) -> UiuaResult<Option<Recording>> {
         self.execution_start = instant::now();
-        let (items, errors) = parse(input, path);
-        if !errors.is_empty() {
-            return Err(errors.into());
-        }
         if let Some(path) = path {
             self.current_imports.lock().insert(path.into());
         }
-        let res = match catch_unwind(AssertUnwindSafe(|| self.items(items, false))) {
+        let res = match catch_unwind(AssertUnwindSafe(|| {
+            let record = (record || self.cache_dir.is_some() || !self.recordings.is_empty())
+                && self.check.is_none()
+                && self.debugger.is_none();
+            match path {
+                Some(path) if record => self.load_recorded(input, path).map(Some),
+                _ => {
+                    let (items, errors) = parse(input, path);
+                    if !errors.is_empty() {
+                        return Err(errors.into());
+                    }
+                    self.items(items, false).map(|_| None)
+                }
+            }
+        })) {
             Ok(res) => res,
             Err(_) => Err(self.error(format!(
                 "\
@@ -252,7 +497,157 @@ code:
         if let Some(path) = path {
             self.current_imports.lock().remove(path);
         }
-        res.map(|_| self)
+        res
+    }
+    /// Load a file while recording it, using and updating the cache if there is one
+    fn load_recorded(&mut self, input: &str, path: &Path) -> UiuaResult<Recording> {
+        let cache_path = (self.cache_dir.as_ref()).map(|dir| {
+            let mut hasher = DefaultHasher::new();
+            env!("CARGO_PKG_VERSION").hash(&mut hasher);
+            self.mode.hash(&mut hasher);
+            self.optimize.hash(&mut hasher);
+            path.hash(&mut hasher);
+            let path_hash = hasher.finish();
+            dir.join(format!("{path_hash:016x}-{:016x}.uac", source_hash(input)))
+        });
+        let cached = (cache_path.as_deref()).and_then(|cache_path| self.read_cache(cache_path));
+        let mut recording = Recording::default();
+        let res = if let Some(file) = cached {
+            recording.deps = file.deps;
+            self.recordings.push(recording);
+            self.replay(file.items)
+        } else {
+            let (items, errors) = parse(input, Some(path));
+            if !errors.is_empty() {
+                return Err(errors.into());
+            }
+            recording.items = Some(vec![Vec::new()]);
+            self.recordings.push(recording);
+            self.items(items, false)
+        };
+        let recording = self.recordings.pop().unwrap();
+        if let Some(importer) = self.recordings.last_mut() {
+            importer.impure |= recording.impure;
+            if importer.tainted.is_none() {
+                importer.tainted = recording.tainted.clone();
+            }
+            for dep in &recording.deps {
+                if !importer.deps.contains(dep) {
+                    importer.deps.push(dep.clone());
+                }
+            }
+        }
+        res?;
+        if let (Some(cache_path), Some(_), None) =
+            (cache_path, &recording.items, &recording.tainted)
+        {
+            self.write_cache(&cache_path, recording.clone());
+        }
+        Ok(recording)
+    }
+    /// Read a cached file, if it exists and none of the files it imports have changed
+    fn read_cache(&self, cache_path: &Path) -> Option<CompiledFile> {
+        let bytes = fs::read(cache_path).ok()?;
+        let mut spans = self.spans.lock();
+        let span_count = spans.len();
+        let file = bytecode::decode(&bytes, &mut spans).ok().filter(|file| {
+            file.deps.iter().all(|(path, hash)| {
+                (self.backend.file_read_all(&path.to_string_lossy()))
+                    .is_ok_and(|bytes| source_hash(&String::from_utf8_lossy(&bytes)) == *hash)
+            })
+        });
+        if file.is_none() {
+            spans.truncate(span_count);
+        }
+        file
+    }
+    /// Write a file to the cache, replacing older versions of it
+    ///
+    /// Failing to cache a file is not an error.
+    fn write_cache(&self, cache_path: &Path, recording: Recording) {
+        let Some(file) = recording.into_compiled() else {
+            return;
+        };
+        let Ok(bytes) = bytecode::encode(&file, &self.spans.lock()) else {
+            return;
+        };
+        let (Some(dir), Some(name)) = (cache_path.parent(), cache_path.file_name()) else {
+            return;
+        };
+        if fs::create_dir_all(dir).is_err() {
+            return;
+        }
+        let name = name.to_string_lossy();
+        let prefix = name.split('-').next().unwrap_or_default();
+        for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+            if entry.file_name().to_string_lossy().starts_with(prefix) {
+                _ = fs::remove_file(entry.path());
+            }
+        }
+        _ = fs::write(cache_path, bytes);
+    }
+    /// Run compiled items
+    fn replay(&mut self, items: Vec<CompiledItem>) -> UiuaResult {
+        for item in items {
+            match item {
+                CompiledItem::Run(instrs) => self.exec_global_instrs(instrs)?,
+                CompiledItem::Bind {
+                    name,
+                    private,
+                    instrs,
+                    signature,
+                    run,
+                } => {
+                    let val = self.binding_value(&name, instrs, signature, run)?;
+                    self.bind_value(name, private, val);
+                }
+                CompiledItem::Scoped(items) => {
+                    let scope_stack = self.in_scope(true, |env| env.replay(items))?;
+                    self.stack.extend(scope_stack);
+                }
+                CompiledItem::Import { name, path } => {
+                    let (path, input) = (self.read_import(None, &path.to_string_lossy()))
+                        .map_err(|e| self.error(e))?;
+                    let module = self.load_module(&input, &path)?;
+                    self.scope.modules.insert(name, module);
+                }
+            }
+        }
+        Ok(())
+    }
+    /// Record a compiled item if the current file is being recorded
+    fn record(&mut self, item: impl FnOnce() -> CompiledItem) {
+        if let Some(items) = (self.recordings.last_mut()).and_then(|rec| rec.items.as_mut()) {
+            items.last_mut().unwrap().push(item());
+        }
+    }
+    /// Note that something with side effects has run
+    pub(crate) fn record_impurity(&mut self) {
+        if let Some(recording) = self.recordings.last_mut() {
+            recording.impure = true;
+        }
+    }
+    /// Optimize compiled instructions, unless optimization is off or the code is not being run normally
+    fn optimize(&mut self, instrs: Vec<Instr>) -> Vec<Instr> {
+        if self.optimize && self.check.is_none() && self.debugger.is_none() {
+            optimize::optimize(instrs, self)
+        } else {
+            instrs
+        }
+    }
+    fn check_impl(&mut self, input: &str, path: Option<&Path>) -> CheckOutput {
+        let (items, parse_errors) = parse(input, path);
+        let mut check = CheckOutput::default();
+        if !parse_errors.is_empty() {
+            check.errors.push(parse_errors.into());
+        }
+        self.check = Some(check);
+        let res = self.items(items, false);
+        let mut check = self.check.take().unwrap();
+        if let Err(e) = res {
+            check.errors.push(e);
+        }
+        check
     }
     fn trace_error(&self, mut error: UiuaError, frame: StackFrame) -> UiuaError {
         let mut frames = Vec::new();
@@ -278,23 +673,144 @@ code:
             }
         }
     }
+    /// Read a file to import
+    ///
+    /// Relative paths are resolved relative to the directory of the importing file.
+    /// Returns the resolved path and the file's contents.
+    pub(crate) fn read_import(
+        &self,
+        importer: Option<&Path>,
+        path: &str,
+    ) -> Result<(PathBuf, String), String> {
+        let resolved = match importer.and_then(Path::parent) {
+            Some(dir) if Path::new(path).is_relative() => dir.join(path),
+            _ => PathBuf::from(path),
+        };
+        // Paths that don't name a file may name a dependency
+        #[cfg(feature = "package")]
+        let resolved = if self.backend.file_exists(&resolved.to_string_lossy()) {
+            resolved
+        } else {
+            crate::package::resolve_import(&*self.backend, importer, path)?.unwrap_or(resolved)
+        };
+        let bytes = (self.backend)
+            .file_read_all(&resolved.to_string_lossy())
+            .or_else(|e| {
+                if path == "example.ua" {
+                    Ok(example_ua(|ex| ex.as_bytes().to_vec()))
+                } else {
+                    Err(e)
+                }
+            })?;
+        let input = String::from_utf8(bytes).map_err(|e| format!("Failed to read file: {e}"))?;
+        Ok((resolved, input))
+    }
     pub(crate) fn import(&mut self, input: &str, path: &Path) -> UiuaResult {
+        let module = self.load_module(input, path)?;
+        self.stack.extend(module.stack.iter().cloned());
+        Ok(())
+    }
+    fn load_module(&mut self, input: &str, path: &Path) -> UiuaResult<Arc<Module>> {
         if self.current_imports.lock().contains(path) {
             return Err(self.error(format!(
                 "Cycle detected importing {}",
                 path.to_string_lossy()
             )));
         }
-        if !self.imports.lock().contains_key(path) {
-            let import = self.in_scope(false, |env| env.load_str_path(input, path).map(drop))?;
-            self.imports.lock().insert(path.into(), import);
+        let dep = (path.to_path_buf(), source_hash(input));
+        let cached = self.imports.lock().get(path).cloned();
+        if let Some(module) = cached {
+            if let Some(recording) = self.recordings.last_mut() {
+                if recording.tainted.is_none() {
+                    recording.tainted = module.tainted.clone();
+                }
+                for dep in module.deps.iter().chain([&dep]) {
+                    if !recording.deps.contains(dep) {
+                        recording.deps.push(dep.clone());
+                    }
+                }
+            }
+            return Ok(module);
+        }
+        if let Some(recording) = self.recordings.last_mut() {
+            if !recording.deps.contains(&dep) {
+                recording.deps.push(dep);
+            }
+        }
+        // Lines in other files don't affect the stack height of the file being checked
+        let check_state =
+            (self.check.as_ref()).map(|check| (check.height, check.stack_heights.len()));
+        let mut exports = None;
+        let res = self.in_scope(false, |env| {
+            let recording = env.load_impl(input, Some(path), false)?;
+            let scope = &mut env.scope;
+            exports = Some((
+                take(&mut scope.names),
+                take(&mut scope.private),
+                take(&mut scope.modules),
+                recording.unwrap_or_default(),
+            ));
+            Ok(())
+        });
+        if let (Some(check), Some((height, len))) = (&mut self.check, check_state) {
+            check.height = height;
+            check.stack_heights.truncate(len);
+        }
+        let stack = res?;
+        let (mut names, private, modules, recording) = exports.unwrap();
+        names.retain(|name, _| !private.contains(name));
+        let module = Arc::new(Module {
+            stack,
+            names,
+            private,
+            modules,
+            tainted: recording.tainted,
+            deps: recording.deps,
+        });
+        self.imports.lock().insert(path.into(), module.clone());
+        Ok(module)
+    }
+    /// Get the global index of a binding in a module
+    ///
+    /// `name` may itself be qualified by modules imported by the module.
+    fn module_binding(&self, module_name: &str, mut name: &str) -> Result<usize, String> {
+        let mut module = (self.scope.modules.get(module_name))
+            .or_else(|| {
+                self.higher_scopes
+                    .last()
+                    .filter(|_| self.scope.local)?
+                    .modules
+                    .get(module_name)
+            })
+            .ok_or_else(|| format!("Unknown module `{module_name}`"))?;
+        let mut qualified = module_name.to_string();
+        while let Some((sub, rest)) = name.split_once('?') {
+            module = (module.modules.get(sub))
+                .ok_or_else(|| format!("Module `{qualified}` has no module `{sub}`"))?;
+            qualified = format!("{qualified}?{sub}");
+            name = rest;
+        }
+        if let Some(idx) = module.names.get(name) {
+            Ok(*idx)
+        } else if module.private.contains(name) {
+            Err(format!("`{name}` is private to module `{qualified}`"))
+        } else {
+            Err(format!("Module `{qualified}` has no binding `{name}`"))
         }
-        self.stack.extend(self.imports.lock()[path].iter().cloned());
-        Ok(())
     }
     fn items(&mut self, items: Vec<Item>, in_test: bool) -> UiuaResult {
         for item in items {
-            self.item(item, in_test)?;
+            let function_depth = self.new_functions.len();
+            if let Err(e) = self.item(item, in_test) {
+                match &mut self.check {
+                    Some(check) => {
+                        check.errors.push(e);
+                        check.height = None;
+                        self.new_functions.truncate(function_depth);
+                    }
+                    None => return Err(e),
+                }
+            }
         }
         Ok(())
     }
@@ -306,9 +822,31 @@ code:
         }
         match item {
             Item::Scoped { items, test } => {
+                let recording = (self.recordings.last_mut()).and_then(|rec| rec.items.as_mut());
+                if let Some(items) = recording {
+                    items.push(Vec::new());
+                }
                 let scope_stack = self.in_scope(true, |env| env.items(items, test))?;
+                let recording = (self.recordings.last_mut()).and_then(|rec| rec.items.as_mut());
+                if let Some(items) = recording {
+                    let scoped = items.pop().unwrap();
+                    items.last_mut().unwrap().push(CompiledItem::Scoped(scoped));
+                }
                 self.stack.extend(scope_stack);
             }
+            Item::Words(words) if self.check.is_some() => {
+                let span = (words.first().zip(words.last()))
+                    .map(|(first, last)| first.span.clone().merge(last.span.clone()));
+                let instrs = self.compile_words(words, true)?;
+                let check = self.check.as_mut().unwrap();
+                check.height = match (check.height, instrs_signature(&instrs)) {
+                    (Some(height), Ok(sig)) => Some(height.saturating_sub(sig.args) + sig.outputs),
+                    _ => None,
+                };
+                if let (Some(span), Some(height), false) = (span, check.height, instrs.is_empty()) {
+                    check.stack_heights.push((span, height));
+                }
+            }
             Item::Words(words) => {
                 let can_run = match self.mode {
                     RunMode::Normal => !in_test,
@@ -317,6 +855,8 @@ code:
                 };
                 if can_run || words_have_import(&words) {
                     let instrs = self.compile_words(words, true)?;
+                    let instrs = self.optimize(instrs);
+                    self.record(|| CompiledItem::Run(instrs.clone()));
                     self.exec_global_instrs(instrs)?;
                 }
             }
@@ -325,10 +865,21 @@ code:
                     RunMode::Normal => !in_test,
                     RunMode::All | RunMode::Test => true,
                 };
-                if can_run || words_have_import(&binding.words) {
+                if can_run || words_have_import(&binding.words) || self.check.is_some() {
                     self.binding(binding)?;
                 }
             }
+            Item::Import(import) => {
+                let importer = import.path.span.path.clone();
+                let (path, input) = (self.read_import(importer.as_deref(), &import.path.value))
+                    .map_err(|e| UiuaError::Run(Span::Code(import.path.span.clone()).sp(e)))?;
+                let module = self.load_module(&input, &path)?;
+                self.record(|| CompiledItem::Import {
+                    name: import.name.value.clone(),
+                    path,
+                });
+                self.scope.modules.insert(import.name.value, module);
+            }
             Item::ExtraNewlines(_) => {}
         }
         Ok(())
@@ -341,6 +892,7 @@ code:
     }
     fn binding(&mut self, binding: Binding) -> UiuaResult {
         let instrs = self.compile_words(binding.words, true)?;
+        let instrs = self.optimize(instrs);
         let make_fn = |instrs: Vec<Instr>, sig: Signature| {
             let func = Function::new(
                 FunctionId::Named(binding.name.value.clone()),
@@ -350,51 +902,79 @@ code:
             );
             Value::from(func)
         };
-        let mut val = match instrs_signature(&instrs) {
+        let val = match instrs_signature(&instrs) {
             Ok(mut sig) => {
                 if let Some(declared_sig) = &binding.signature {
-                    if declared_sig.value.is_superset_of(sig) {
-                        sig = declared_sig.value;
-                    } else {
-                        return Err(UiuaError::Run(Span::Code(declared_sig.span.clone()).sp(
-                            format!(
+                    if !declared_sig.value.is_superset_of(sig) {
+                        let error =
+                            UiuaError::Run(Span::Code(declared_sig.span.clone()).sp(format!(
                                 "Function signature mismatch: \
                                  declared {} but inferred {}",
                                 declared_sig.value, sig
-                            ),
-                        )));
+                            )));
+                        // When checking, keep going with the declared signature
+                        match &mut self.check {
+                            Some(check) => check.errors.push(error),
+                            None => return Err(error),
+                        }
                     }
+                    sig = declared_sig.value;
                 }
 
-                if sig.args <= self.stack.len() {
-                    self.exec_global_instrs(instrs)?;
-                    if let Some(value) = self.stack.pop() {
-                        match value {
-                            Value::Func(fs) => match fs.into_scalar() {
-                                Ok(mut f) => {
-                                    Arc::make_mut(&mut f).id =
-                                        FunctionId::Named(binding.name.value.clone());
-                                    f.into()
-                                }
-                                Err(fs) => fs.into(),
-                            },
-                            val => val,
+                let used_sig = match instrs.as_slice() {
+                    [.., Instr::Push(name), Instr::Prim(Primitive::Use, _)] => (name
+                        .as_string(self, ""))
+                    .ok()
+                    .and_then(|name| self.imported_signatures.get(name.as_str()).copied()),
+                    _ => None,
+                };
+                if let Some(check) = &mut self.check {
+                    // Don't run anything when checking
+                    if let Some(height) = &mut check.height {
+                        if sig.args <= *height {
+                            *height = (*height - sig.args + sig.outputs).saturating_sub(1);
                         }
+                    }
+                    let pushed_fn = match instrs.as_slice() {
+                        [Instr::Push(val)] => match &**val {
+                            Value::Func(fs) => fs.as_scalar().cloned(),
+                            _ => None,
+                        },
+                        _ => None,
+                    };
+                    if let Some(used_sig) = used_sig {
+                        (check.signatures).push((binding.name.span.clone(), used_sig));
+                        make_fn(Vec::new(), used_sig)
+                    } else if let Some(mut f) = pushed_fn {
+                        Arc::make_mut(&mut f).id = FunctionId::Named(binding.name.value.clone());
+                        (check.signatures).push((binding.name.span.clone(), f.signature()));
+                        f.into()
                     } else {
-                        Function::new(
-                            FunctionId::Named(binding.name.value.clone()),
-                            Vec::new(),
-                            FunctionKind::Normal,
-                            sig,
-                        )
-                        .into()
+                        (check.signatures).push((binding.name.span.clone(), sig));
+                        make_fn(instrs, sig)
                     }
                 } else {
-                    make_fn(instrs, sig)
+                    let name = &binding.name.value;
+                    let run = sig.args <= self.stack.len();
+                    self.record(|| CompiledItem::Bind {
+                        name: name.clone(),
+                        private: binding.private,
+                        instrs: instrs.clone(),
+                        signature: sig,
+                        run,
+                    });
+                    self.binding_value(name, instrs, sig, run)?
                 }
             }
             Err(e) => {
                 if let Some(sig) = binding.signature {
+                    self.record(|| CompiledItem::Bind {
+                        name: binding.name.value.clone(),
+                        private: binding.private,
+                        instrs: instrs.clone(),
+                        signature: sig.value,
+                        run: false,
+                    });
                     make_fn(instrs, sig.value)
                 } else {
                     return Err(UiuaError::Run(
@@ -404,12 +984,64 @@ code:
                 }
             }
         };
+        self.bind_value(binding.name.value, binding.private, val);
+        Ok(())
+    }
+    /// Get the value of a binding from its instructions
+    ///
+    /// If `run` is set, the binding gets the value the instructions push.
+    /// Otherwise, the binding is a function.
+    fn binding_value(
+        &mut self,
+        name: &Ident,
+        instrs: Vec<Instr>,
+        sig: Signature,
+        run: bool,
+    ) -> UiuaResult<Value> {
+        let make_fn = |instrs: Vec<Instr>| {
+            Function::new(
+                FunctionId::Named(name.clone()),
+                instrs,
+                FunctionKind::Normal,
+                sig,
+            )
+            .into()
+        };
+        if !run {
+            return Ok(make_fn(instrs));
+        }
+        self.exec_global_instrs(instrs)?;
+        if let Some(recording) = self.recordings.last_mut() {
+            if recording.impure && recording.tainted.is_none() {
+                recording.tainted = Some(name.clone());
+            }
+        }
+        Ok(if let Some(value) = self.stack.pop() {
+            match value {
+                Value::Func(fs) => match fs.into_scalar() {
+                    Ok(mut f) => {
+                        Arc::make_mut(&mut f).id = FunctionId::Named(name.clone());
+                        f.into()
+                    }
+                    Err(fs) => fs.into(),
+                },
+                val => val.unpacked(),
+            }
+        } else {
+            make_fn(Vec::new())
+        })
+    }
+    fn bind_value(&mut self, name: Ident, private: bool, mut val: Value) {
         val.compress();
         let mut globals = self.globals.lock();
         let idx = globals.len();
         globals.push(val);
-        self.scope.names.insert(binding.name.value, idx);
-        Ok(())
+        if private {
+            self.scope.private.insert(name.clone());
+        } else {
+            self.scope.private.remove(&name);
+        }
+        self.scope.names.insert(name, idx);
     }
     fn compile_words(&mut self, words: Vec<Sp<Word>>, call: bool) -> UiuaResult<Vec<Instr>> {
         self.new_functions.push(Vec::new());
@@ -453,81 +1085,31 @@ code:
     }
     fn word(&mut self, word: Sp<Word>, call: bool) -> UiuaResult {
         match word.value {
-            Word::Number(_, n) => {
-                self.push_instr(Instr::push(n));
+            Word::Number(s, n) => {
+                // Integers that numbers cannot hold exactly are kept as integers
+                let int = s.replace(['`', '¯'], "-").parse::<i64>();
+                match int {
+                    Ok(i) if i.unsigned_abs() > 1 << f64::MANTISSA_DIGITS => {
+                        self.push_instr(Instr::push(i))
+                    }
+                    _ => self.push_instr(Instr::push(n)),
+                }
             }
             Word::Char(c) => self.push_instr(Instr::push(c)),
             Word::String(s) => self.push_instr(Instr::push(s)),
-            Word::FormatString(frags) => {
-                let signature = Signature::new(frags.len() - 1, 1);
-                let f = Function::new(
-                    FunctionId::Anonymous(word.span.clone()),
-                    Vec::new(),
-                    FunctionKind::Dynamic(DynamicFunctionKind {
-                        id: {
-                            let mut hasher = DefaultHasher::new();
-                            frags.hash(&mut hasher);
-                            hasher.finish()
-                        },
-                        f: Arc::new(move |env| {
-                            let mut formatted = String::new();
-                            for (i, frag) in frags.iter().enumerate() {
-                                if i > 0 {
-                                    let val = env.pop(format!("format argument {i}"))?;
-                                    formatted.push_str(&format!("{}", val));
-                                }
-                                formatted.push_str(frag);
-                            }
-                            env.push(formatted);
-                            Ok(())
-                        }),
-                    }),
-                    signature,
-                );
-                self.push_instr(Instr::push(f));
-                if call {
-                    let span = self.add_span(word.span);
-                    self.push_instr(Instr::Call(span));
-                }
-            }
+            Word::FormatString(frags) => self.format_string(frags, word.span, call),
             Word::MultilineString(lines) => {
-                let signature = Signature::new(lines.iter().map(|l| l.value.len() - 1).sum(), 1);
-                let f = Function::new(
-                    FunctionId::Anonymous(word.span.clone()),
-                    Vec::new(),
-                    FunctionKind::Dynamic(DynamicFunctionKind {
-                        id: {
-                            let mut hasher = DefaultHasher::new();
-                            lines.hash(&mut hasher);
-                            hasher.finish()
-                        },
-                        f: Arc::new(move |env| {
-                            let mut formatted = String::new();
-                            let mut i = 0;
-                            for (j, line) in lines.iter().enumerate() {
-                                if j > 0 {
-                                    formatted.push_str("\r\n");
-                                }
-                                for (k, frag) in line.value.iter().enumerate() {
-                                    if k > 0 {
-                                        let val = env.pop(format!("format argument {i}"))?;
-                                        formatted.push_str(&format!("{}", val));
-                                    }
-                                    formatted.push_str(frag);
-                                    i += 1;
-                                }
-                            }
-                            env.push(formatted);
-                            Ok(())
-                        }),
-                    }),
-                    signature,
-                );
-                self.push_instr(Instr::push(f));
-                if call {
-                    let span = self.add_span(word.span);
-                    self.push_instr(Instr::Call(span));
+                // Join the lines into one format string
+                let mut frags: Vec<String> = Vec::new();
+                for line in lines {
+                    let mut line = line.value.into_iter();
+                    if let Some(last) = frags.last_mut() {
+                        last.push_str("\r\n");
+                        last.extend(line.next());
+                    }
+                    frags.extend(line);
                 }
+                self.format_string(frags, word.span, call)
             }
             Word::Ident(ident) => self.ident(ident, word.span, call)?,
             Word::Strand(items) => {
@@ -607,8 +1189,26 @@ code:
         }
         Ok(())
     }
+    fn format_string(&mut self, frags: Vec<String>, span: CodeSpan, call: bool) {
+        let signature = Signature::new(frags.len() - 1, 1);
+        let f = Function::new(
+            FunctionId::Anonymous(span.clone()),
+            Vec::new(),
+            FunctionKind::Format(frags.into()),
+            signature,
+        );
+        self.push_instr(Instr::push(f));
+        if call {
+            let span = self.add_span(span);
+            self.push_instr(Instr::Call(span));
+        }
+    }
     fn ident(&mut self, ident: Ident, span: CodeSpan, call: bool) -> UiuaResult {
-        if let Some(idx) = self.scope.names.get(&ident).or_else(|| {
+        if let Some((module, name)) = ident.split_once('?') {
+            // Name is qualified by a module
+            let idx = (self.module_binding(module, name)).map_err(|e| span.clone().sp(e))?;
+            self.push_binding(idx, span, call);
+        } else if let Some(idx) = self.scope.names.get(&ident).or_else(|| {
             self.higher_scopes
                 .last()
                 .filter(|_| self.scope.local)?
@@ -616,13 +1216,7 @@ code:
                 .get(&ident)
         }) {
             // Name exists in scope
-            let value = self.globals.lock()[*idx].clone();
-            let should_call = matches!(&value, Value::Func(f) if f.shape.is_empty());
-            self.push_instr(Instr::push(value));
-            if should_call && call {
-                let span = self.add_span(span);
-                self.push_instr(Instr::Call(span));
-            }
+            self.push_binding(*idx, span, call);
         } else if let Some(prim) =
             Primitive::all().find(|p| p.names().is_some_and(|n| &*ident == n.text))
         {
@@ -634,6 +1228,15 @@ code:
         }
         Ok(())
     }
+    fn push_binding(&mut self, idx: usize, span: CodeSpan, call: bool) {
+        let value = self.globals.lock()[idx].clone();
+        let should_call = matches!(&value, Value::Func(f) if f.shape.is_empty());
+        self.push_instr(Instr::push(value));
+        if should_call && call {
+            let span = self.add_span(span);
+            self.push_instr(Instr::Call(span));
+        }
+    }
     fn func(&mut self, func: Func, span: CodeSpan) -> UiuaResult {
         let mut instrs = Vec::new();
         for line in func.lines {
@@ -677,6 +1280,13 @@ code:
             }
         }
 
+        // A function that folds to a single value would act like that value
+        let optimized = self.optimize(instrs.clone());
+        let instrs = match optimized.as_slice() {
+            [Instr::Push(_)] if !matches!(instrs.as_slice(), [Instr::Push(_)]) => instrs,
+            _ => optimized,
+        };
+
         let function = Function::new(func.id, instrs, FunctionKind::Normal, sig);
         self.push_instr(Instr::push(function));
         Ok(())
@@ -735,7 +1345,7 @@ code:
         }
         Ok(())
     }
-    fn exec_global_instrs(&mut self, instrs: Vec<Instr>) -> UiuaResult {
+    pub(crate) fn exec_global_instrs(&mut self, instrs: Vec<Instr>) -> UiuaResult {
         let func = Function::new(
             FunctionId::Main,
             instrs,
@@ -760,6 +1370,17 @@ code:
             };
             // println!("{:?}", self.stack);
             // println!("  {:?}", instr);
+            if let Some(debugger) = &self.debugger {
+                let span = match instr {
+                    Instr::Prim(_, span) | Instr::Call(span) | Instr::EndArray { span, .. } => {
+                        Some(*span)
+                    }
+                    Instr::Push(_) | Instr::BeginArray | Instr::Fused(_) => None,
+                };
+                if let Some(span) = span {
+                    self.debug_instr(debugger, span)?;
+                }
+            }
             let res = match instr {
                 Instr::Push(val) => {
                     self.stack.push(Value::clone(val));
@@ -772,7 +1393,7 @@ code:
                 &Instr::EndArray { span, constant } => (|| {
                     let start = self.scope.array.pop().unwrap();
                     self.push_span(span, None);
-                    let values = self.stack.drain(start..).rev();
+                    let values = self.stack.drain(start..).rev().map(Value::unpacked);
                     let values: Vec<Value> = if constant {
                         values
                             .map(Function::constant)
@@ -796,6 +1417,10 @@ code:
                 &Instr::Call(span) => self
                     .pop("called function")
                     .and_then(|f| self.call_with_span(f, span)),
+                Instr::Fused(fused) => {
+                    let fused = fused.clone();
+                    fused.run(self)
+                }
             };
             if let Err(mut err) = res {
                 // Trace errors
@@ -815,14 +1440,88 @@ code:
                         return Err(UiuaError::Timeout(self.span()));
                     }
                 }
+                self.check_memory_limits()?;
             }
         }
         Ok(())
     }
-    fn push_span(&mut self, span: usize, prim: Option<Primitive>) {
+    /// Let the debugger pause before an instruction with the given span
+    fn debug_instr(&self, debugger: &Mutex<Debugger>, span: usize) -> UiuaResult {
+        let Span::Code(span) = self.spans.lock()[span].clone() else {
+            return Ok(());
+        };
+        let mut debugger = debugger.lock();
+        let depth = self.scope.call.len();
+        let line = (span.path.clone(), span.start.line);
+        let new_line = debugger.line.as_ref() != Some(&line);
+        debugger.line = Some(line);
+        let reason = if let Some(step) = debugger.step {
+            if new_line && (debugger.hook).is_breakpoint(span.path.as_deref(), span.start.line) {
+                Some(PauseReason::Breakpoint)
+            } else {
+                match step {
+                    DebugStep::Into => Some(PauseReason::Step),
+                    DebugStep::Over if depth <= debugger.depth => Some(PauseReason::Step),
+                    DebugStep::Out if depth < debugger.depth => Some(PauseReason::Step),
+                    _ => None,
+                }
+            }
+        } else {
+            let step = debugger.hook.entry();
+            debugger.step = Some(step);
+            match step {
+                DebugStep::Continue => None,
+                DebugStep::Stop => {
+                    return Err(span.sp("Execution stopped by debugger".into()).into())
+                }
+                _ => Some(PauseReason::Entry),
+            }
+        };
+        let Some(reason) = reason else {
+            return Ok(());
+        };
+        let frames = {
+            let spans = self.spans.lock();
+            (self.scope.call.iter())
+                .map(|frame| DebugFrame {
+                    function: frame.function.id.clone(),
+                    span: spans[frame.call_span].clone(),
+                })
+                .collect()
+        };
+        let pause = DebugPause {
+            reason,
+            span: &span,
+            stack: &self.stack,
+            frames,
+        };
+        let step = debugger.hook.pause(&pause);
+        debugger.step = Some(step);
+        debugger.depth = depth;
+        if step == DebugStep::Stop {
+            return Err(span.sp("Execution stopped by debugger".into()).into());
+        }
+        Ok(())
+    }
+    /// Run instructions that were fused into a kernel, for values the kernel does not handle
+    pub(crate) fn run_unfused(&mut self, instrs: &[Instr]) -> UiuaResult {
+        for instr in instrs {
+            match instr {
+                Instr::Push(val) => self.stack.push(Value::clone(val)),
+                &Instr::Prim(prim, span) => {
+                    self.push_span(span, Some(prim));
+                    prim.run(self)?;
+                    self.pop_span();
+                }
+                instr => unreachable!("{instr} is never fused"),
+            }
+        }
+        Ok(())
+    }
+    pub(crate) fn push_span(&mut self, span: usize, prim: Option<Primitive>) {
         self.scope.call.last_mut().unwrap().spans.push((span, prim));
     }
-    fn pop_span(&mut self) {
+    pub(crate) fn pop_span(&mut self) {
         self.scope.call.last_mut().unwrap().spans.pop();
     }
     fn call_with_span(&mut self, mut f: Value, call_span: usize) -> UiuaResult {
@@ -834,6 +1533,25 @@ code:
                     let f = f.into_scalar().unwrap();
                     match &f.kind {
                         FunctionKind::Normal => {}
+                        FunctionKind::Format(frags) => {
+                            self.scope.call.push(StackFrame {
+                                function: f.clone(),
+                                call_span,
+                                spans: Vec::new(),
+                                pc: 0,
+                            });
+                            let mut formatted = String::new();
+                            for (i, frag) in frags.iter().enumerate() {
+                                if i > 0 {
+                                    let val = self.pop(format!("format argument {i}"))?;
+                                    formatted.push_str(&val.to_string());
+                                }
+                                formatted.push_str(frag);
+                            }
+                            self.push(formatted);
+                            self.scope.call.pop();
+                            break Ok(());
+                        }
                         FunctionKind::Dynamic(dfk) => {
                             self.scope.call.push(StackFrame {
                                 function: f.clone(),
@@ -884,9 +1602,38 @@ code:
                 self.scope.call.len()
             )));
         }
+        if let Some(limit) = self.recursion_limit {
+            if self.scope.call.len() >= limit {
+                return Err(UiuaError::RecursionLimit(self.span()));
+            }
+        }
         let f = self.scope.call[self.scope.call.len() - n].function.clone();
         self.call(f)
     }
+    fn check_memory_limits(&self) -> UiuaResult {
+        if let Some(limit) = self.stack_limit {
+            if self.stack.len() > limit {
+                return Err(UiuaError::StackLimit(self.span()));
+            }
+        }
+        if let Some(limit) = self.memory_limit {
+            let globals = self.globals.lock();
+            let size: usize = (self.stack.iter().chain(globals.iter()))
+                .map(Value::size_estimate)
+                .sum();
+            if size > limit {
+                return Err(UiuaError::MemoryLimit(self.span()));
+            }
+        }
+        Ok(())
+    }
+    /// Check that an array with the given number of elements would not exceed the array limit
+    pub(crate) fn check_array_limit(&self, elements: usize) -> UiuaResult {
+        match self.array_limit {
+            Some(limit) if elements > limit => Err(UiuaError::ArrayLimit(self.span())),
+            _ => Ok(()),
+        }
+    }
     pub fn call_catch_break(&mut self, f: Value) -> UiuaResult<bool> {
         match self.call(f) {
             Ok(_) => Ok(false),
@@ -932,7 +1679,13 @@ code:
         UiuaError::Run(self.span().clone().sp(message.to_string()))
     }
     /// Pop a value from the stack
+    ///
+    /// A [`Mask`](crate::mask::Mask) is unpacked into the byte array it stands for.
     pub fn pop(&mut self, arg: impl StackArg) -> UiuaResult<Value> {
+        self.pop_packed(arg).map(Value::unpacked)
+    }
+    /// Pop a value from the stack, leaving a [`Mask`](crate::mask::Mask) packed
+    pub(crate) fn pop_packed(&mut self, arg: impl StackArg) -> UiuaResult<Value> {
         let res = self.stack.pop().ok_or_else(|| {
             self.error(format!(
                 "Stack was empty when evaluating {}",
@@ -951,9 +1704,124 @@ code:
     /// Take the entire stack
     pub fn take_stack(&mut self) -> Vec<Value> {
         take(&mut self.stack)
+            .into_iter()
+            .map(Value::unpacked)
+            .collect()
+    }
+    /// Get the values bound to names in the current scope
+    pub fn bound_values(&self) -> HashMap<Ident, Value> {
+        let globals = self.globals.lock();
+        (self.scope.names.iter())
+            .map(|(name, idx)| (name.clone(), globals[*idx].clone()))
+            .collect()
+    }
+    /// Bind a Rust function to a name
+    ///
+    /// The function should pop its arguments from the stack and push its outputs.
+    /// Code that uses the name is checked against the given [`Signature`],
+    /// and an error is returned if a call does not match it.
+    pub fn bind_native(
+        &mut self,
+        name: impl Into<Ident>,
+        signature: Signature,
+        f: impl Fn(&mut Uiua) -> UiuaResult + Send + Sync + 'static,
+    ) {
+        let name = name.into();
+        let id = {
+            let mut hasher = DefaultHasher::new();
+            name.hash(&mut hasher);
+            hasher.finish()
+        };
+        let f = Function::new(
+            FunctionId::Named(name.clone()),
+            Vec::new(),
+            FunctionKind::Dynamic(DynamicFunctionKind {
+                id,
+                f: Arc::new(move |env| {
+                    let start_height = env.stack.len();
+                    f(env)?;
+                    let expected_height = (start_height + signature.outputs)
+                        .checked_sub(signature.args)
+                        .filter(|_| start_height >= signature.args);
+                    if expected_height != Some(env.stack.len()) {
+                        return Err(env.error(format!(
+                            "Native function was declared with signature {signature}, \
+                            but it changed the stack height from {start_height} to {}",
+                            env.stack.len()
+                        )));
+                    }
+                    Ok(())
+                }),
+            }),
+            signature,
+        );
+        let mut globals = self.globals.lock();
+        let idx = globals.len();
+        globals.push(f.into());
+        self.scope.names.insert(name, idx);
+    }
+    /// Get the value bound to a name in the current scope
+    pub fn bound_value(&self, name: &str) -> Option<Value> {
+        let idx = *self.scope.names.get(name)?;
+        Some(self.globals.lock()[idx].clone())
+    }
+    /// Get the function bound to a name in the current scope
+    ///
+    /// Returns `None` if the name is unbound or bound to a non-function value
+    pub fn bound_function(&self, name: &str) -> Option<Arc<Function>> {
+        match self.bound_value(name)? {
+            Value::Func(fs) => fs.into_scalar().ok(),
+            _ => None,
+        }
+    }
+    /// Call the value bound to a name with the given arguments
+    ///
+    /// Arguments are given in the order they would be written in Uiua code,
+    /// so the first argument ends up on top of the stack.
+    /// Outputs are returned in the same order, with the top of the stack first.
+    ///
+    /// The number of arguments must match the bound function's [`Signature`].
+    /// The rest of the stack is left untouched.
+    pub fn call_function<A: Into<Value>>(
+        &mut self,
+        name: &str,
+        args: impl IntoIterator<Item = A>,
+    ) -> UiuaResult<Vec<Value>> {
+        let f = (self.bound_value(name))
+            .ok_or_else(|| self.error(format!("No value is bound to `{name}`")))?;
+        let sig = f.signature();
+        let args: Vec<Value> = args.into_iter().map(Into::into).collect();
+        if args.len() != sig.args {
+            return Err(self.error(format!(
+                "`{name}` has signature {sig}, so it expects {} argument{}, but {} {} given",
+                sig.args,
+                if sig.args == 1 { "" } else { "s" },
+                args.len(),
+                if args.len() == 1 { "was" } else { "were" }
+            )));
+        }
+        self.execution_start = instant::now();
+        let call_height = self.scope.call.len();
+        let outer_stack = replace(&mut self.stack, args.into_iter().rev().collect());
+        let res = self.call(f);
+        let mut outputs = replace(&mut self.stack, outer_stack);
+        if res.is_err() {
+            self.scope.call.truncate(call_height);
+        }
+        res?;
+        if outputs.len() != sig.outputs {
+            return Err(self.error(format!(
+                "`{name}` has signature {sig}, but it returned {} value{}",
+                outputs.len(),
+                if outputs.len() == 1 { "" } else { "s" }
+            )));
+        }
+        outputs.reverse();
+        Ok(outputs.into_iter().map(Value::unpacked).collect())
     }
     pub fn clone_stack_top(&mut self, n: usize) -> Vec<Value> {
-        self.stack.iter().rev().take(n).rev().cloned().collect()
+        let values = self.stack.iter().rev().take(n).rev().cloned();
+        values.map(Value::unpacked).collect()
     }
     pub(crate) fn monadic_ref<V: Into<Value>>(&mut self, f: fn(&Value) -> V) -> UiuaResult {
         let value = self.pop(1)?;
@@ -1015,6 +1883,16 @@ code:
         self.push(f(&a, b, self)?);
         Ok(())
     }
+    /// The number of threads an operation may be split across
+    ///
+    /// Work is not split while checking or debugging, which must see every call.
+    pub(crate) fn thread_count(&self) -> usize {
+        if self.check.is_some() || self.debugger.is_some() {
+            1
+        } else {
+            self.thread_count
+        }
+    }
     pub(crate) fn stack_size(&self) -> usize {
         self.stack.len()
     }
@@ -1028,6 +1906,10 @@ code:
         let n = self.scope.fills.nums.last().copied()?;
         (n.fract() == 0.0 && (0.0..=255.0).contains(&n)).then_some(n as u8)
     }
+    pub(crate) fn int_fill(&self) -> Option<i64> {
+        let n = self.scope.fills.nums.last().copied()?;
+        (n.fract() == 0.0 && (i64::MIN as f64..i64::MAX as f64).contains(&n)).then_some(n as i64)
+    }
     pub(crate) fn char_fill(&self) -> Option<char> {
         self.scope.fills.chars.last().copied()
     }
@@ -1054,6 +1936,12 @@ code:
                     set = true;
                 }
             }
+            Value::Int(i) => {
+                if let Some(&i) = i.as_scalar() {
+                    self.scope.fills.nums.push(i as f64);
+                    set = true;
+                }
+            }
             Value::Char(c) => {
                 if let Some(&c) = c.as_scalar() {
                     self.scope.fills.chars.push(c);
@@ -1066,6 +1954,8 @@ code:
                     set = true;
                 }
             }
+            // Masks are never scalar
+            Value::Mask(_) => {}
         }
         if !set {
             return Err(self.error(format!(
@@ -1075,7 +1965,7 @@ code:
         }
         let res = in_ctx(self);
         match fill {
-            Value::Num(_) | Value::Byte(_) => {
+            Value::Num(_) | Value::Byte(_) | Value::Int(_) | Value::Mask(_) => {
                 self.scope.fills.nums.pop();
             }
             Value::Char(_) => {
@@ -1087,6 +1977,37 @@ code:
         }
         res
     }
+    /// Make a runtime for running code on another thread
+    ///
+    /// It shares globals and imports with this one, but starts with an empty stack and
+    /// does not split work across more threads.
+    pub(crate) fn fork(&self) -> Self {
+        Uiua {
+            new_functions: Vec::new(),
+            globals: self.globals.clone(),
+            spans: self.spans.clone(),
+            stack: Vec::new(),
+            scope: self.scope.clone(),
+            higher_scopes: self.higher_scopes.last().cloned().into_iter().collect(),
+            mode: self.mode,
+            current_imports: self.current_imports.clone(),
+            imports: self.imports.clone(),
+            backend: self.backend.clone(),
+            execution_limit: self.execution_limit,
+            execution_start: self.execution_start,
+            memory_limit: self.memory_limit,
+            stack_limit: self.stack_limit,
+            array_limit: self.array_limit,
+            recursion_limit: self.recursion_limit,
+            debugger: None,
+            check: None,
+            imported_signatures: HashMap::new(),
+            cache_dir: self.cache_dir.clone(),
+            recordings: Vec::new(),
+            optimize: self.optimize,
+            thread_count: 1,
+        }
+    }
     /// Spawn a thread
     pub(crate) fn spawn(
         &mut self,
@@ -1100,23 +2021,11 @@ code:
                 self.stack.len()
             )))?;
         }
-        let env = Uiua {
-            new_functions: Vec::new(),
-            globals: self.globals.clone(),
-            spans: self.spans.clone(),
-            stack: self
-                .stack
-                .drain(self.stack.len() - capture_count..)
-                .collect(),
-            scope: self.scope.clone(),
-            higher_scopes: self.higher_scopes.last().cloned().into_iter().collect(),
-            mode: self.mode,
-            current_imports: self.current_imports.clone(),
-            imports: self.imports.clone(),
-            backend: self.backend.clone(),
-            execution_limit: self.execution_limit,
-            execution_start: self.execution_start,
-        };
+        let mut env = self.fork();
+        env.stack = (self.stack)
+            .drain(self.stack.len() - capture_count..)
+            .collect();
+        env.thread_count = self.thread_count;
         self.backend
             .spawn(env, Box::new(f))
             .map(Value::from)
@@ -1158,6 +2067,13 @@ code:
     }
 }
 
+/// Hash the source of a file, to check if compiled code is out of date
+fn source_hash(input: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    input.hash(&mut hasher);
+    hasher.finish()
+}
+
 /// A trait for types that can be used as argument specifiers for [`Uiua::pop`] and [`Uiua::antipop`]
 ///
 /// If the stack is empty, the error message will be "Stack was empty when evaluating {arg_name}"
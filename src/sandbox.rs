@@ -0,0 +1,660 @@
+//! A system backend for running untrusted code
+
+use std::{
+    any::Any,
+    collections::{BTreeMap, HashMap, HashSet},
+    fs::{self, File, OpenOptions},
+    io::{self, Cursor, Read, Write},
+    path::{Component, Path, PathBuf},
+    time::Duration,
+};
+
+use parking_lot::Mutex;
+
+use crate::{value::Value, Handle, NativeSys, SysBackend, Uiua, UiuaError, UiuaResult};
+
+/// A [`SysBackend`] that keeps code away from the host system
+///
+/// By default:
+/// - Files live in an in-memory filesystem. Use [`SandboxSys::with_root`] to use a real directory instead.
+/// - Stdout and stderr are captured. Use [`SandboxSys::take_stdout`] and [`SandboxSys::take_stderr`] to get them.
+/// - Stdin is empty. Use [`SandboxSys::with_stdin`] to provide input.
+/// - Network access, running commands, and environment access are denied.
+///
+/// ```
+/// use uiua::{SandboxSys, Uiua};
+///
+/// let sandbox = SandboxSys::new().with_file("in.txt", "Hello");
+/// let mut uiua = Uiua::with_backend(sandbox);
+/// uiua.load_str(r#"&p &fras "in.txt""#).unwrap();
+/// let sandbox = uiua.downcast_backend::<SandboxSys>().unwrap();
+/// assert_eq!(sandbox.take_stdout(), "Hello\n");
+/// ```
+pub struct SandboxSys {
+    fs: Filesystem,
+    stdin: Mutex<Cursor<Vec<u8>>>,
+    stdout: Mutex<Vec<u8>>,
+    stderr: Mutex<Vec<u8>>,
+    open_files: Mutex<HashMap<Handle, OpenFile>>,
+    /// Handles created by delegating to [`NativeSys`]
+    native_handles: Mutex<HashSet<Handle>>,
+    allow_network: bool,
+    allow_processes: bool,
+    allow_env: bool,
+}
+
+enum Filesystem {
+    Memory(Mutex<BTreeMap<String, Vec<u8>>>),
+    Dir(PathBuf),
+}
+
+enum OpenFile {
+    Memory {
+        path: String,
+        contents: Cursor<Vec<u8>>,
+    },
+    Real(File),
+}
+
+impl Default for SandboxSys {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SandboxSys {
+    /// Create a sandbox with an empty in-memory filesystem and everything denied
+    pub fn new() -> Self {
+        SandboxSys {
+            fs: Filesystem::Memory(Mutex::new(BTreeMap::new())),
+            stdin: Mutex::new(Cursor::new(Vec::new())),
+            stdout: Mutex::new(Vec::new()),
+            stderr: Mutex::new(Vec::new()),
+            open_files: Mutex::new(HashMap::new()),
+            native_handles: Mutex::new(HashSet::new()),
+            allow_network: false,
+            allow_processes: false,
+            allow_env: false,
+        }
+    }
+    /// Use a real directory as the root of the filesystem
+    ///
+    /// Paths cannot refer to anything outside of the directory.
+    pub fn with_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.fs = Filesystem::Dir(root.into());
+        self
+    }
+    /// Add a file to the filesystem
+    pub fn with_file(self, path: &str, contents: impl Into<Vec<u8>>) -> Self {
+        if let Err(e) = self.file_write_all(path, &contents.into()) {
+            panic!("Failed to add {path} to sandbox: {e}")
+        }
+        self
+    }
+    /// Set the contents of stdin
+    pub fn with_stdin(self, stdin: impl Into<Vec<u8>>) -> Self {
+        *self.stdin.lock() = Cursor::new(stdin.into());
+        self
+    }
+    /// Allow TCP and UDP sockets
+    pub fn allow_network(mut self) -> Self {
+        self.allow_network = true;
+        self
+    }
+    /// Allow running commands
+    ///
+    /// The output of commands run with `&runi` is captured along with the rest of stdout and stderr.
+    pub fn allow_processes(mut self) -> Self {
+        self.allow_processes = true;
+        self
+    }
+    /// Allow reading environment variables and command line arguments
+    pub fn allow_env(mut self) -> Self {
+        self.allow_env = true;
+        self
+    }
+    /// Take everything written to stdout so far
+    pub fn take_stdout(&self) -> String {
+        String::from_utf8_lossy(&std::mem::take(&mut *self.stdout.lock())).into_owned()
+    }
+    /// Take everything written to stderr so far
+    pub fn take_stderr(&self) -> String {
+        String::from_utf8_lossy(&std::mem::take(&mut *self.stderr.lock())).into_owned()
+    }
+    /// Take the files in the in-memory filesystem
+    ///
+    /// Returns an empty map if the sandbox uses a real directory.
+    pub fn take_files(&self) -> BTreeMap<String, Vec<u8>> {
+        match &self.fs {
+            Filesystem::Memory(files) => std::mem::take(&mut *files.lock()),
+            Filesystem::Dir(_) => BTreeMap::new(),
+        }
+    }
+    fn check_network(&self) -> Result<(), String> {
+        if self.allow_network {
+            Ok(())
+        } else {
+            Err("Network access is not allowed in this sandbox".into())
+        }
+    }
+    fn check_processes(&self) -> Result<(), String> {
+        if self.allow_processes {
+            Ok(())
+        } else {
+            Err("Running commands is not allowed in this sandbox".into())
+        }
+    }
+    fn native_handle(&self, handle: Handle) -> Result<Handle, String> {
+        if self.native_handles.lock().contains(&handle) {
+            Ok(handle)
+        } else {
+            Err("Invalid handle".into())
+        }
+    }
+    fn add_native_handle(&self, handle: Handle) -> Handle {
+        self.native_handles.lock().insert(handle);
+        handle
+    }
+    /// Resolve a path to a real path inside the root directory
+    ///
+    /// Symlinks in the returned path are already resolved, so it stays inside the root
+    /// even if they change later. Only the parts of the path that do not exist yet are
+    /// left as they are.
+    fn real_path(root: &Path, path: &str) -> Result<PathBuf, String> {
+        let outside = || "Cannot access paths outside of the sandbox".to_string();
+        let root = root.canonicalize().map_err(|e| e.to_string())?;
+        let path = root.join(normalize(path)?);
+        // Find the nearest part of the path that exists
+        let mut existing = path.as_path();
+        let mut missing = Vec::new();
+        while existing.symlink_metadata().is_err() {
+            missing.extend(existing.file_name());
+            existing = existing.parent().ok_or_else(outside)?;
+        }
+        // Symlinks may point outside of the root, and a broken one cannot be checked
+        let canonical = (existing.canonicalize())
+            .map_err(|_| format!("Cannot follow the broken symlink {}", existing.display()))?;
+        if !canonical.starts_with(&root) {
+            return Err(outside());
+        }
+        Ok(missing
+            .into_iter()
+            .rev()
+            .fold(canonical, |path, part| path.join(part)))
+    }
+}
+
+/// Create or truncate a real file, refusing to follow a symlink put in its place
+fn create_real(path: &Path) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::custom_flags(&mut options, libc::O_NOFOLLOW);
+    options.open(path)
+}
+
+/// Normalize a path relative to the sandbox root
+///
+/// Returns an empty string for the root itself.
+fn normalize(path: &str) -> Result<String, String> {
+    let mut parts = Vec::new();
+    for component in Path::new(&path.replace('\\', "/")).components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().into_owned()),
+            Component::ParentDir => {
+                if parts.pop().is_none() {
+                    return Err("Cannot access paths outside of the sandbox".into());
+                }
+            }
+            Component::CurDir | Component::RootDir | Component::Prefix(_) => {}
+        }
+    }
+    Ok(parts.join("/"))
+}
+
+impl SysBackend for SandboxSys {
+    fn any(&self) -> &dyn Any {
+        self
+    }
+    fn print_str_stdout(&self, s: &str) -> Result<(), String> {
+        self.stdout.lock().extend_from_slice(s.as_bytes());
+        Ok(())
+    }
+    fn print_str_stderr(&self, s: &str) -> Result<(), String> {
+        self.stderr.lock().extend_from_slice(s.as_bytes());
+        Ok(())
+    }
+    fn print_str_trace(&self, s: &str) {
+        self.stderr.lock().extend_from_slice(s.as_bytes());
+    }
+    fn scan_line_stdin(&self) -> Result<String, String> {
+        let mut line = Vec::new();
+        let mut stdin = self.stdin.lock();
+        for byte in (&mut *stdin).bytes() {
+            match byte.map_err(|e| e.to_string())? {
+                b'\n' => break,
+                byte => line.push(byte),
+            }
+        }
+        if line.ends_with(b"\r") {
+            line.pop();
+        }
+        String::from_utf8(line).map_err(|e| e.to_string())
+    }
+    fn var(&self, name: &str) -> Option<String> {
+        if self.allow_env {
+            NativeSys.var(name)
+        } else {
+            None
+        }
+    }
+    fn args(&self) -> Vec<String> {
+        if self.allow_env {
+            NativeSys.args()
+        } else {
+            Vec::new()
+        }
+    }
+    fn file_exists(&self, path: &str) -> bool {
+        let Ok(path) = normalize(path) else {
+            return false;
+        };
+        match &self.fs {
+            Filesystem::Memory(files) => {
+                let files = files.lock();
+                let dir = format!("{path}/");
+                path.is_empty()
+                    || files.contains_key(&path)
+                    || files.keys().any(|file| file.starts_with(&dir))
+            }
+            Filesystem::Dir(root) => Self::real_path(root, &path).is_ok_and(|path| path.exists()),
+        }
+    }
+    fn list_dir(&self, path: &str) -> Result<Vec<String>, String> {
+        let path = normalize(path)?;
+        match &self.fs {
+            Filesystem::Memory(files) => {
+                let prefix = if path.is_empty() {
+                    String::new()
+                } else {
+                    format!("{path}/")
+                };
+                let mut entries: Vec<String> = (files.lock().keys())
+                    .filter_map(|file| file.strip_prefix(&prefix))
+                    .map(|rest| format!("{prefix}{}", rest.split('/').next().unwrap()))
+                    .collect();
+                entries.dedup();
+                if entries.is_empty() && !path.is_empty() {
+                    return Err(format!("{path} is not a directory"));
+                }
+                Ok(entries)
+            }
+            Filesystem::Dir(root) => {
+                let mut entries = Vec::new();
+                let dir = Self::real_path(root, &path)?;
+                for entry in fs::read_dir(dir).map_err(|e| e.to_string())? {
+                    let entry = entry.map_err(|e| e.to_string())?;
+                    let name = entry.file_name().to_string_lossy().into_owned();
+                    entries.push(if path.is_empty() {
+                        name
+                    } else {
+                        format!("{path}/{name}")
+                    });
+                }
+                Ok(entries)
+            }
+        }
+    }
+    fn is_file(&self, path: &str) -> Result<bool, String> {
+        let path = normalize(path)?;
+        match &self.fs {
+            Filesystem::Memory(files) => {
+                if files.lock().contains_key(&path) {
+                    Ok(true)
+                } else if self.file_exists(&path) {
+                    Ok(false)
+                } else {
+                    Err(format!("{path} does not exist"))
+                }
+            }
+            Filesystem::Dir(root) => fs::metadata(Self::real_path(root, &path)?)
+                .map(|m| m.is_file())
+                .map_err(|e| e.to_string()),
+        }
+    }
+    fn open_file(&self, path: &str) -> Result<Handle, String> {
+        let path = normalize(path)?;
+        let file = match &self.fs {
+            Filesystem::Memory(files) => OpenFile::Memory {
+                contents: Cursor::new(
+                    (files.lock().get(&path).cloned())
+                        .ok_or_else(|| format!("File not found: {path}"))?,
+                ),
+                path,
+            },
+            Filesystem::Dir(root) => OpenFile::Real(
+                File::open(Self::real_path(root, &path)?).map_err(|e| e.to_string())?,
+            ),
+        };
+        let handle = NativeSys::new_handle();
+        self.open_files.lock().insert(handle, file);
+        Ok(handle)
+    }
+    fn create_file(&self, path: &str) -> Result<Handle, String> {
+        let path = normalize(path)?;
+        if path.is_empty() {
+            return Err("Cannot create a file at the root of the sandbox".into());
+        }
+        let file = match &self.fs {
+            Filesystem::Memory(files) => {
+                files.lock().insert(path.clone(), Vec::new());
+                OpenFile::Memory {
+                    contents: Cursor::new(Vec::new()),
+                    path,
+                }
+            }
+            Filesystem::Dir(root) => OpenFile::Real(
+                create_real(&Self::real_path(root, &path)?).map_err(|e| e.to_string())?,
+            ),
+        };
+        let handle = NativeSys::new_handle();
+        self.open_files.lock().insert(handle, file);
+        Ok(handle)
+    }
+    fn read(&self, handle: Handle, count: usize) -> Result<Vec<u8>, String> {
+        let mut buf = Vec::new();
+        if handle == Handle::STDIN {
+            let mut stdin = self.stdin.lock();
+            (&mut *stdin)
+                .take(count as u64)
+                .read_to_end(&mut buf)
+                .map_err(|e| e.to_string())?;
+            return Ok(buf);
+        }
+        let mut open_files = self.open_files.lock();
+        match open_files.get_mut(&handle) {
+            Some(OpenFile::Memory { contents, .. }) => {
+                contents
+                    .take(count as u64)
+                    .read_to_end(&mut buf)
+                    .map_err(|e| e.to_string())?;
+            }
+            Some(OpenFile::Real(file)) => {
+                file.take(count as u64)
+                    .read_to_end(&mut buf)
+                    .map_err(|e| e.to_string())?;
+            }
+            None => {
+                drop(open_files);
+                return NativeSys.read(self.native_handle(handle)?, count);
+            }
+        }
+        Ok(buf)
+    }
+    fn write(&self, handle: Handle, contents: &[u8]) -> Result<(), String> {
+        let mut open_files = self.open_files.lock();
+        match open_files.get_mut(&handle) {
+            Some(OpenFile::Memory { path, contents: c }) => {
+                c.write_all(contents).map_err(|e| e.to_string())?;
+                if let Filesystem::Memory(files) = &self.fs {
+                    files.lock().insert(path.clone(), c.get_ref().clone());
+                }
+                Ok(())
+            }
+            Some(OpenFile::Real(file)) => file.write_all(contents).map_err(|e| e.to_string()),
+            None => {
+                drop(open_files);
+                NativeSys.write(self.native_handle(handle)?, contents)
+            }
+        }
+    }
+    fn file_read_all(&self, path: &str) -> Result<Vec<u8>, String> {
+        let path = normalize(path)?;
+        match &self.fs {
+            Filesystem::Memory(files) => {
+                (files.lock().get(&path).cloned()).ok_or_else(|| format!("File not found: {path}"))
+            }
+            Filesystem::Dir(root) => {
+                fs::read(Self::real_path(root, &path)?).map_err(|e| e.to_string())
+            }
+        }
+    }
+    fn file_write_all(&self, path: &str, contents: &[u8]) -> Result<(), String> {
+        let path = normalize(path)?;
+        if path.is_empty() {
+            return Err("Cannot create a file at the root of the sandbox".into());
+        }
+        match &self.fs {
+            Filesystem::Memory(files) => {
+                files.lock().insert(path, contents.to_vec());
+                Ok(())
+            }
+            Filesystem::Dir(root) => (create_real(&Self::real_path(root, &path)?))
+                .and_then(|mut file| file.write_all(contents))
+                .map_err(|e| e.to_string()),
+        }
+    }
+    fn sleep(&self, seconds: f64) -> Result<(), String> {
+        NativeSys.sleep(seconds)
+    }
+    fn tcp_listen(&self, addr: &str) -> Result<Handle, String> {
+        self.check_network()?;
+        NativeSys
+            .tcp_listen(addr)
+            .map(|h| self.add_native_handle(h))
+    }
+    fn tcp_accept(&self, handle: Handle) -> Result<Handle, String> {
+        let handle = self.native_handle(handle)?;
+        NativeSys
+            .tcp_accept(handle)
+            .map(|h| self.add_native_handle(h))
+    }
+    fn tcp_connect(&self, addr: &str) -> Result<Handle, String> {
+        self.check_network()?;
+        NativeSys
+            .tcp_connect(addr)
+            .map(|h| self.add_native_handle(h))
+    }
+    fn tcp_addr(&self, handle: Handle) -> Result<String, String> {
+        NativeSys.tcp_addr(self.native_handle(handle)?)
+    }
+    fn tcp_set_non_blocking(&self, handle: Handle, non_blocking: bool) -> Result<(), String> {
+        NativeSys.tcp_set_non_blocking(self.native_handle(handle)?, non_blocking)
+    }
+    fn tcp_set_read_timeout(
+        &self,
+        handle: Handle,
+        timeout: Option<Duration>,
+    ) -> Result<(), String> {
+        NativeSys.tcp_set_read_timeout(self.native_handle(handle)?, timeout)
+    }
+    fn tcp_set_write_timeout(
+        &self,
+        handle: Handle,
+        timeout: Option<Duration>,
+    ) -> Result<(), String> {
+        NativeSys.tcp_set_write_timeout(self.native_handle(handle)?, timeout)
+    }
+    fn udp_bind(&self, addr: &str) -> Result<Handle, String> {
+        self.check_network()?;
+        NativeSys.udp_bind(addr).map(|h| self.add_native_handle(h))
+    }
+    fn udp_send(&self, handle: Handle, contents: &[u8], addr: &str) -> Result<(), String> {
+        NativeSys.udp_send(self.native_handle(handle)?, contents, addr)
+    }
+    fn udp_receive(&self, handle: Handle) -> Result<(Vec<u8>, String), String> {
+        NativeSys.udp_receive(self.native_handle(handle)?)
+    }
+    fn udp_addr(&self, handle: Handle) -> Result<String, String> {
+        NativeSys.udp_addr(self.native_handle(handle)?)
+    }
+    fn poll(
+        &self,
+        handles: &[Handle],
+        timeout: Option<Duration>,
+    ) -> Result<Vec<[bool; 2]>, String> {
+        let open_files = self.open_files.lock();
+        let mut native = Vec::new();
+        for &handle in handles {
+            if !open_files.contains_key(&handle) && handle != Handle::STDIN {
+                native.push(self.native_handle(handle)?);
+            }
+        }
+        // Files and stdin are always ready, so there is no need to wait if there are any
+        let timeout = if native.len() < handles.len() {
+            Some(Duration::ZERO)
+        } else {
+            timeout
+        };
+        drop(open_files);
+        let mut native_ready = if native.is_empty() {
+            Vec::new()
+        } else {
+            NativeSys.poll(&native, timeout)?
+        }
+        .into_iter();
+        Ok((handles.iter())
+            .map(|handle| {
+                if native.contains(handle) {
+                    native_ready.next().unwrap()
+                } else {
+                    [true, true]
+                }
+            })
+            .collect())
+    }
+    fn close(&self, handle: Handle) -> Result<(), String> {
+        if self.open_files.lock().remove(&handle).is_some() {
+            return Ok(());
+        }
+        NativeSys.close(self.native_handle(handle)?)?;
+        self.native_handles.lock().remove(&handle);
+        Ok(())
+    }
+    fn spawn(
+        &self,
+        env: Uiua,
+        f: Box<dyn FnOnce(&mut Uiua) -> UiuaResult + Send>,
+    ) -> Result<Handle, String> {
+        NativeSys.spawn(env, f).map(|h| self.add_native_handle(h))
+    }
+    fn wait(&self, handle: Handle) -> Result<Vec<Value>, Result<UiuaError, String>> {
+        let handle = self.native_handle(handle).map_err(Err)?;
+        self.native_handles.lock().remove(&handle);
+        NativeSys.wait(handle)
+    }
+    fn run_command_inherit(&self, command: &str, args: &[&str]) -> Result<(), String> {
+        self.check_processes()?;
+        let (stdout, stderr) = NativeSys.run_command_capture(command, args)?;
+        self.print_str_stdout(&stdout)?;
+        self.print_str_stderr(&stderr)
+    }
+    fn run_command_capture(
+        &self,
+        command: &str,
+        args: &[&str],
+    ) -> Result<(String, String), String> {
+        self.check_processes()?;
+        NativeSys.run_command_capture(command, args)
+    }
+    fn run_command_stream(&self, command: &str, args: &[&str]) -> Result<[Handle; 4], String> {
+        self.check_processes()?;
+        let handles = NativeSys.run_command_stream(command, args)?;
+        Ok(handles.map(|h| self.add_native_handle(h)))
+    }
+    fn run_command_wait(&self, handle: Handle) -> Result<i32, String> {
+        let code = NativeSys.run_command_wait(self.native_handle(handle)?)?;
+        self.native_handles.lock().remove(&handle);
+        Ok(code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(sandbox: SandboxSys, code: &str) -> (UiuaResult, Uiua) {
+        let mut uiua = Uiua::with_backend(sandbox);
+        let res = uiua.load_str(code).map(drop);
+        (res, uiua)
+    }
+
+    #[test]
+    fn memory_fs() {
+        let sandbox = SandboxSys::new()
+            .with_file("dir/a.txt", "a")
+            .with_stdin("line\n");
+        let (res, uiua) = run(
+            sandbox,
+            r#"
+&fwa "dir/b.txt" "b"
+&p &fras "dir/b.txt"
+&p ⧻&fld "dir"
+&p &fif "dir"
+&p &fe "dir/../dir/a.txt"
+F ← &fc "c.txt"
+&w "hello" F
+&cl F
+&p &rs 5 &fo "c.txt"
+&p &sc
+&w "oops" 2
+"#,
+        );
+        res.unwrap();
+        let sandbox = uiua.downcast_backend::<SandboxSys>().unwrap();
+        assert_eq!(sandbox.take_stdout(), "b\n2\n0\n1\nhello\nline\n");
+        assert_eq!(sandbox.take_stderr(), "oops");
+        assert_eq!(sandbox.list_dir("dir").unwrap(), ["dir/a.txt", "dir/b.txt"]);
+        assert_eq!(sandbox.take_files()["c.txt"], b"hello");
+    }
+
+    #[test]
+    fn denied() {
+        for code in [
+            r#"&fras "../secret""#,
+            r#"&tcpl "127.0.0.1:0""#,
+            r#"&udpb "127.0.0.1:0""#,
+            r#"&runc "ls""#,
+            r#"&rs 1 1000"#,
+        ] {
+            let (res, _) = run(SandboxSys::new(), code);
+            assert!(res.is_err(), "{code} should fail");
+        }
+        let (res, uiua) = run(SandboxSys::new(), r#"&p &var "PATH""#);
+        res.unwrap();
+        let sandbox = uiua.downcast_backend::<SandboxSys>().unwrap();
+        assert_eq!(sandbox.take_stdout(), "\n");
+    }
+
+    #[test]
+    fn root_dir() {
+        let root = std::env::temp_dir().join(format!("uiua_sandbox_test_{}", std::process::id()));
+        fs::create_dir_all(root.join("sub")).unwrap();
+        let sandbox = SandboxSys::new().with_root(&root);
+        let (res, uiua) = run(
+            sandbox,
+            r#"
+&fwa "sub/x.txt" "x"
+&p &fras "sub/x.txt"
+"#,
+        );
+        res.unwrap();
+        let sandbox = uiua.downcast_backend::<SandboxSys>().unwrap();
+        assert_eq!(sandbox.take_stdout(), "x\n");
+        assert_eq!(sandbox.list_dir("sub").unwrap(), ["sub/x.txt"]);
+        assert_eq!(fs::read(root.join("sub/x.txt")).unwrap(), b"x");
+        assert!(sandbox.file_read_all("../x").is_err());
+        // Symlinks out of the root are refused, even broken ones that writing would create
+        #[cfg(unix)]
+        {
+            let outside = root.with_extension("outside");
+            std::os::unix::fs::symlink(&outside, root.join("sub/link")).unwrap();
+            assert!(sandbox.file_write_all("sub/link", b"x").is_err());
+            assert!(sandbox.file_write_all("sub/link/y.txt", b"x").is_err());
+            assert!(!outside.exists());
+            std::os::unix::fs::symlink(std::env::temp_dir(), root.join("tmp")).unwrap();
+            assert!(sandbox.file_read_all("tmp/x").is_err());
+        }
+        _ = fs::remove_dir_all(&root);
+    }
+}
@@ -4,7 +4,7 @@ use std::{
     fs::{self, File},
     io::{stderr, stdin, stdout, BufRead, Cursor, Read, Write},
     net::*,
-    process::Command,
+    process::{Child, ChildStderr, ChildStdin, ChildStdout, Command, Stdio},
     sync::{
         atomic::{self, AtomicU64},
         Arc, OnceLock,
@@ -23,8 +23,8 @@ use parking_lot::Mutex;
 use tinyvec::tiny_vec;
 
 use crate::{
-    array::Array, cowslice::CowSlice, function::Function, grid_fmt::GridFmt, primitive::PrimDoc,
-    value::Value, Uiua, UiuaError, UiuaResult,
+    array::Array, cowslice::CowSlice, function::Function, grid_fmt::GridFmt, lex::Span,
+    primitive::PrimDoc, value::Value, FfiArg, FfiSpec, FfiType, Uiua, UiuaError, UiuaResult,
 };
 
 pub fn example_ua<T>(f: impl FnOnce(&mut String) -> T) -> T {
@@ -130,6 +130,19 @@ sys_op! {
     ///
     /// Expects either a string, a [rank]`2` character array, or a [rank]`1` array of [constant] strings.
     (1(2), RunCapture, "&runc", "run command capture"),
+    /// Run a command without waiting for it to finish
+    ///
+    /// Returns a list of 4 handles.
+    /// The first 3 are the process's stdin, stdout, and stderr, which can be used with [&w], [&rs], [&rb], [&ru], and [&cl].
+    /// Reading from stdout or stderr with [&rs] or [&rb] waits for some output, then returns at most the requested number of bytes of what is available.
+    /// The last is the process itself, which can be passed to [&runw] to wait for it to finish, or to [&cl] to kill it.
+    ///
+    /// Expects either a string, a [rank]`2` character array, or a [rank]`1` array of [constant] strings.
+    (1, RunStream, "&runs", "run command stream"),
+    /// Wait for a process started with [&runs] to finish and get its exit code
+    ///
+    /// The process's stdin is closed first if it is still open.
+    (1, RunWait, "&runw", "run command wait"),
     /// Change the current directory
     (1(0), ChangeDirectory, "&cd", "change directory"),
     /// Sleep for n seconds
@@ -154,9 +167,22 @@ sys_op! {
     ///   : square ← use "Square"
     ///   : square double 5
     (1, Import, "&i", "import"),
+    /// Check which of a list of handles are ready for IO
+    ///
+    /// The first argument is a timeout in seconds, and the second is a list of handles.
+    /// Waits until at least one handle is ready or the timeout expires.
+    /// An infinite or very large timeout waits forever, and a timeout of `0` returns immediately.
+    ///
+    /// Returns a table with a row for each handle.
+    /// The first column is whether the handle is ready to be read from, or accepted from for tcp listeners.
+    /// The second column is whether the handle is ready to be written to.
+    ///
+    /// Works with files, tcp listeners, tcp sockets, udp sockets, the standard IO handles, and the pipes of processes started with [&runs].
+    (2, Poll, "&poll", "poll"),
     /// Close a stream by its handle
     ///
-    /// This will close files, tcp listeners, and tcp sockets.
+    /// This will close files, tcp listeners, tcp sockets, udp sockets, and process pipes.
+    /// Closing a process handle from [&runs] kills the process.
     (1(0), Close, "&cl", "close handle"),
     /// Open a file and return a handle to it
     (1, FOpen, "&fo", "file - open"),
@@ -264,8 +290,51 @@ sys_op! {
     (2(0), TcpSetReadTimeout, "&tcpsrt", "tcp - set read timeout"),
     /// Set the write timeout of a TCP socket in seconds
     (2(0), TcpSetWriteTimeout, "&tcpswt", "tcp - set write timeout"),
-    /// Get the connection address of a TCP socket
+    /// Get the address of a TCP socket or listener
+    ///
+    /// For a socket, this is the address it is connected to.
+    /// For a listener, this is the address it is bound to, which is useful for finding the port of a listener bound to port 0.
     (1, TcpAddr, "&tcpaddr", "tcp - address"),
+    /// Create a UDP socket and bind it to an address
+    (1, UdpBind, "&udpb", "udp - bind"),
+    /// Send bytes from a UDP socket to an address
+    ///
+    /// The first argument is the data, the second is the address, and the third is the socket handle.
+    /// Strings will be sent as UTF-8.
+    (3(0), UdpSend, "&udps", "udp - send"),
+    /// Receive a datagram with a UDP socket
+    ///
+    /// This waits until a datagram arrives.
+    /// The received bytes are pushed, followed by the address of the sender.
+    (1(2), UdpReceive, "&udpr", "udp - receive"),
+    /// Get the local address of a UDP socket
+    ///
+    /// This is useful for finding the port of a socket bound to port 0.
+    (1, UdpAddr, "&udpaddr", "udp - address"),
+    /// Call a function in a C shared library
+    ///
+    /// The first argument is a spec list of strings.
+    /// It contains the path to the library, the return type, the name of the function, and then the argument types.
+    /// The second argument is a list of the arguments to pass.
+    /// The list may be boxed with [constant] if the arguments have different types or shapes.
+    ///
+    /// The available types are:
+    /// - `int` - a C `int`
+    /// - `long` - a C `long`
+    /// - `double` - a C `double`
+    /// - `ptr` - a pointer, represented as a number
+    /// - `str` - a null-terminated string
+    /// - `bytes` - a pointer to a byte buffer that the function may modify (argument only)
+    /// - `void` - no value (return only)
+    ///
+    /// If any of the arguments are `bytes`, the result is a list of [constant]s.
+    /// The first is the return value, and the rest are the contents of each buffer after the call.
+    /// Otherwise, the result is just the return value.
+    ///
+    /// A `void` function returns an empty list.
+    ///
+    /// Calling a function with the wrong signature is undefined behavior.
+    (2, Ffi, "&ffi", "foreign function interface"),
 }
 
 /// A handle to an IO stream
@@ -277,9 +346,9 @@ sys_op! {
 pub struct Handle(pub u64);
 
 impl Handle {
-    const STDIN: Self = Self(0);
-    const STDOUT: Self = Self(1);
-    const STDERR: Self = Self(2);
+    pub(crate) const STDIN: Self = Self(0);
+    pub(crate) const STDOUT: Self = Self(1);
+    pub(crate) const STDERR: Self = Self(2);
     pub const FIRST_UNRESERVED: Self = Self(3);
 }
 
@@ -415,6 +484,26 @@ pub trait SysBackend: Any + Send + Sync + 'static {
     ) -> Result<(), String> {
         Err("TCP sockets are not supported in this environment".into())
     }
+    fn udp_bind(&self, addr: &str) -> Result<Handle, String> {
+        Err("UDP sockets are not supported in this environment".into())
+    }
+    fn udp_send(&self, handle: Handle, contents: &[u8], addr: &str) -> Result<(), String> {
+        Err("UDP sockets are not supported in this environment".into())
+    }
+    fn udp_receive(&self, handle: Handle) -> Result<(Vec<u8>, String), String> {
+        Err("UDP sockets are not supported in this environment".into())
+    }
+    fn udp_addr(&self, handle: Handle) -> Result<String, String> {
+        Err("UDP sockets are not supported in this environment".into())
+    }
+    /// Check which handles are ready to be read from and written to
+    fn poll(
+        &self,
+        handles: &[Handle],
+        timeout: Option<Duration>,
+    ) -> Result<Vec<[bool; 2]>, String> {
+        Err("Polling is not supported in this environment".into())
+    }
     fn close(&self, handle: Handle) -> Result<(), String> {
         Ok(())
     }
@@ -440,9 +529,25 @@ pub trait SysBackend: Any + Send + Sync + 'static {
     ) -> Result<(String, String), String> {
         Err("Running commands is not supported in this environment".into())
     }
+    /// Run a command without waiting for it to finish
+    ///
+    /// Returns handles to the process's stdin, stdout, stderr, and the process itself
+    fn run_command_stream(&self, command: &str, args: &[&str]) -> Result<[Handle; 4], String> {
+        Err("Running commands is not supported in this environment".into())
+    }
+    /// Wait for a process started with `run_command_stream` and get its exit code
+    fn run_command_wait(&self, handle: Handle) -> Result<i32, String> {
+        Err("Running commands is not supported in this environment".into())
+    }
     fn change_directory(&self, path: &str) -> Result<(), String> {
         Err("Changing directories is not supported in this environment".into())
     }
+    /// Call a foreign function
+    ///
+    /// Returns the function's return value and the contents of any `bytes` arguments after the call.
+    fn ffi(&self, spec: &FfiSpec, args: Vec<FfiArg>) -> Result<(Value, Vec<Vec<u8>>), String> {
+        Err("FFI is not supported in this environment".into())
+    }
 }
 
 #[derive(Default)]
@@ -455,7 +560,14 @@ struct GlobalNativeSys {
     files: DashMap<Handle, Buffered<File>>,
     tcp_listeners: DashMap<Handle, TcpListener>,
     tcp_sockets: DashMap<Handle, Buffered<TcpStream>>,
+    udp_sockets: DashMap<Handle, UdpSocket>,
     threads: DashMap<Handle, JoinHandle<UiuaResult<Vec<Value>>>>,
+    processes: DashMap<Handle, ChildProcess>,
+    child_stdins: DashMap<Handle, ChildStdin>,
+    child_stdouts: DashMap<Handle, ChildStdout>,
+    child_stderrs: DashMap<Handle, ChildStderr>,
+    #[cfg(feature = "ffi")]
+    ffi_libs: DashMap<String, libloading::Library>,
     #[cfg(feature = "audio")]
     audio_stream_time: Mutex<Option<f64>>,
     #[cfg(feature = "audio")]
@@ -467,6 +579,16 @@ enum SysStream<'a> {
     File(dashmap::mapref::one::RefMut<'a, Handle, Buffered<File>>),
     TcpListener(dashmap::mapref::one::RefMut<'a, Handle, TcpListener>),
     TcpSocket(dashmap::mapref::one::RefMut<'a, Handle, Buffered<TcpStream>>),
+    UdpSocket(dashmap::mapref::one::RefMut<'a, Handle, UdpSocket>),
+    ChildStdin(dashmap::mapref::one::RefMut<'a, Handle, ChildStdin>),
+    ChildStdout(dashmap::mapref::one::RefMut<'a, Handle, ChildStdout>),
+    ChildStderr(dashmap::mapref::one::RefMut<'a, Handle, ChildStderr>),
+}
+
+struct ChildProcess {
+    child: Child,
+    /// The handle of the process's stdin, which is closed before waiting
+    stdin: Handle,
 }
 
 impl Default for GlobalNativeSys {
@@ -476,7 +598,14 @@ impl Default for GlobalNativeSys {
             files: DashMap::new(),
             tcp_listeners: DashMap::new(),
             tcp_sockets: DashMap::new(),
+            udp_sockets: DashMap::new(),
             threads: DashMap::new(),
+            processes: DashMap::new(),
+            child_stdins: DashMap::new(),
+            child_stdouts: DashMap::new(),
+            child_stderrs: DashMap::new(),
+            #[cfg(feature = "ffi")]
+            ffi_libs: DashMap::new(),
             #[cfg(feature = "audio")]
             audio_stream_time: Mutex::new(None),
             #[cfg(feature = "audio")]
@@ -493,6 +622,11 @@ impl GlobalNativeSys {
             if !self.files.contains_key(&handle)
                 && !self.tcp_listeners.contains_key(&handle)
                 && !self.tcp_sockets.contains_key(&handle)
+                && !self.udp_sockets.contains_key(&handle)
+                && !self.processes.contains_key(&handle)
+                && !self.child_stdins.contains_key(&handle)
+                && !self.child_stdouts.contains_key(&handle)
+                && !self.child_stderrs.contains_key(&handle)
             {
                 return handle;
             }
@@ -506,6 +640,14 @@ impl GlobalNativeSys {
             SysStream::TcpListener(listener)
         } else if let Some(socket) = self.tcp_sockets.get_mut(&handle) {
             SysStream::TcpSocket(socket)
+        } else if let Some(socket) = self.udp_sockets.get_mut(&handle) {
+            SysStream::UdpSocket(socket)
+        } else if let Some(stdin) = self.child_stdins.get_mut(&handle) {
+            SysStream::ChildStdin(stdin)
+        } else if let Some(stdout) = self.child_stdouts.get_mut(&handle) {
+            SysStream::ChildStdout(stdout)
+        } else if let Some(stderr) = self.child_stderrs.get_mut(&handle) {
+            SysStream::ChildStderr(stderr)
         } else {
             return Err("Invalid file handle".to_string());
         })
@@ -514,6 +656,22 @@ impl GlobalNativeSys {
 
 static NATIVE_SYS: Lazy<GlobalNativeSys> = Lazy::new(Default::default);
 
+/// Read up to `len` bytes with a single read, so that a pipe does not wait for more output
+/// than is available
+fn read_available(reader: &mut impl Read, len: usize) -> Result<Vec<u8>, String> {
+    let mut buf = vec![0; len.min(1 << 16)];
+    let n = reader.read(&mut buf).map_err(|e| e.to_string())?;
+    buf.truncate(n);
+    Ok(buf)
+}
+
+impl NativeSys {
+    /// Reserve a handle that will not be used by any native stream
+    pub(crate) fn new_handle() -> Handle {
+        NATIVE_SYS.new_handle()
+    }
+}
+
 #[cfg(feature = "audio")]
 pub fn set_audio_stream_time(time: f64) {
     *NATIVE_SYS.audio_stream_time.lock() = Some(time);
@@ -593,6 +751,14 @@ impl SysBackend for NativeSys {
         Ok(handle)
     }
     fn read(&self, handle: Handle, len: usize) -> Result<Vec<u8>, String> {
+        if handle == Handle::STDIN {
+            return stdin()
+                .lock()
+                .bytes()
+                .take(len)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string());
+        }
         Ok(match NATIVE_SYS.get_stream(handle)? {
             SysStream::File(mut file) => {
                 let mut buf = Vec::new();
@@ -611,6 +777,14 @@ impl SysBackend for NativeSys {
                     .map_err(|e| e.to_string())?;
                 buf
             }
+            SysStream::UdpSocket(_) => {
+                return Err("Cannot read from a udp socket, use &udpr instead".to_string())
+            }
+            SysStream::ChildStdin(_) => {
+                return Err("Cannot read from a process's stdin".to_string())
+            }
+            SysStream::ChildStdout(mut stdout) => read_available(&mut *stdout, len)?,
+            SysStream::ChildStderr(mut stderr) => read_available(&mut *stderr, len)?,
         })
     }
     fn write(&self, handle: Handle, conts: &[u8]) -> Result<(), String> {
@@ -629,6 +803,15 @@ impl SysBackend for NativeSys {
             SysStream::File(mut file) => file.write_all(conts).map_err(|e| e.to_string()),
             SysStream::TcpListener(_) => Err("Cannot write to a tcp listener".to_string()),
             SysStream::TcpSocket(mut socket) => socket.write_all(conts).map_err(|e| e.to_string()),
+            SysStream::UdpSocket(_) => {
+                Err("Cannot write to a udp socket, use &udps instead".to_string())
+            }
+            SysStream::ChildStdin(mut stdin) => {
+                stdin.write_all(conts).map_err(|e| e.to_string())?;
+                stdin.flush().map_err(|e| e.to_string())
+            }
+            SysStream::ChildStdout(_) => Err("Cannot write to a process's stdout".to_string()),
+            SysStream::ChildStderr(_) => Err("Cannot write to a process's stderr".to_string()),
         }
     }
     fn sleep(&self, seconds: f64) -> Result<(), String> {
@@ -769,6 +952,12 @@ impl SysBackend for NativeSys {
         Ok(handle)
     }
     fn tcp_addr(&self, handle: Handle) -> Result<String, String> {
+        if let Some(listener) = NATIVE_SYS.tcp_listeners.get(&handle) {
+            return Ok(listener
+                .local_addr()
+                .map_err(|e| e.to_string())?
+                .to_string());
+        }
         let socket = NATIVE_SYS
             .tcp_sockets
             .get(&handle)
@@ -820,12 +1009,122 @@ impl SysBackend for NativeSys {
             .map_err(|e| e.to_string())?;
         Ok(())
     }
+    fn udp_bind(&self, addr: &str) -> Result<Handle, String> {
+        let handle = NATIVE_SYS.new_handle();
+        let socket = UdpSocket::bind(addr).map_err(|e| e.to_string())?;
+        NATIVE_SYS.udp_sockets.insert(handle, socket);
+        Ok(handle)
+    }
+    fn udp_send(&self, handle: Handle, contents: &[u8], addr: &str) -> Result<(), String> {
+        let socket = match NATIVE_SYS.get_stream(handle)? {
+            SysStream::UdpSocket(socket) => socket,
+            _ => return Err("Invalid udp socket handle".to_string()),
+        };
+        let sent = socket.send_to(contents, addr).map_err(|e| e.to_string())?;
+        if sent < contents.len() {
+            return Err(format!(
+                "Only {sent} of {} bytes could be sent",
+                contents.len()
+            ));
+        }
+        Ok(())
+    }
+    fn udp_receive(&self, handle: Handle) -> Result<(Vec<u8>, String), String> {
+        // Clone the socket out of the map so that waiting does not lock it
+        let socket = NATIVE_SYS
+            .udp_sockets
+            .get(&handle)
+            .ok_or_else(|| "Invalid udp socket handle".to_string())?
+            .try_clone()
+            .map_err(|e| e.to_string())?;
+        let mut buf = vec![0; u16::MAX as usize];
+        let (len, addr) = socket.recv_from(&mut buf).map_err(|e| e.to_string())?;
+        buf.truncate(len);
+        Ok((buf, addr.to_string()))
+    }
+    fn udp_addr(&self, handle: Handle) -> Result<String, String> {
+        let socket = NATIVE_SYS
+            .udp_sockets
+            .get(&handle)
+            .ok_or_else(|| "Invalid udp socket handle".to_string())?;
+        Ok(socket.local_addr().map_err(|e| e.to_string())?.to_string())
+    }
+    #[cfg(unix)]
+    fn poll(
+        &self,
+        handles: &[Handle],
+        timeout: Option<Duration>,
+    ) -> Result<Vec<[bool; 2]>, String> {
+        use std::os::fd::AsRawFd;
+        fn has_buffered_input<T: Read + Write>(stream: &Buffered<T>) -> bool {
+            stream.buffer().is_some_and(|b| !b.is_empty())
+                || (stream.get_bufreader_ref()).is_some_and(|r| !r.buffer().is_empty())
+        }
+        let mut fds = Vec::with_capacity(handles.len());
+        let mut buffered = Vec::with_capacity(handles.len());
+        for &handle in handles {
+            let (fd, has_buffered) = match handle {
+                Handle::STDIN => (stdin().as_raw_fd(), false),
+                Handle::STDOUT => (stdout().as_raw_fd(), false),
+                Handle::STDERR => (stderr().as_raw_fd(), false),
+                handle => match NATIVE_SYS.get_stream(handle)? {
+                    SysStream::File(file) => {
+                        (file.get_ref().as_raw_fd(), has_buffered_input(&file))
+                    }
+                    SysStream::TcpListener(listener) => (listener.as_raw_fd(), false),
+                    SysStream::TcpSocket(socket) => {
+                        (socket.get_ref().as_raw_fd(), has_buffered_input(&socket))
+                    }
+                    SysStream::UdpSocket(socket) => (socket.as_raw_fd(), false),
+                    SysStream::ChildStdin(stdin) => (stdin.as_raw_fd(), false),
+                    SysStream::ChildStdout(stdout) => (stdout.as_raw_fd(), false),
+                    SysStream::ChildStderr(stderr) => (stderr.as_raw_fd(), false),
+                },
+            };
+            fds.push(libc::pollfd {
+                fd,
+                events: libc::POLLIN | libc::POLLOUT,
+                revents: 0,
+            });
+            buffered.push(has_buffered);
+        }
+        // Data that has already been buffered can be read without waiting
+        let timeout = if buffered.contains(&true) {
+            0
+        } else {
+            timeout.map_or(-1, |t| t.as_millis().min(i32::MAX as u128) as i32)
+        };
+        // Safety: the pointer and length come from a valid slice of `pollfd`s
+        let res = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout) };
+        if res < 0 {
+            return Err(std::io::Error::last_os_error().to_string());
+        }
+        let readable = libc::POLLIN | libc::POLLHUP | libc::POLLERR;
+        Ok((fds.iter().zip(buffered))
+            .map(|(fd, buffered)| {
+                [
+                    buffered || fd.revents & readable != 0,
+                    fd.revents & libc::POLLOUT != 0,
+                ]
+            })
+            .collect())
+    }
     fn close(&self, handle: Handle) -> Result<(), String> {
         if NATIVE_SYS.files.remove(&handle).is_some()
             || NATIVE_SYS.tcp_listeners.remove(&handle).is_some()
             || NATIVE_SYS.tcp_sockets.remove(&handle).is_some()
+            || NATIVE_SYS.udp_sockets.remove(&handle).is_some()
+            || NATIVE_SYS.child_stdins.remove(&handle).is_some()
+            || NATIVE_SYS.child_stdouts.remove(&handle).is_some()
+            || NATIVE_SYS.child_stderrs.remove(&handle).is_some()
         {
             Ok(())
+        } else if let Some((_, mut process)) = NATIVE_SYS.processes.remove(&handle) {
+            NATIVE_SYS.child_stdins.remove(&process.stdin);
+            // The process may have already exited
+            _ = process.child.kill();
+            process.child.wait().map_err(|e| e.to_string())?;
+            Ok(())
         } else {
             Err("Invalid stream handle".to_string())
         }
@@ -877,9 +1176,60 @@ impl SysBackend for NativeSys {
             String::from_utf8_lossy(&output.stderr).into(),
         ))
     }
+    fn run_command_stream(&self, command: &str, args: &[&str]) -> Result<[Handle; 4], String> {
+        let mut child = Command::new(command)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| e.to_string())?;
+        let stdin = NATIVE_SYS.new_handle();
+        NATIVE_SYS
+            .child_stdins
+            .insert(stdin, child.stdin.take().unwrap());
+        let stdout = NATIVE_SYS.new_handle();
+        NATIVE_SYS
+            .child_stdouts
+            .insert(stdout, child.stdout.take().unwrap());
+        let stderr = NATIVE_SYS.new_handle();
+        NATIVE_SYS
+            .child_stderrs
+            .insert(stderr, child.stderr.take().unwrap());
+        let process = NATIVE_SYS.new_handle();
+        NATIVE_SYS
+            .processes
+            .insert(process, ChildProcess { child, stdin });
+        Ok([stdin, stdout, stderr, process])
+    }
+    fn run_command_wait(&self, handle: Handle) -> Result<i32, String> {
+        let (_, mut process) = NATIVE_SYS
+            .processes
+            .remove(&handle)
+            .ok_or_else(|| "Invalid process handle".to_string())?;
+        NATIVE_SYS.child_stdins.remove(&process.stdin);
+        let status = process.child.wait().map_err(|e| e.to_string())?;
+        status
+            .code()
+            .ok_or_else(|| "Process was terminated by a signal".to_string())
+    }
     fn change_directory(&self, path: &str) -> Result<(), String> {
         env::set_current_dir(path).map_err(|e| e.to_string())
     }
+    #[cfg(feature = "ffi")]
+    fn ffi(&self, spec: &FfiSpec, args: Vec<FfiArg>) -> Result<(Value, Vec<Vec<u8>>), String> {
+        let lib = NATIVE_SYS
+            .ffi_libs
+            .entry(spec.lib.clone())
+            .or_try_insert_with(|| {
+                // Safety: loading a library runs its initialization code,
+                // which is the responsibility of the caller
+                unsafe { libloading::Library::new(&spec.lib) }
+                    .map_err(|e| format!("Failed to load {}: {e}", spec.lib))
+            })?
+            .downgrade();
+        crate::ffi::call(&lib, spec, args)
+    }
 }
 
 impl SysOp {
@@ -945,12 +1295,6 @@ impl SysOp {
                 let bytes = match handle {
                     Handle::STDOUT => return Err(env.error("Cannot read from stdout")),
                     Handle::STDERR => return Err(env.error("Cannot read from stderr")),
-                    Handle::STDIN => stdin()
-                        .lock()
-                        .bytes()
-                        .take(count)
-                        .collect::<Result<Vec<_>, _>>()
-                        .map_err(|e| env.error(e))?,
                     _ => env.backend.read(handle, count).map_err(|e| env.error(e))?,
                 };
                 let s = String::from_utf8(bytes).map_err(|e| env.error(e))?;
@@ -965,12 +1309,6 @@ impl SysOp {
                 let bytes = match handle {
                     Handle::STDOUT => return Err(env.error("Cannot read from stdout")),
                     Handle::STDERR => return Err(env.error("Cannot read from stderr")),
-                    Handle::STDIN => stdin()
-                        .lock()
-                        .bytes()
-                        .take(count)
-                        .collect::<Result<Vec<_>, _>>()
-                        .map_err(|e| env.error(e))?,
                     _ => env.backend.read(handle, count).map_err(|e| env.error(e))?,
                 };
                 env.push(bytes);
@@ -987,33 +1325,6 @@ impl SysOp {
                 match handle {
                     Handle::STDOUT => return Err(env.error("Cannot read from stdout")),
                     Handle::STDERR => return Err(env.error("Cannot read from stderr")),
-                    Handle::STDIN => {
-                        let mut is_string = false;
-                        let delim_bytes: Vec<u8> = match delim {
-                            Value::Num(arr) => arr.data.iter().map(|&x| x as u8).collect(),
-                            Value::Byte(arr) => arr.data.into(),
-                            Value::Char(arr) => {
-                                is_string = true;
-                                arr.data.iter().collect::<String>().into()
-                            }
-                            _ => return Err(env.error("Delimiter must be a string or byte array")),
-                        };
-                        let mut buffer = Vec::new();
-                        let stdin = stdin().lock();
-                        for byte in stdin.bytes() {
-                            let byte = byte.map_err(|e| env.error(e))?;
-                            buffer.push(byte);
-                            if buffer.ends_with(&delim_bytes) {
-                                break;
-                            }
-                        }
-                        if is_string {
-                            let s = String::from_utf8_lossy(&buffer).into_owned();
-                            env.push(s);
-                        } else {
-                            env.push(buffer);
-                        }
-                    }
                     _ => match delim {
                         Value::Num(arr) => {
                             let delim: Vec<u8> = arr.data.iter().map(|&x| x as u8).collect();
@@ -1053,6 +1364,8 @@ impl SysOp {
                 let bytes: Vec<u8> = match data {
                     Value::Num(arr) => arr.data.iter().map(|&x| x as u8).collect(),
                     Value::Byte(arr) => arr.data.into(),
+                    Value::Mask(mask) => mask.into_unpacked().data.into(),
+                    Value::Int(arr) => arr.data.iter().map(|&x| x as u8).collect(),
                     Value::Char(arr) => arr.data.iter().collect::<String>().into(),
                     Value::Func(_) => return Err(env.error("Cannot write function array to file")),
                 };
@@ -1110,6 +1423,8 @@ impl SysOp {
                 let bytes: Vec<u8> = match data {
                     Value::Num(arr) => arr.data.iter().map(|&x| x as u8).collect(),
                     Value::Byte(arr) => arr.data.into(),
+                    Value::Mask(mask) => mask.into_unpacked().data.into(),
+                    Value::Int(arr) => arr.data.iter().map(|&x| x as u8).collect(),
                     Value::Char(arr) => arr.data.iter().collect::<String>().into(),
                     Value::Func(_) => return Err(env.error("Cannot write function array to file")),
                 };
@@ -1143,20 +1458,13 @@ impl SysOp {
             }
             SysOp::Import => {
                 let path = env.pop(1)?.as_string(env, "Import path must be a string")?;
-                let input = String::from_utf8(
-                    env.backend
-                        .file_read_all(&path)
-                        .or_else(|e| {
-                            if path == "example.ua" {
-                                Ok(example_ua(|ex| ex.as_bytes().to_vec()))
-                            } else {
-                                Err(e)
-                            }
-                        })
-                        .map_err(|e| env.error(e))?,
-                )
-                .map_err(|e| env.error(format!("Failed to read file: {e}")))?;
-                env.import(&input, path.as_ref())?;
+                let importer = match env.span() {
+                    Span::Code(span) => span.path,
+                    Span::Builtin => None,
+                };
+                let (path, input) =
+                    (env.read_import(importer.as_deref(), &path)).map_err(|e| env.error(e))?;
+                env.import(&input, &path)?;
             }
             SysOp::Now => env.push(instant::now() / 1000.0),
             SysOp::ImDecode => {
@@ -1366,6 +1674,69 @@ impl SysOp {
                     .tcp_set_write_timeout(handle, timeout)
                     .map_err(|e| env.error(e))?;
             }
+            SysOp::UdpBind => {
+                let addr = env.pop(1)?.as_string(env, "Address must be a string")?;
+                let handle = env.backend.udp_bind(&addr).map_err(|e| env.error(e))?;
+                env.push(handle);
+            }
+            SysOp::UdpSend => {
+                let data = env.pop(1)?;
+                let addr = env.pop(2)?.as_string(env, "Address must be a string")?;
+                let handle = env
+                    .pop(3)?
+                    .as_nat(env, "Handle must be an natural number")?
+                    .into();
+                let bytes: Vec<u8> = match data {
+                    Value::Num(arr) => arr.data.iter().map(|&x| x as u8).collect(),
+                    Value::Byte(arr) => arr.data.into(),
+                    Value::Mask(mask) => mask.into_unpacked().data.into(),
+                    Value::Int(arr) => arr.data.iter().map(|&x| x as u8).collect(),
+                    Value::Char(arr) => arr.data.iter().collect::<String>().into(),
+                    Value::Func(_) => return Err(env.error("Cannot send function array")),
+                };
+                env.backend
+                    .udp_send(handle, &bytes, &addr)
+                    .map_err(|e| env.error(e))?;
+            }
+            SysOp::UdpReceive => {
+                let handle = env
+                    .pop(1)?
+                    .as_nat(env, "Handle must be an natural number")?
+                    .into();
+                let (bytes, addr) = env.backend.udp_receive(handle).map_err(|e| env.error(e))?;
+                env.push(bytes);
+                env.push(addr);
+            }
+            SysOp::UdpAddr => {
+                let handle = env
+                    .pop(1)?
+                    .as_nat(env, "Handle must be an natural number")?
+                    .into();
+                let addr = env.backend.udp_addr(handle).map_err(|e| env.error(e))?;
+                env.push(addr);
+            }
+            SysOp::Poll => {
+                let timeout = env.pop(1)?.as_num(env, "Timeout must be a number")?;
+                if timeout.is_nan() || timeout < 0.0 {
+                    return Err(env.error(format!(
+                        "Timeout must be a non-negative number, but it is {timeout}"
+                    )));
+                }
+                // Timeouts too long to represent wait forever
+                let timeout = Duration::try_from_secs_f64(timeout).ok();
+                let handles: Vec<Handle> = env
+                    .pop(2)?
+                    .as_naturals(env, "Handles must be a list of natural numbers")?
+                    .into_iter()
+                    .map(Handle::from)
+                    .collect();
+                let ready = env
+                    .backend
+                    .poll(&handles, timeout)
+                    .map_err(|e| env.error(e))?;
+                let data: Vec<u8> = ready.into_iter().flatten().map(u8::from).collect();
+                env.push(Array::<u8>::new(tiny_vec![handles.len(), 2], data));
+            }
             SysOp::Close => {
                 let handle = env
                     .pop(1)?
@@ -1390,18 +1761,62 @@ impl SysOp {
                 env.push(stdout);
                 env.push(stderr);
             }
+            SysOp::RunStream => {
+                let (command, args) = value_to_command(&env.pop(1)?, env)?;
+                let args: Vec<_> = args.iter().map(|s| s.as_str()).collect();
+                let handles = env
+                    .backend
+                    .run_command_stream(&command, &args)
+                    .map_err(|e| env.error(e))?;
+                env.push(Value::from_iter(handles.map(|h| h.0 as f64)));
+            }
+            SysOp::RunWait => {
+                let handle = env
+                    .pop(1)?
+                    .as_nat(env, "Handle must be an natural number")?
+                    .into();
+                let code = env
+                    .backend
+                    .run_command_wait(handle)
+                    .map_err(|e| env.error(e))?;
+                env.push(code);
+            }
             SysOp::ChangeDirectory => {
                 let path = env.pop(1)?.as_string(env, "Path must be a string")?;
                 env.backend
                     .change_directory(&path)
                     .map_err(|e| env.error(e))?;
             }
+            SysOp::Ffi => {
+                let spec = value_to_strings(&env.pop(1)?, env, "FFI spec")?;
+                let spec = FfiSpec::from_strings(&spec).map_err(|e| env.error(e))?;
+                let args = value_to_ffi_args(env.pop(2)?, &spec, env)?;
+                let (result, buffers) = env.backend.ffi(&spec, args).map_err(|e| env.error(e))?;
+                if buffers.is_empty() {
+                    env.push(result);
+                } else {
+                    let boxed = (Some(result).into_iter())
+                        .chain(buffers.into_iter().map(Value::from))
+                        .map(Function::constant)
+                        .map(Arc::new);
+                    env.push(Value::from_iter(boxed));
+                }
+            }
         }
         Ok(())
     }
 }
 
 fn value_to_command(value: &Value, env: &Uiua) -> UiuaResult<(String, Vec<String>)> {
+    let mut strings = value_to_strings(value, env, "Command")?;
+    if strings.is_empty() {
+        return Err(env.error("Command array not be empty"));
+    }
+    let command = strings.remove(0);
+    Ok((command, strings))
+}
+
+fn value_to_strings(value: &Value, env: &Uiua, what: &str) -> UiuaResult<Vec<String>> {
     let mut strings = Vec::new();
     match value {
         Value::Char(arr) => match arr.rank() {
@@ -1448,18 +1863,76 @@ fn value_to_command(value: &Value, env: &Uiua) -> UiuaResult<(String, Vec<String
                 )))
             }
         },
-        Value::Num(_) | Value::Byte(_) => {
+        Value::Num(_) | Value::Byte(_) | Value::Int(_) | Value::Mask(_) => {
             return Err(env.error(format!(
-                "Command must be a string or function array, but it is {}s",
+                "{what} must be a string or function array, but it is {}s",
                 value.type_name()
             )))
         }
     }
-    if strings.is_empty() {
-        return Err(env.error("Command array not be empty"));
-    }
-    let command = strings.remove(0);
-    Ok((command, strings))
+    Ok(strings)
+}
+
+fn value_to_ffi_args(value: Value, spec: &FfiSpec, env: &Uiua) -> UiuaResult<Vec<FfiArg>> {
+    let args: Vec<Value> = match value {
+        Value::Func(arr) if arr.rank() == 1 => (arr.data.iter())
+            .map(|f| {
+                f.as_constant().cloned().ok_or_else(|| {
+                    env.error(format!(
+                        "Function array of ffi arguments must be all constant \
+                        functions, but {f} is not constant"
+                    ))
+                })
+            })
+            .collect::<UiuaResult<_>>()?,
+        value if value.rank() == 1 => value.into_rows().collect(),
+        value => {
+            return Err(env.error(format!(
+                "FFI arguments must be a list, but their rank is {}",
+                value.rank()
+            )))
+        }
+    };
+    if args.len() != spec.args.len() {
+        return Err(env.error(format!(
+            "`{}` expects {} argument{}, but {} {} given",
+            spec.name,
+            spec.args.len(),
+            if spec.args.len() == 1 { "" } else { "s" },
+            args.len(),
+            if args.len() == 1 { "was" } else { "were" }
+        )));
+    }
+    (spec.args.iter().zip(args))
+        .map(|(ty, arg)| {
+            Ok(match ty {
+                FfiType::Int => {
+                    let i = arg.as_int(env, "An `int` argument must be an integer")?;
+                    FfiArg::Int(i32::try_from(i).map_err(|_| {
+                        env.error(format!(
+                            "An `int` argument must fit in 32 bits, but it is {i}"
+                        ))
+                    })?)
+                }
+                FfiType::Long => {
+                    FfiArg::Long(arg.as_int(env, "A `long` argument must be an integer")? as i64)
+                }
+                FfiType::Double => {
+                    FfiArg::Double(arg.as_num(env, "A `double` argument must be a number")?)
+                }
+                FfiType::Ptr => {
+                    FfiArg::Ptr(arg.as_nat(env, "A `ptr` argument must be a natural number")?)
+                }
+                FfiType::Bytes => FfiArg::Bytes(
+                    arg.into_bytes(env, "A `bytes` argument must be a list of bytes")?,
+                ),
+                FfiType::Str => {
+                    FfiArg::Str(arg.as_string(env, "A `str` argument must be a string")?)
+                }
+                FfiType::Void => return Err(env.error("`void` cannot be used as an argument type")),
+            })
+        })
+        .collect()
 }
 
 pub fn value_to_image_bytes(value: &Value, format: ImageOutputFormat) -> Result<Vec<u8>, String> {
@@ -1485,6 +1958,7 @@ pub fn value_to_image(value: &Value) -> Result<DynamicImage, String> {
             .map(|f| (*f * 255.0).floor() as u8)
             .collect(),
         Value::Byte(bytes) => bytes.data.iter().map(|&b| (b > 0) as u8 * 255).collect(),
+        Value::Int(ints) => ints.data.iter().map(|&i| (i > 0) as u8 * 255).collect(),
         _ => return Err("Image must be a numeric array".into()),
     };
     #[allow(clippy::match_ref_pats)]
@@ -1518,6 +1992,7 @@ pub fn value_to_sample(audio: &Value) -> Result<Vec<[f32; 2]>, String> {
     let unrolled: Vec<f32> = match audio {
         Value::Num(nums) => nums.data.iter().map(|&f| f as f32).collect(),
         Value::Byte(byte) => byte.data.iter().map(|&b| b as f32).collect(),
+        Value::Int(int) => int.data.iter().map(|&i| i as f32).collect(),
         _ => return Err("Audio must be a numeric array".into()),
     };
     let (length, mut channels) = match audio.rank() {
@@ -1557,6 +2032,7 @@ pub fn value_to_audio_channels(audio: &Value) -> Result<Vec<Vec<f64>>, String> {
     let interleaved: Vec<f64> = match audio {
         Value::Num(nums) => nums.data.iter().copied().collect(),
         Value::Byte(byte) => byte.data.iter().map(|&b| b as f64).collect(),
+        Value::Int(int) => int.data.iter().map(|&i| i as f64).collect(),
         _ => return Err("Audio must be a numeric array".into()),
     };
     let (length, mut channels) = match audio.rank() {
@@ -3,25 +3,29 @@ use std::{
     cmp::Ordering,
     fmt,
     hash::{Hash, Hasher},
-    mem::take,
+    mem::{size_of, take},
     sync::Arc,
 };
 
 use crate::{
-    algorithm::pervade::*,
+    algorithm::{pervade::*, simd},
     array::*,
     function::{Function, Signature},
     grid_fmt::GridFmt,
+    lex::Span,
+    mask::Mask,
     primitive::Primitive,
-    Uiua, UiuaResult,
+    Uiua, UiuaError, UiuaResult,
 };
 
 #[derive(Clone)]
 pub enum Value {
     Num(Array<f64>),
     Byte(Array<u8>),
+    Int(Array<i64>),
     Char(Array<char>),
     Func(Array<Arc<Function>>),
+    Mask(Mask),
 }
 
 impl Default for Value {
@@ -35,8 +39,10 @@ impl fmt::Debug for Value {
         match self {
             Self::Num(array) => array.fmt(f),
             Self::Byte(array) => array.fmt(f),
+            Self::Int(array) => array.fmt(f),
             Self::Char(array) => array.fmt(f),
             Self::Func(array) => array.fmt(f),
+            Self::Mask(mask) => mask.fmt(f),
         }
     }
 }
@@ -61,6 +67,12 @@ impl Value {
             _ => None,
         }
     }
+    pub fn as_int_array(&self) -> Option<&Array<i64>> {
+        match self {
+            Self::Int(array) => Some(array),
+            _ => None,
+        }
+    }
     pub fn as_char_array(&self) -> Option<&Array<char>> {
         match self {
             Self::Char(array) => Some(array),
@@ -92,36 +104,68 @@ impl Value {
         match self {
             Self::Num(array) => Box::new(array.into_rows().map(Value::from)),
             Self::Byte(array) => Box::new(array.into_rows().map(Value::from)),
+            Self::Int(array) => Box::new(array.into_rows().map(Value::from)),
             Self::Char(array) => Box::new(array.into_rows().map(Value::from)),
             Self::Func(array) => Box::new(array.into_rows().map(Value::from)),
+            Self::Mask(mask) => Box::new(mask.into_unpacked().into_rows().map(Value::from)),
+        }
+    }
+    /// Iterate over the windows of `size` consecutive rows, making each one only when it is needed
+    ///
+    /// The array must have at least `size` rows.
+    pub fn into_row_windows(self, size: usize) -> Box<dyn Iterator<Item = Self>> {
+        match self {
+            Self::Num(array) => Box::new(array.into_row_windows(size).map(Value::from)),
+            Self::Byte(array) => Box::new(array.into_row_windows(size).map(Value::from)),
+            Self::Int(array) => Box::new(array.into_row_windows(size).map(Value::from)),
+            Self::Char(array) => Box::new(array.into_row_windows(size).map(Value::from)),
+            Self::Func(array) => Box::new(array.into_row_windows(size).map(Value::from)),
+            Self::Mask(mask) => {
+                Box::new((mask.into_unpacked().into_row_windows(size)).map(Value::from))
+            }
         }
     }
     pub fn into_rows_rev(self) -> Box<dyn Iterator<Item = Self>> {
         match self {
             Self::Num(array) => Box::new(array.into_rows_rev().map(Value::from)),
             Self::Byte(array) => Box::new(array.into_rows_rev().map(Value::from)),
+            Self::Int(array) => Box::new(array.into_rows_rev().map(Value::from)),
             Self::Char(array) => Box::new(array.into_rows_rev().map(Value::from)),
             Self::Func(array) => Box::new(array.into_rows_rev().map(Value::from)),
+            Self::Mask(mask) => Box::new(mask.into_unpacked().into_rows_rev().map(Value::from)),
         }
     }
     pub fn into_flat_values(self) -> Box<dyn Iterator<Item = Self>> {
         match self {
             Self::Num(array) => Box::new(array.data.into_iter().map(Value::from)),
             Self::Byte(array) => Box::new(array.data.into_iter().map(Value::from)),
+            Self::Int(array) => Box::new(array.data.into_iter().map(Value::from)),
             Self::Char(array) => Box::new(array.data.into_iter().map(Value::from)),
             Self::Func(array) => Box::new(array.data.into_iter().map(Value::from)),
+            Self::Mask(mask) => Box::new((mask.into_unpacked().data.into_iter()).map(Value::from)),
         }
     }
     pub fn type_name(&self) -> &'static str {
         match self {
             Self::Num(_) => "number",
             Self::Byte(_) => "byte",
+            Self::Int(_) => "integer",
             Self::Char(_) => "character",
             Self::Func(_) => "function",
+            Self::Mask(_) => "byte",
         }
     }
     pub fn shape(&self) -> &[usize] {
-        self.generic_ref(Array::shape, Array::shape, Array::shape, Array::shape)
+        if let Self::Mask(mask) = self {
+            return mask.shape();
+        }
+        self.generic_ref(
+            Array::shape,
+            Array::shape,
+            Array::shape,
+            Array::shape,
+            Array::shape,
+        )
     }
     pub fn shape_prefixes_match(&self, other: &Self) -> bool {
         self.shape().iter().zip(other.shape()).all(|(a, b)| a == b)
@@ -132,6 +176,7 @@ impl Value {
             Array::row_count,
             Array::row_count,
             Array::row_count,
+            Array::row_count,
         )
     }
     pub fn row_len(&self) -> usize {
@@ -140,22 +185,44 @@ impl Value {
             Array::row_len,
             Array::row_len,
             Array::row_len,
+            Array::row_len,
         )
     }
     pub fn flat_len(&self) -> usize {
+        if let Self::Mask(mask) = self {
+            return mask.flat_len();
+        }
         self.generic_ref(
             Array::flat_len,
             Array::flat_len,
             Array::flat_len,
             Array::flat_len,
+            Array::flat_len,
         )
     }
+    /// Estimate the number of bytes used by the value's elements, including the values in boxes
+    pub(crate) fn size_estimate(&self) -> usize {
+        match self {
+            Self::Num(array) => array.flat_len() * size_of::<f64>(),
+            Self::Byte(array) => array.flat_len() * size_of::<u8>(),
+            Self::Int(array) => array.flat_len() * size_of::<i64>(),
+            Self::Char(array) => array.flat_len() * size_of::<char>(),
+            Self::Func(array) => (array.data.iter())
+                .map(|f| {
+                    size_of::<Arc<Function>>() + f.as_constant().map_or(0, Value::size_estimate)
+                })
+                .sum(),
+            Self::Mask(mask) => mask.flat_len().div_ceil(8),
+        }
+    }
     pub(crate) fn first_dim_zero(&self) -> Self {
         match self {
             Self::Num(array) => array.first_dim_zero().into(),
             Self::Byte(array) => array.first_dim_zero().into(),
+            Self::Int(array) => array.first_dim_zero().into(),
             Self::Char(array) => array.first_dim_zero().into(),
             Self::Func(array) => array.first_dim_zero().into(),
+            Self::Mask(mask) => mask.unpacked().first_dim_zero().into(),
         }
     }
     pub fn format_shape(&self) -> FormatShape {
@@ -164,17 +231,21 @@ impl Value {
             Array::format_shape,
             Array::format_shape,
             Array::format_shape,
+            Array::format_shape,
         )
     }
     pub fn rank(&self) -> usize {
         self.shape().len()
     }
     pub fn shape_mut(&mut self) -> &mut Shape {
+        self.unpack();
         match self {
             Self::Num(array) => &mut array.shape,
             Self::Byte(array) => &mut array.shape,
+            Self::Int(array) => &mut array.shape,
             Self::Char(array) => &mut array.shape,
             Self::Func(array) => &mut array.shape,
+            Self::Mask(_) => unreachable!(),
         }
     }
     pub(crate) fn validate_shape(&self) {
@@ -183,6 +254,7 @@ impl Value {
             Array::validate_shape,
             Array::validate_shape,
             Array::validate_shape,
+            Array::validate_shape,
         )
     }
     pub fn row(&self, i: usize) -> Self {
@@ -191,73 +263,91 @@ impl Value {
             |arr| arr.row(i).into(),
             |arr| arr.row(i).into(),
             |arr| arr.row(i).into(),
+            |arr| arr.row(i).into(),
         )
     }
     pub fn generic_into<T>(
         self,
         n: impl FnOnce(Array<f64>) -> T,
         b: impl FnOnce(Array<u8>) -> T,
+        i: impl FnOnce(Array<i64>) -> T,
         c: impl FnOnce(Array<char>) -> T,
         f: impl FnOnce(Array<Arc<Function>>) -> T,
     ) -> T {
         match self {
             Self::Num(array) => n(array),
             Self::Byte(array) => b(array),
+            Self::Int(array) => i(array),
             Self::Char(array) => c(array),
             Self::Func(array) => match array.into_constant() {
-                Ok(value) => value.generic_into(n, b, c, f),
+                Ok(value) => value.generic_into(n, b, i, c, f),
                 Err(array) => f(array),
             },
+            Self::Mask(mask) => b(mask.into_unpacked()),
         }
     }
     pub fn generic_ref<'a, T: 'a>(
         &'a self,
         n: impl FnOnce(&'a Array<f64>) -> T,
         b: impl FnOnce(&'a Array<u8>) -> T,
+        i: impl FnOnce(&'a Array<i64>) -> T,
         c: impl FnOnce(&'a Array<char>) -> T,
         f: impl FnOnce(&'a Array<Arc<Function>>) -> T,
     ) -> T {
         match self {
             Self::Num(array) => n(array),
             Self::Byte(array) => b(array),
+            Self::Int(array) => i(array),
             Self::Char(array) => c(array),
             Self::Func(array) => {
                 if let Some(value) = array.as_constant() {
-                    value.generic_ref(n, b, c, f)
+                    value.generic_ref(n, b, i, c, f)
                 } else {
                     f(array)
                 }
             }
+            Self::Mask(mask) => b(mask.unpacked()),
         }
     }
     pub fn generic_ref_env<'a, T: 'a>(
         &'a self,
         n: impl FnOnce(&'a Array<f64>, &Uiua) -> UiuaResult<T>,
         b: impl FnOnce(&'a Array<u8>, &Uiua) -> UiuaResult<T>,
+        i: impl FnOnce(&'a Array<i64>, &Uiua) -> UiuaResult<T>,
         c: impl FnOnce(&'a Array<char>, &Uiua) -> UiuaResult<T>,
         f: impl FnOnce(&'a Array<Arc<Function>>, &Uiua) -> UiuaResult<T>,
         env: &Uiua,
     ) -> UiuaResult<T> {
-        self.generic_ref(|a| n(a, env), |a| b(a, env), |a| c(a, env), |a| f(a, env))
+        self.generic_ref(
+            |a| n(a, env),
+            |a| b(a, env),
+            |a| i(a, env),
+            |a| c(a, env),
+            |a| f(a, env),
+        )
     }
     pub fn generic_mut<T>(
         &mut self,
         n: impl FnOnce(&mut Array<f64>) -> T,
         b: impl FnOnce(&mut Array<u8>) -> T,
+        i: impl FnOnce(&mut Array<i64>) -> T,
         c: impl FnOnce(&mut Array<char>) -> T,
         f: impl FnOnce(&mut Array<Arc<Function>>) -> T,
     ) -> T {
+        self.unpack();
         match self {
             Self::Num(array) => n(array),
             Self::Byte(array) => b(array),
+            Self::Int(array) => i(array),
             Self::Char(array) => c(array),
             Self::Func(array) => {
                 if let Some(value) = array.as_constant_mut() {
-                    value.generic_mut(n, b, c, f)
+                    value.generic_mut(n, b, i, c, f)
                 } else {
                     f(array)
                 }
             }
+            Self::Mask(_) => unreachable!(),
         }
     }
     /// Get the pretty-printed string representation of the value
@@ -265,8 +355,10 @@ impl Value {
         match self {
             Self::Num(array) => array.grid_string(),
             Self::Byte(array) => array.grid_string(),
+            Self::Int(array) => array.grid_string(),
             Self::Char(array) => array.grid_string(),
             Self::Func(array) => array.grid_string(),
+            Self::Mask(mask) => mask.unpacked().grid_string(),
         }
     }
     pub fn as_primitive(&self) -> Option<(Primitive, usize)> {
@@ -313,6 +405,18 @@ impl Value {
                 }
                 bytes.data[0] as usize
             }
+            Value::Int(ints) => {
+                if ints.rank() > 0 {
+                    return Err(
+                        env.error(format!("{requirement}, but its rank is {}", ints.rank()))
+                    );
+                }
+                let int = ints.data[0];
+                if int < 0 {
+                    return Err(env.error(format!("{requirement}, but it is negative")));
+                }
+                int as usize
+            }
             value => {
                 return Err(env.error(format!("{requirement}, but it is {}", value.type_name())))
             }
@@ -340,6 +444,14 @@ impl Value {
                 }
                 bytes.data[0] as isize
             }
+            Value::Int(ints) => {
+                if ints.rank() > 0 {
+                    return Err(
+                        env.error(format!("{requirement}, but its rank is {}", ints.rank()))
+                    );
+                }
+                ints.data[0] as isize
+            }
             value => {
                 return Err(env.error(format!("{requirement}, but it is {}", value.type_name())))
             }
@@ -363,6 +475,14 @@ impl Value {
                 }
                 bytes.data[0] as f64
             }
+            Value::Int(ints) => {
+                if ints.rank() > 0 {
+                    return Err(
+                        env.error(format!("{requirement}, but its rank is {}", ints.rank()))
+                    );
+                }
+                ints.data[0] as f64
+            }
             value => {
                 return Err(env.error(format!("{requirement}, but it is {}", value.type_name())))
             }
@@ -415,6 +535,22 @@ impl Value {
                 }
                 result
             }
+            Value::Int(ints) => {
+                if ints.rank() > 1 {
+                    return Err(
+                        env.error(format!("{requirement}, but its rank is {}", ints.rank()))
+                    );
+                }
+                let mut result = Vec::with_capacity(ints.row_count());
+                for &int in ints.data() {
+                    let num = int as f64;
+                    if !test(num) {
+                        return Err(env.error(requirement));
+                    }
+                    result.push(convert(num));
+                }
+                result
+            }
             value => {
                 return Err(env.error(format!("{requirement}, but it is {}s", value.type_name())))
             }
@@ -462,6 +598,23 @@ impl Value {
                 }
                 Array::new(self.shape(), result)
             }
+            Value::Int(ints) => {
+                if !test_shape(self.shape()) {
+                    return Err(env.error(format!(
+                        "{requirement}, but its shape is {}",
+                        ints.format_shape()
+                    )));
+                }
+                let mut result = Vec::with_capacity(ints.flat_len());
+                for &int in ints.data() {
+                    let num = int as f64;
+                    if !test_num(num) {
+                        return Err(env.error(requirement));
+                    }
+                    result.push(convert_num(num));
+                }
+                Array::new(self.shape(), result)
+            }
             value => {
                 return Err(env.error(format!(
                     "{requirement}, but its type is {}",
@@ -497,6 +650,12 @@ impl Value {
                 }
                 a.data.into_iter().map(|f| f as u8).collect()
             }
+            Value::Int(a) => {
+                if a.rank() != 1 {
+                    return Err(env.error(format!("{requirement}, but its rank is {}", a.rank())));
+                }
+                a.data.into_iter().map(|i| i as u8).collect()
+            }
             Value::Char(a) => {
                 if a.rank() != 1 {
                     return Err(env.error(format!("{requirement}, but its rank is {}", a.rank())));
@@ -527,10 +686,33 @@ impl Value {
             }
         }
     }
+    /// Give an integer array paired with a number or byte array the same type as it
+    ///
+    /// Bytes become integers. Numbers become integers if they all are integers that fit,
+    /// and otherwise the integers become numbers. Other pairs are returned unchanged.
+    pub(crate) fn unify_ints(a: Self, b: Self) -> (Self, Self) {
+        match (a, b) {
+            (Value::Int(a), Value::Byte(b)) => (Value::Int(a), Value::Int(b.convert())),
+            (Value::Byte(a), Value::Int(b)) => (Value::Int(a.convert()), Value::Int(b)),
+            (Value::Int(a), Value::Num(b)) => match nums_as_ints(&b) {
+                Some(b) => (Value::Int(a), Value::Int(b)),
+                None => (Value::Num(a.convert_with(|i| i as f64)), Value::Num(b)),
+            },
+            (Value::Num(a), Value::Int(b)) => match nums_as_ints(&a) {
+                Some(a) => (Value::Int(a), Value::Int(b)),
+                None => (Value::Num(a), Value::Num(b.convert_with(|i| i as f64))),
+            },
+            (a, b) => (a, b),
+        }
+    }
     pub fn coerce_to_function(self) -> Array<Arc<Function>> {
         match self {
             Value::Num(arr) => arr.convert_with(|n| Arc::new(Function::constant(n))),
             Value::Byte(arr) => arr.convert_with(|n| Arc::new(Function::constant(n))),
+            Value::Int(arr) => arr.convert_with(|n| Arc::new(Function::constant(n))),
+            Value::Mask(mask) => {
+                (mask.into_unpacked()).convert_with(|n| Arc::new(Function::constant(n)))
+            }
             Value::Char(arr) => arr.convert_with(|n| Arc::new(Function::constant(n))),
             Value::Func(arr) => arr,
         }
@@ -543,6 +725,12 @@ impl Value {
             Value::Byte(arr) => {
                 Cow::Owned(arr.convert_ref_with(|n| Arc::new(Function::constant(n))))
             }
+            Value::Int(arr) => {
+                Cow::Owned(arr.convert_ref_with(|n| Arc::new(Function::constant(n))))
+            }
+            Value::Mask(mask) => {
+                Cow::Owned((mask.unpacked()).convert_ref_with(|n| Arc::new(Function::constant(n))))
+            }
             Value::Char(arr) => {
                 Cow::Owned(arr.convert_ref_with(|n| Arc::new(Function::constant(n))))
             }
@@ -551,6 +739,14 @@ impl Value {
     }
 }
 
+/// Convert numbers to integers if they all are integers that fit
+pub(crate) fn nums_as_ints(nums: &Array<f64>) -> Option<Array<i64>> {
+    const LIMIT: f64 = 9_223_372_036_854_775_808.0; // 2^63
+    (nums.data.iter())
+        .all(|&n| n.fract() == 0.0 && (-LIMIT..LIMIT).contains(&n))
+        .then(|| nums.convert_ref_with(|n| n as i64))
+}
+
 macro_rules! value_from {
     ($ty:ty, $variant:ident) => {
         impl From<$ty> for Value {
@@ -583,6 +779,7 @@ macro_rules! value_from {
 
 value_from!(f64, Num);
 value_from!(u8, Byte);
+value_from!(i64, Int);
 value_from!(char, Char);
 value_from!(Arc<Function>, Func);
 
@@ -628,14 +825,237 @@ impl From<i32> for Value {
     }
 }
 
+fn conversion_error(message: impl Into<String>) -> UiuaError {
+    UiuaError::Run(Span::Builtin.sp(message.into()))
+}
+
+impl TryFrom<Value> for Array<f64> {
+    type Error = UiuaError;
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Num(arr) => Ok(arr),
+            Value::Byte(arr) => Ok(arr.convert_with(|b| b as f64)),
+            Value::Int(arr) => Ok(arr.convert_with(|i| i as f64)),
+            value => Err(conversion_error(format!(
+                "Expected a number array, but the value is a {} array",
+                value.type_name()
+            ))),
+        }
+    }
+}
+
+impl TryFrom<Value> for f64 {
+    type Error = UiuaError;
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        Array::<f64>::try_from(value)?.into_scalar().map_err(|arr| {
+            conversion_error(format!(
+                "Expected a scalar number, but the value has shape {}",
+                arr.format_shape()
+            ))
+        })
+    }
+}
+
+impl TryFrom<Value> for Vec<f64> {
+    type Error = UiuaError;
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        let arr = Array::<f64>::try_from(value)?;
+        if arr.rank() != 1 {
+            return Err(conversion_error(format!(
+                "Expected a list of numbers, but the value has rank {}",
+                arr.rank()
+            )));
+        }
+        Ok(arr.data.into())
+    }
+}
+
+impl TryFrom<Value> for Vec<Vec<f64>> {
+    type Error = UiuaError;
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        let arr = Array::<f64>::try_from(value)?;
+        if arr.rank() != 2 {
+            return Err(conversion_error(format!(
+                "Expected a table of numbers, but the value has rank {}",
+                arr.rank()
+            )));
+        }
+        Ok(arr.rows().map(|row| row.data.into()).collect())
+    }
+}
+
+/// Convert a numeric value into its shape and row-major data
+impl TryFrom<Value> for (Vec<usize>, Vec<f64>) {
+    type Error = UiuaError;
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        let arr = Array::<f64>::try_from(value)?;
+        Ok((arr.shape.to_vec(), arr.data.into()))
+    }
+}
+
+impl TryFrom<Value> for Array<i64> {
+    type Error = UiuaError;
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Int(arr) => Ok(arr),
+            Value::Byte(arr) => Ok(arr.convert_with(|b| b as i64)),
+            Value::Num(arr) => arr.try_convert_with(|n| {
+                if n.fract() == 0.0 && (i64::MIN as f64..i64::MAX as f64).contains(&n) {
+                    Ok(n as i64)
+                } else {
+                    Err(conversion_error(format!(
+                        "Expected an array of integers, but it contains {n}"
+                    )))
+                }
+            }),
+            value => Err(conversion_error(format!(
+                "Expected an integer array, but the value is a {} array",
+                value.type_name()
+            ))),
+        }
+    }
+}
+
+impl TryFrom<Value> for i64 {
+    type Error = UiuaError;
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        Array::<i64>::try_from(value)?.into_scalar().map_err(|arr| {
+            conversion_error(format!(
+                "Expected a scalar integer, but the value has shape {}",
+                arr.format_shape()
+            ))
+        })
+    }
+}
+
+impl TryFrom<Value> for Vec<i64> {
+    type Error = UiuaError;
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        let arr = Array::<i64>::try_from(value)?;
+        if arr.rank() != 1 {
+            return Err(conversion_error(format!(
+                "Expected a list of integers, but the value has rank {}",
+                arr.rank()
+            )));
+        }
+        Ok(arr.data.into())
+    }
+}
+
+impl TryFrom<Value> for String {
+    type Error = UiuaError;
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Char(arr) if arr.rank() <= 1 => Ok(arr.data.iter().collect()),
+            Value::Char(arr) => Err(conversion_error(format!(
+                "Expected a string, but the value has rank {}",
+                arr.rank()
+            ))),
+            Value::Func(arr) if arr.as_constant().is_some() => {
+                String::try_from(arr.as_constant().unwrap().clone())
+            }
+            value => Err(conversion_error(format!(
+                "Expected a string, but the value is a {} array",
+                value.type_name()
+            ))),
+        }
+    }
+}
+
+/// Convert either a character table or a list of boxed strings into a list of strings
+impl TryFrom<Value> for Vec<String> {
+    type Error = UiuaError;
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        if value.rank() != 1 && value.rank() != 2 {
+            return Err(conversion_error(format!(
+                "Expected a list of strings, but the value has rank {}",
+                value.rank()
+            )));
+        }
+        match value {
+            Value::Char(arr) if arr.rank() == 2 => {
+                Ok(arr.rows().map(|row| row.data.iter().collect()).collect())
+            }
+            Value::Func(arr) if arr.rank() == 1 => (arr.data.iter())
+                .map(|f| {
+                    let constant = f.as_constant().ok_or_else(|| {
+                        conversion_error("Expected a list of strings, but it contains a function")
+                    })?;
+                    String::try_from(constant.clone())
+                })
+                .collect(),
+            value => Err(conversion_error(format!(
+                "Expected a list of strings, but the value is a rank {} {} array",
+                value.rank(),
+                value.type_name()
+            ))),
+        }
+    }
+}
+
+/// Build a number array from a shape and row-major data
+impl TryFrom<(Vec<usize>, Vec<f64>)> for Value {
+    type Error = UiuaError;
+    fn try_from((shape, data): (Vec<usize>, Vec<f64>)) -> Result<Self, Self::Error> {
+        let elements: usize = shape.iter().product();
+        if elements != data.len() {
+            return Err(conversion_error(format!(
+                "Shape {} requires {elements} elements, but {} were given",
+                FormatShape(&shape),
+                data.len()
+            )));
+        }
+        Ok(Array::new(shape.as_slice(), data).into())
+    }
+}
+
+/// Build a table of numbers from its rows, which must all be the same length
+impl TryFrom<Vec<Vec<f64>>> for Value {
+    type Error = UiuaError;
+    fn try_from(rows: Vec<Vec<f64>>) -> Result<Self, Self::Error> {
+        let row_len = rows.first().map_or(0, Vec::len);
+        if let Some(row) = rows.iter().find(|row| row.len() != row_len) {
+            return Err(conversion_error(format!(
+                "Cannot make a table from rows of lengths {row_len} and {}",
+                row.len()
+            )));
+        }
+        let shape = [rows.len(), row_len];
+        Ok(Array::new(shape.as_slice(), rows.concat()).into())
+    }
+}
+
+/// Strings of the same length become a character table, otherwise they are boxed
+impl From<Vec<String>> for Value {
+    fn from(strings: Vec<String>) -> Self {
+        let mut lens = strings.iter().map(|s| s.chars().count());
+        let first_len = lens.next();
+        match first_len {
+            Some(len) if lens.all(|l| l == len) => {
+                let shape = [strings.len(), len];
+                let data: Vec<char> = strings.iter().flat_map(|s| s.chars()).collect();
+                Array::new(shape.as_slice(), data).into()
+            }
+            _ => Value::Func(strings.into_iter().collect()),
+        }
+    }
+}
+
 macro_rules! value_un_impl {
-    ($name:ident, $(($variant:ident, $f:ident)),* $(,)?) => {
+    ($name:ident $(, int $int:ident)?, $(($variant:ident, $f:ident)),* $(,)?) => {
         impl Value {
             pub fn $name(self, env: &Uiua) -> UiuaResult<Self> {
                 Ok(match self {
                     $(Self::$variant(array) => {
                         (array.shape, array.data.into_iter().map($name::$f).collect::<Vec<_>>()).into()
                     },)*
+                    // Integers stay integers unless the result does not fit
+                    Value::Int(array) => {
+                        $(if let Some(data) = array.data.iter().map(|&i| $name::$int(i)).collect::<Option<Vec<_>>>() {
+                            return Ok((array.shape, data).into());
+                        })?
+                        Value::Num(array.convert_with(|i| i as f64)).$name(env)?
+                    }
                     Value::Func(mut array) => {
                         let mut new_data = Vec::with_capacity(array.flat_len());
                         for f in array.data {
@@ -655,12 +1075,26 @@ macro_rules! value_un_impl {
 }
 
 macro_rules! value_un_impl_all {
-    ($($name:ident),* $(,)?) => {
-        $(value_un_impl!($name, (Num, num), (Byte, byte));)*
+    ($($name:ident $(: $int:ident)?),* $(,)?) => {
+        $(value_un_impl!($name $(, int $int)?, (Num, num), (Byte, byte));)*
     }
 }
 
-value_un_impl_all!(neg, not, abs, sign, sqrt, sin, cos, tan, asin, acos, floor, ceil, round);
+value_un_impl_all!(
+    neg: int,
+    not: int,
+    abs: int,
+    sign: int,
+    sqrt,
+    sin,
+    cos,
+    tan,
+    asin,
+    acos,
+    floor: int,
+    ceil: int,
+    round: int
+);
 
 macro_rules! val_retry {
     (Byte, $env:expr) => {
@@ -671,14 +1105,34 @@ macro_rules! val_retry {
     };
 }
 
+/// Pervade a dyadic function, using the SIMD kernels for numbers and bytes when the shapes allow it
+macro_rules! simd_bin_pervade {
+    (Num, Num, $($rest:tt)*) => { simd_bin_pervade!(@simd $($rest)*) };
+    (Num, Byte, $($rest:tt)*) => { simd_bin_pervade!(@simd $($rest)*) };
+    (Byte, Num, $($rest:tt)*) => { simd_bin_pervade!(@simd $($rest)*) };
+    (Byte, Byte, $($rest:tt)*) => { simd_bin_pervade!(@simd $($rest)*) };
+    (Int, Int, $($rest:tt)*) => { simd_bin_pervade!(@simd $($rest)*) };
+    (Int, Num, $($rest:tt)*) => { simd_bin_pervade!(@simd $($rest)*) };
+    (Num, Int, $($rest:tt)*) => { simd_bin_pervade!(@simd $($rest)*) };
+    (@simd $a:expr, $b:expr, $env:expr, $f:expr) => {
+        match simd::bin($a, $b, $env.thread_count(), $f) {
+            Some(arr) => Ok(arr),
+            None => bin_pervade($a, $b, $env, InfalliblePervasiveFn::new($f)),
+        }
+    };
+    ($va:ident, $vb:ident, $a:expr, $b:expr, $env:expr, $f:expr) => {
+        bin_pervade($a, $b, $env, InfalliblePervasiveFn::new($f))
+    };
+}
+
 macro_rules! value_bin_impl {
-    ($name:ident, $(($va:ident, $vb:ident, $f:ident $(, $retry:ident)?)),* $(,)?) => {
+    ($name:ident $(, int $int:ident)?, $(($va:ident, $vb:ident, $f:ident $(, $retry:ident)?)),* $(,)?) => {
         impl Value {
             #[allow(unreachable_patterns)]
             pub fn $name(&self, other: &Self, env: &Uiua) -> UiuaResult<Self> {
                 Ok(match (self, other) {
                     $((Value::$va(a), Value::$vb(b)) => {
-                        let res = bin_pervade(a, b, env, InfalliblePervasiveFn::new($name::$f));
+                        let res = simd_bin_pervade!($va, $vb, a, b, env, $name::$f);
                         match res {
                             Ok(arr) => arr.into(),
                             #[allow(unreachable_code, unused_variables)]
@@ -689,6 +1143,30 @@ macro_rules! value_bin_impl {
                             Err(e) => return Err(e),
                         }
                     },)*
+                    // Integers stay integers unless a result does not fit, and are
+                    // otherwise treated as numbers
+                    (Value::Int(a), Value::Int(b)) => {
+                        $(if let Some(arr) = int_bin(a, b, env, $name::$int)? {
+                            return Ok(arr.into());
+                        })?
+                        Value::$name(&Value::Num(a.convert_ref_with(|i| i as f64)), &Value::Num(b.convert_ref_with(|i| i as f64)), env)?
+                    }
+                    (Value::Int(_), Value::Byte(b)) => Value::$name(self, &Value::Int(b.convert_ref()), env)?,
+                    (Value::Byte(a), Value::Int(_)) => Value::$name(&Value::Int(a.convert_ref()), other, env)?,
+                    (Value::Int(a), Value::Num(b)) => match nums_as_ints(b) {
+                        Some(b) => Value::$name(self, &Value::Int(b), env)?,
+                        None => Value::$name(&Value::Num(a.convert_ref_with(|i| i as f64)), other, env)?,
+                    },
+                    (Value::Num(a), Value::Int(b)) => match nums_as_ints(a) {
+                        Some(a) => Value::$name(&Value::Int(a), other, env)?,
+                        None => Value::$name(self, &Value::Num(b.convert_ref_with(|i| i as f64)), env)?,
+                    },
+                    (Value::Int(a), b) if !matches!(b, Value::Func(_)) => {
+                        Value::$name(&Value::Num(a.convert_ref_with(|i| i as f64)), b, env)?
+                    }
+                    (a, Value::Int(b)) if !matches!(a, Value::Func(_)) => {
+                        Value::$name(a, &Value::Num(b.convert_ref_with(|i| i as f64)), env)?
+                    }
                     (Value::Func(a), b) => {
                         match a.as_constant() {
                             Some(a) => Value::$name(a, b, env)?,
@@ -724,6 +1202,7 @@ macro_rules! value_bin_impl {
 
 value_bin_impl!(
     add,
+    int int_int,
     (Num, Num, num_num),
     (Num, Char, num_char),
     (Char, Num, char_num),
@@ -736,6 +1215,7 @@ value_bin_impl!(
 
 value_bin_impl!(
     sub,
+    int int_int,
     (Num, Num, num_num),
     (Num, Char, num_char),
     (Char, Char, char_char),
@@ -747,6 +1227,7 @@ value_bin_impl!(
 
 value_bin_impl!(
     mul,
+    int int_int,
     (Num, Num, num_num),
     (Byte, Byte, byte_byte, num_num),
     (Byte, Num, byte_num, num_num),
@@ -761,6 +1242,7 @@ value_bin_impl!(
 );
 value_bin_impl!(
     modulus,
+    int int_int,
     (Num, Num, num_num),
     (Byte, Byte, byte_byte, num_num),
     (Byte, Num, byte_num, num_num),
@@ -768,6 +1250,7 @@ value_bin_impl!(
 );
 value_bin_impl!(
     pow,
+    int int_int,
     (Num, Num, num_num),
     (Byte, Byte, byte_byte, num_num),
     (Byte, Num, byte_num, num_num),
@@ -784,6 +1267,7 @@ value_bin_impl!(atan2, (Num, Num, num_num));
 
 value_bin_impl!(
     min,
+    int int_int,
     (Num, Num, num_num),
     (Char, Char, char_char),
     (Byte, Byte, byte_byte, num_num),
@@ -793,6 +1277,7 @@ value_bin_impl!(
 
 value_bin_impl!(
     max,
+    int int_int,
     (Num, Num, num_num),
     (Char, Char, char_char),
     (Byte, Byte, byte_byte, num_num),
@@ -811,6 +1296,9 @@ macro_rules! cmp_impls {
                 (Char, Char, generic),
                 (Num, Byte, num_byte, num_num),
                 (Byte, Num, byte_num, num_num),
+                (Int, Int, generic),
+                (Int, Num, int_num),
+                (Num, Int, num_int),
                 // Type comparable
                 (Num, Char, always_less),
                 (Num, Func, always_less),
@@ -829,12 +1317,20 @@ cmp_impls!(is_eq, is_ne, is_lt, is_le, is_gt, is_ge);
 impl PartialEq for Value {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
+            (Value::Mask(a), Value::Mask(b)) => a == b,
+            (Value::Mask(a), b) => Value::Byte(a.unpacked().clone()) == *b,
+            (a, Value::Mask(b)) => *a == Value::Byte(b.unpacked().clone()),
             (Value::Num(a), Value::Num(b)) => a == b,
             (Value::Byte(a), Value::Byte(b)) => a == b,
             (Value::Char(a), Value::Char(b)) => a == b,
             (Value::Func(a), Value::Func(b)) => a == b,
             (Value::Num(a), Value::Byte(b)) => a == b,
             (Value::Byte(a), Value::Num(b)) => a == b,
+            (Value::Int(a), Value::Int(b)) => a == b,
+            (Value::Int(a), Value::Num(b)) => a == b,
+            (Value::Num(a), Value::Int(b)) => a == b,
+            (Value::Int(a), Value::Byte(b)) => a == b,
+            (Value::Byte(a), Value::Int(b)) => a == b,
             _ => false,
         }
     }
@@ -851,16 +1347,25 @@ impl PartialOrd for Value {
 impl Ord for Value {
     fn cmp(&self, other: &Self) -> Ordering {
         match (self, other) {
+            (Value::Mask(a), b) => Value::Byte(a.unpacked().clone()).cmp(b),
+            (a, Value::Mask(b)) => a.cmp(&Value::Byte(b.unpacked().clone())),
             (Value::Num(a), Value::Num(b)) => a.cmp(b),
             (Value::Byte(a), Value::Byte(b)) => a.cmp(b),
             (Value::Char(a), Value::Char(b)) => a.cmp(b),
             (Value::Func(a), Value::Func(b)) => a.cmp(b),
             (Value::Num(a), Value::Byte(b)) => a.partial_cmp(b).unwrap(),
             (Value::Byte(a), Value::Num(b)) => a.partial_cmp(b).unwrap(),
+            (Value::Int(a), Value::Int(b)) => a.cmp(b),
+            (Value::Int(a), Value::Num(b)) => a.partial_cmp(b).unwrap(),
+            (Value::Num(a), Value::Int(b)) => a.partial_cmp(b).unwrap(),
+            (Value::Int(a), Value::Byte(b)) => a.partial_cmp(b).unwrap(),
+            (Value::Byte(a), Value::Int(b)) => a.partial_cmp(b).unwrap(),
             (Value::Num(_), _) => Ordering::Less,
             (_, Value::Num(_)) => Ordering::Greater,
             (Value::Byte(_), _) => Ordering::Less,
             (_, Value::Byte(_)) => Ordering::Greater,
+            (Value::Int(_), _) => Ordering::Less,
+            (_, Value::Int(_)) => Ordering::Greater,
             (Value::Char(_), _) => Ordering::Less,
             (_, Value::Char(_)) => Ordering::Greater,
         }
@@ -878,6 +1383,10 @@ impl Hash for Value {
                 1u8.hash(state);
                 arr.hash(state);
             }
+            Value::Mask(mask) => {
+                1u8.hash(state);
+                mask.unpacked().hash(state);
+            }
             Value::Char(arr) => {
                 2u8.hash(state);
                 arr.hash(state);
@@ -886,6 +1395,10 @@ impl Hash for Value {
                 3u8.hash(state);
                 arr.hash(state);
             }
+            Value::Int(arr) => {
+                4u8.hash(state);
+                arr.hash(state);
+            }
         }
     }
 }
@@ -895,6 +1408,8 @@ impl fmt::Display for Value {
         match self {
             Value::Num(n) => n.fmt(f),
             Value::Byte(b) => b.fmt(f),
+            Value::Mask(m) => m.fmt(f),
+            Value::Int(i) => i.fmt(f),
             Value::Char(c) => c.fmt(f),
             Value::Func(func) => {
                 if let Some(val) = func.as_constant() {
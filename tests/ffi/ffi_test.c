@@ -0,0 +1,19 @@
+#include <string.h>
+
+int add(int a, int b) { return a + b; }
+
+long negate(long a) { return -a; }
+
+double mean(double a, double b) { return (a + b) / 2; }
+
+int str_len(const char *s) { return (int)strlen(s); }
+
+const char *greeting(void) { return "Hello, World!"; }
+
+void reverse(unsigned char *buf, int len) {
+    for (int i = 0; i < len / 2; i++) {
+        unsigned char tmp = buf[i];
+        buf[i] = buf[len - 1 - i];
+        buf[len - 1 - i] = tmp;
+    }
+}